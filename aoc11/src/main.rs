@@ -72,32 +72,35 @@ impl Robot {
             .unwrap_or(self.default_color)
     }
 
-    fn step(&mut self) {
+    fn step(&mut self) -> bool {
         self.machine.feed(match self.look() {
             Color::Black => 0,
             Color::White => 1,
         });
-        self.machine.step();
-        let color = match self.machine.last_output() {
-            0 => Color::Black,
-            1 => Color::White,
-            _ => unreachable!(),
+        let (color, turn) = match self.machine.run_for_outputs(2).reached() {
+            Some(outputs) => (outputs[0], outputs[1]),
+            None => return false,
         };
-        self.colors.insert(self.pos, color);
-        self.machine.step();
-        self.dir = match self.machine.last_output() {
+        self.colors.insert(
+            self.pos,
+            match color {
+                0 => Color::Black,
+                1 => Color::White,
+                _ => unreachable!(),
+            },
+        );
+        self.dir = match turn {
             0 => self.dir.left(),
             1 => self.dir.right(),
             _ => unreachable!(),
         };
         self.pos.0 += self.dir.v().0;
         self.pos.1 += self.dir.v().1;
+        true
     }
 
     fn run(&mut self) {
-        while !self.machine.halted() {
-            self.step();
-        }
+        while self.step() {}
     }
 
     fn colored_squares(&self) -> usize {
@@ -16,7 +16,7 @@ impl Momentum {
     }
 }
 
-fn step(axis: &mut Vec<Momentum>) {
+fn step(axis: &mut [Momentum]) {
     for i in 0..axis.len() {
         for j in 0..i {
             let (dvi, dvj) = match axis[i].pos.cmp(&axis[j].pos) {
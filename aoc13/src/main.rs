@@ -1,6 +1,6 @@
 use std::cmp::Ordering;
 
-use interpreter::Machine;
+use interpreter::{Machine, Patch};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Tile {
@@ -25,27 +25,20 @@ impl Game {
     }
 
     pub fn set_free_play(&mut self) {
-        self.machine.mem_set(0, 2);
-    }
-
-    fn step(&mut self) -> Option<i64> {
-        self.machine.step();
-        if self.machine.halted() {
-            None
-        } else {
-            Some(self.machine.last_output())
-        }
+        Patch::parse(include_str!("../free_play.patch"))
+            .and_then(|patch| patch.apply(&mut self.machine))
+            .unwrap();
     }
 
     fn input(&mut self, input: i64) -> Option<(u8, u8, Tile)> {
+        self.machine.set_mem_input(input);
         loop {
-            self.machine.set_mem_input(input);
-            let x = self.step()? as u8;
-            let y = self.step()? as u8;
+            let record = self.machine.run_for_outputs(3).reached()?;
+            let (x, y) = (record[0] as u8, record[1] as u8);
             if x == !0 && y == 0 {
-                self.score = self.step()? as _;
+                self.score = record[2] as _;
             } else {
-                let tile = match self.step()? {
+                let tile = match record[2] {
                     0 => Tile::Empty,
                     1 => Tile::Wall,
                     2 => Tile::Block,
@@ -1,24 +1,22 @@
-use interpreter::{parse_ascii_program, Machine};
+use interpreter::{parse_ascii_program, Machine, Patch, SymbolicMachine, DAY2_OPCODES};
 
 fn main() {
     let instructions = parse_ascii_program(include_str!("../input.txt"));
     let mut machine = Machine::new(&instructions);
-    machine.mem_set(1, 12);
-    machine.mem_set(2, 2);
+    machine.restrict_opcodes(DAY2_OPCODES);
+    Patch::parse(include_str!("../alarm.patch"))
+        .and_then(|patch| patch.apply(&mut machine))
+        .unwrap();
     machine.eval();
 
     println!("part 1: {}", machine.mem_get(0));
 
-    for a in 0..=99 {
-        for b in 0..=99 {
-            let mut machine = Machine::new(&instructions);
-            machine.mem_set(1, a);
-            machine.mem_set(2, b);
-            machine.eval();
-            if machine.mem_get(0) == 19_690_720 {
-                println!("part 2: {}", 100 * machine.mem_get(1) + machine.mem_get(2));
-                return;
-            }
-        }
-    }
+    let mut machine = SymbolicMachine::new(&instructions);
+    machine.add_variable(1, "noun", 0..=99);
+    machine.add_variable(2, "verb", 0..=99);
+    let solution = machine
+        .solve_mem(0, 19_690_720)
+        .unwrap()
+        .expect("no noun and verb produce 19690720");
+    println!("part 2: {}", 100 * solution[0] + solution[1]);
 }
@@ -5,14 +5,16 @@ fn walk_wires<'a>(
 ) -> impl Iterator<Item = impl Iterator<Item = (i32, i32)> + 'a> + 'a {
     input.lines().take(2).map(|wire| {
         wire.split(',').flat_map(|item| {
-            std::iter::repeat(match item.chars().next().unwrap() {
-                'L' => (-1, 0),
-                'R' => (1, 0),
-                'D' => (0, -1),
-                'U' => (0, 1),
-                _ => panic!("bad input"),
-            })
-            .take(item[1..].parse().unwrap())
+            std::iter::repeat_n(
+                match item.chars().next().unwrap() {
+                    'L' => (-1, 0),
+                    'R' => (1, 0),
+                    'D' => (0, -1),
+                    'U' => (0, 1),
+                    _ => panic!("bad input"),
+                },
+                item[1..].parse().unwrap(),
+            )
         })
     })
 }
@@ -18,7 +18,7 @@ fn check_part1(num: u32) -> bool {
             Ordering::Less => Some((cur, good)),
             Ordering::Greater => None,
         })
-        .map_or(false, |(_, good)| good)
+        .is_some_and(|(_, good)| good)
 }
 
 fn check_part2(num: u32) -> bool {
@@ -28,7 +28,7 @@ fn check_part2(num: u32) -> bool {
             Ordering::Less => Some((0, cur, ok || n == 1)),
             Ordering::Greater => None,
         })
-        .map_or(false, |(n, _, ok)| ok || n == 1)
+        .is_some_and(|(n, _, ok)| ok || n == 1)
 }
 
 fn complexity<F: Fn(u32) -> bool>(start: u32, end: u32, func: F) -> usize {
@@ -1,7 +1,7 @@
 use interpreter::Machine;
 
 fn eval(code: &str, input: i64) -> Vec<i64> {
-    let mut machine = Machine::from_ascii_program(&code);
+    let mut machine = Machine::from_ascii_program(code);
     machine.feed(input);
     machine.eval_multi()
 }
@@ -1,8 +1,12 @@
-use interpreter::{parse_ascii_program, Machine};
+use interpreter::{default_threads, parallel_max_by_key, parse_ascii_program, Machine};
 use itertools::Itertools;
 
-fn try_permutations<F: FnMut(Vec<i64>) -> i64>(func: F) -> i64 {
-    (0..5).permutations(5).map(func).max().unwrap()
+fn try_permutations<F: Fn(Vec<i64>) -> i64 + Sync>(func: F) -> i64 {
+    parallel_max_by_key((0..5).permutations(5), default_threads(), |x| {
+        func(x.clone())
+    })
+    .unwrap()
+    .1
 }
 
 fn find_max_amplification(code: &[i64]) -> i64 {
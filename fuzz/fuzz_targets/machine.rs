@@ -0,0 +1,35 @@
+#![no_main]
+use interpreter::{decode_program, Machine};
+use libfuzzer_sys::fuzz_target;
+
+const MEMORY_LIMIT: usize = 4096;
+const INSTRUCTION_LIMIT: u64 = 10_000;
+
+fn cells(data: &[u8]) -> Vec<i64> {
+    // every byte pair becomes a cell: the first byte selects the opcode
+    // and modes, the second one a small operand
+    data.chunks(2)
+        .map(|chunk| match *chunk {
+            [a, b] if a % 2 == 0 => i64::from(a / 2 % 10) + 100 * i64::from(b % 27),
+            [_, b] => i64::from(b as i8),
+            [a] => i64::from(a),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+fuzz_target!(|data: &[u8]| {
+    // exercise the binary decoder with the raw input as well
+    let _ = decode_program(data);
+
+    let mut machine = Machine::new(&cells(data));
+    machine.set_memory_limit(MEMORY_LIMIT);
+    machine.set_mem_input(1);
+    let snapshot = machine.clone();
+
+    let a = machine.try_run_until(|m| m.instruction_count() >= INSTRUCTION_LIMIT);
+    let mut copy = snapshot;
+    let b = copy.try_run_until(|m| m.instruction_count() >= INSTRUCTION_LIMIT);
+    assert_eq!(a, b);
+    assert_eq!(machine.mem(), copy.mem());
+});
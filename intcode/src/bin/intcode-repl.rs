@@ -0,0 +1,302 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use interpreter::{
+    assemble, decode_program, disassemble_instruction, encode_program, format_ascii_program,
+    is_binary_program, parse_numbers, CrashDump, Error, Machine,
+};
+
+const HELP: &str = "commands:
+  <instruction>         assemble, store at ip and execute (eg: add #1, #2, 10)
+  <n,...>               store raw cells at ip and execute them
+  regs                  show the registers
+  mem [addr [len]]      show memory
+  poke <addr> <n,...>   write to memory
+  feed <n,...>          queue input
+  step [n]              execute n instructions at ip
+  run [n]               run until the machine halts, at most n instructions
+  jump <addr>           move the instruction pointer
+  dis [addr [count]]    disassemble instructions
+  load <path>           load a text or binary program or a crash dump
+  save <path>           save memory as a program (binary if ending in .icb)
+  dump <path>           write a crash dump of the machine
+  trace                 show the recently executed instructions
+  reset                 reset to an empty machine
+  help                  show this help
+  quit                  leave the repl";
+
+fn parse_values(s: &str) -> Result<Vec<i64>, String> {
+    parse_numbers(s).map_err(|err| err.to_string())
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: Option<&str>, default: T) -> Result<T, String> {
+    match arg {
+        Some(arg) => arg
+            .parse()
+            .map_err(|_| format!("invalid argument '{}'", arg)),
+        None => Ok(default),
+    }
+}
+
+/// The number of instructions and I/O events kept for dumps and traces.
+const HISTORY: usize = 64;
+
+/// The default number of instructions `run` executes before stopping.
+const RUN_LIMIT: u64 = 10_000_000;
+
+/// The first line of a crash dump file.
+const DUMP_HEADER: &str = "# intcode crash dump";
+
+fn new_machine(program: &[i64]) -> Machine {
+    let mut machine = Machine::new(program);
+    machine.set_history_limit(HISTORY);
+    machine
+}
+
+fn load(path: &str) -> Result<Machine, String> {
+    let data = fs::read(path).map_err(|err| format!("could not read {}: {}", path, err))?;
+    if data.starts_with(DUMP_HEADER.as_bytes()) {
+        let dump = CrashDump::parse(&String::from_utf8_lossy(&data))
+            .map_err(|err| format!("{}: {}", path, err))?;
+        if let Some(error) = dump.error() {
+            println!("loaded crash dump: {}", error);
+        }
+        let mut machine = dump.to_machine();
+        machine.set_history_limit(HISTORY);
+        return Ok(machine);
+    }
+    let program = if is_binary_program(&data) {
+        decode_program(&data).map_err(|err| err.to_string())?
+    } else {
+        parse_values(&String::from_utf8_lossy(&data))?
+    };
+    Ok(new_machine(&program))
+}
+
+struct Repl {
+    machine: Machine,
+    error: Option<Error>,
+}
+
+impl Repl {
+    /// Remembers an error of the machine for crash dumps.
+    fn fail(&mut self, err: Error) -> String {
+        let rv = err.to_string();
+        self.error = Some(err);
+        rv
+    }
+
+    fn execute(&mut self, count: usize) -> Result<(), String> {
+        for _ in 0..count {
+            let (instr, _) = disassemble_instruction(self.machine.mem(), self.machine.ip());
+            println!("{:>5}: {}", self.machine.ip(), instr);
+            if self
+                .machine
+                .try_step_instruction()
+                .map_err(|x| self.fail(x))?
+            {
+                if self.machine.halted() {
+                    println!("halted");
+                    break;
+                }
+                println!("output: {}", self.machine.last_output());
+            }
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, limit: u64) -> Result<(), String> {
+        for _ in 0..limit {
+            if self.machine.halted() {
+                println!("halted");
+                return Ok(());
+            }
+            if self
+                .machine
+                .try_step_instruction()
+                .map_err(|x| self.fail(x))?
+                && !self.machine.halted()
+            {
+                println!("output: {}", self.machine.last_output());
+            }
+        }
+        if self.machine.halted() {
+            println!("halted");
+        } else {
+            println!("stopped after {} instructions", limit);
+        }
+        Ok(())
+    }
+
+    fn store(&mut self, addr: usize, cells: &[i64]) -> Result<(), String> {
+        for (off, &cell) in cells.iter().enumerate() {
+            let target = addr
+                .checked_add(off)
+                .ok_or_else(|| format!("address {}+{} overflows", addr, off))?;
+            self.machine
+                .try_mem_set(target, cell)
+                .map_err(|_| format!("address {} is out of range", target))?;
+        }
+        Ok(())
+    }
+
+    fn store_and_execute(&mut self, cells: &[i64]) -> Result<(), String> {
+        let ip = self.machine.ip();
+        self.store(ip, cells)?;
+        self.machine.set_ip(ip);
+        self.execute(1)
+    }
+
+    fn show_regs(&self) {
+        println!("ip: {}", self.machine.ip());
+        println!("relative base: {}", self.machine.relative_base());
+        println!("halted: {}", self.machine.halted());
+        println!("last output: {}", self.machine.last_output());
+        println!("pending inputs: {:?}", self.machine.pending_inputs());
+        println!("instructions: {}", self.machine.instruction_count());
+        println!("memory size: {}", self.machine.mem().len());
+    }
+
+    fn show_trace(&self) {
+        for &(addr, ref cells) in self.machine.crash_dump(None).trace() {
+            let (instr, _) = disassemble_instruction(cells, 0);
+            println!("{:>5}: {}", addr, instr);
+        }
+    }
+
+    fn show_mem(&self, addr: usize, len: usize) {
+        for (idx, chunk) in self.machine.mem()[addr.min(self.machine.mem().len())..]
+            .iter()
+            .take(len)
+            .collect::<Vec<_>>()
+            .chunks(8)
+            .enumerate()
+        {
+            let values: Vec<_> = chunk.iter().map(|x| format!("{:>8}", x)).collect();
+            println!("{:>5}: {}", addr + idx * 8, values.join(" "));
+        }
+    }
+
+    fn disassemble(&self, mut addr: usize, count: usize) {
+        for _ in 0..count {
+            let (instr, len) = disassemble_instruction(self.machine.mem(), addr);
+            let marker = if addr == self.machine.ip() { ">" } else { " " };
+            println!("{}{:>5}: {}", marker, addr, instr);
+            addr = match addr.checked_add(len) {
+                Some(addr) => addr,
+                None => break,
+            };
+        }
+    }
+
+    fn handle(&mut self, line: &str) -> Result<bool, String> {
+        let mut parts = line.split_whitespace();
+        let cmd = match parts.next() {
+            Some(cmd) => cmd,
+            None => return Ok(true),
+        };
+        let rest = line[cmd.len()..].trim();
+        match cmd {
+            "help" => println!("{}", HELP),
+            "quit" | "exit" => return Ok(false),
+            "regs" => self.show_regs(),
+            "mem" => {
+                let addr = parse_arg(parts.next(), 0)?;
+                let len = parse_arg(parts.next(), 32)?;
+                self.show_mem(addr, len);
+            }
+            "poke" => {
+                let addr = parse_arg(parts.next(), 0)?;
+                let values = parse_values(parts.collect::<Vec<_>>().join(" ").as_str())?;
+                self.store(addr, &values)?;
+            }
+            "feed" => {
+                for value in parse_values(rest)? {
+                    self.machine.feed(value);
+                }
+            }
+            "step" => {
+                let count = parse_arg(parts.next(), 1)?;
+                self.execute(count)?;
+            }
+            "run" => {
+                let limit = parse_arg(parts.next(), RUN_LIMIT)?;
+                self.run(limit)?;
+            }
+            "jump" => {
+                let addr = parse_arg(parts.next(), 0)?;
+                self.machine.set_ip(addr);
+            }
+            "dis" => {
+                let addr = parse_arg(parts.next(), self.machine.ip())?;
+                let count = parse_arg(parts.next(), 10)?;
+                self.disassemble(addr, count);
+            }
+            "load" => {
+                self.machine = load(rest)?;
+                self.error = None;
+            }
+            "save" => {
+                let data = if rest.ends_with(".icb") {
+                    encode_program(self.machine.mem())
+                } else {
+                    (format_ascii_program(self.machine.mem()) + "\n").into_bytes()
+                };
+                fs::write(rest, data)
+                    .map_err(|err| format!("could not write {}: {}", rest, err))?;
+            }
+            "dump" => fs::write(
+                rest,
+                self.machine.crash_dump(self.error.as_ref()).to_string(),
+            )
+            .map_err(|err| format!("could not write {}: {}", rest, err))?,
+            "trace" => self.show_trace(),
+            "reset" => {
+                self.machine = new_machine(&[]);
+                self.error = None;
+            }
+            _ if cmd.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                self.store_and_execute(&parse_values(line)?)?;
+            }
+            _ => {
+                let cells = assemble(line).map_err(|err| err.message().to_string())?;
+                self.store_and_execute(&cells)?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn main() {
+    let mut repl = Repl {
+        machine: new_machine(&[]),
+        error: None,
+    };
+    if let Some(path) = env::args().nth(1) {
+        match load(&path) {
+            Ok(machine) => repl.machine = machine,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    println!("intcode repl, type help for a list of commands");
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!(">>> ");
+        io::stdout().flush().ok();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        match repl.handle(line.trim()) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => println!("error: {}", err),
+        }
+    }
+}
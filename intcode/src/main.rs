@@ -0,0 +1,339 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use interpreter::{
+    decode_program, encode_program, format_ascii_program, is_binary_program, parse_numbers, Error,
+    Machine, Patch, Transcript, TranscriptError,
+};
+
+const USAGE: &str = "usage: intcode [options] <program>
+
+options:
+  -i, --input <n,...>       feed the given numbers as input
+      --input-file <path>   feed the numbers from the given file as input
+      --stdin               read input from stdin line by line whenever
+                            the program waits for it
+      --ascii-input         treat input files and stdin as ASCII text
+      --set <addr>=<value>  patch a memory cell before running
+      --patch <path>        apply a patch file before running
+      --peek <addr>         print a memory cell after running
+      --record <path>       write a transcript of all inputs and outputs
+      --replay <path>       feed the inputs of a transcript and check that
+                            the outputs match
+      --budget <n>          fail after executing n instructions
+      --dump <path>         write a crash dump if the run fails
+      --convert <path>      write the patched program to a file and exit,
+                            as binary if the path ends in .icb
+      --ascii               print outputs as ASCII text
+      --stats               report instruction counts on stderr
+  -h, --help                show this help";
+
+/// The number of instructions and I/O events kept for crash dumps.
+const DUMP_HISTORY: usize = 64;
+
+enum InputSource {
+    Values(Vec<i64>),
+    File(String),
+    Stdin,
+}
+
+#[derive(Default)]
+struct Options {
+    program: Option<String>,
+    inputs: Vec<InputSource>,
+    patches: Vec<(usize, i64)>,
+    patch_files: Vec<String>,
+    peeks: Vec<usize>,
+    record: Option<String>,
+    replay: Option<String>,
+    budget: Option<u64>,
+    dump: Option<String>,
+    convert: Option<String>,
+    ascii_input: bool,
+    ascii: bool,
+    stats: bool,
+}
+
+fn parse_patch(s: &str) -> Result<(usize, i64), String> {
+    let mut iter = s.splitn(2, '=');
+    let addr = iter.next().unwrap_or("").trim();
+    let value = iter
+        .next()
+        .ok_or_else(|| format!("invalid patch '{}'", s))?;
+    Ok((
+        addr.parse()
+            .map_err(|_| format!("invalid address '{}'", addr))?,
+        value
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid value '{}'", value))?,
+    ))
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut opts = Options::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+        match arg.as_str() {
+            "-i" | "--input" => opts.inputs.push(InputSource::Values(
+                parse_numbers(&value(&arg)?).map_err(|err| err.to_string())?,
+            )),
+            "--input-file" => opts.inputs.push(InputSource::File(value(&arg)?)),
+            "--stdin" => opts.inputs.push(InputSource::Stdin),
+            "--ascii-input" => opts.ascii_input = true,
+            "--set" => opts.patches.push(parse_patch(&value(&arg)?)?),
+            "--patch" => opts.patch_files.push(value(&arg)?),
+            "--peek" => {
+                let addr = value(&arg)?;
+                opts.peeks.push(
+                    addr.parse()
+                        .map_err(|_| format!("invalid address '{}'", addr))?,
+                );
+            }
+            "--record" => opts.record = Some(value(&arg)?),
+            "--replay" => opts.replay = Some(value(&arg)?),
+            "--budget" => {
+                let budget = value(&arg)?;
+                opts.budget = Some(
+                    budget
+                        .parse()
+                        .map_err(|_| format!("invalid budget '{}'", budget))?,
+                );
+            }
+            "--dump" => opts.dump = Some(value(&arg)?),
+            "--convert" => opts.convert = Some(value(&arg)?),
+            "--ascii" => opts.ascii = true,
+            "--stats" => opts.stats = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if opts.program.is_none() => opts.program = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    Ok(opts)
+}
+
+fn parse_input(contents: &str, ascii: bool) -> Result<Vec<i64>, String> {
+    if ascii {
+        Ok(contents.bytes().map(i64::from).collect())
+    } else {
+        parse_numbers(contents).map_err(|err| err.to_string())
+    }
+}
+
+/// Reads the inputs given on the command line and in files.
+///
+/// Stdin is not read here but whenever the program waits for input.
+fn read_inputs(opts: &Options) -> Result<Vec<i64>, String> {
+    let mut rv = Vec::new();
+    for source in &opts.inputs {
+        match *source {
+            InputSource::Values(ref values) => rv.extend_from_slice(values),
+            InputSource::File(ref path) => {
+                let contents = fs::read_to_string(path)
+                    .map_err(|err| format!("could not read {}: {}", path, err))?;
+                rv.extend(parse_input(&contents, opts.ascii_input)?);
+            }
+            InputSource::Stdin => {}
+        }
+    }
+    Ok(rv)
+}
+
+/// Feeds the next line of stdin, returns false at the end of stdin.
+fn feed_line(machine: &mut Machine, stdin: &mut impl BufRead, ascii: bool) -> Result<bool, String> {
+    let mut line = String::new();
+    if stdin
+        .read_line(&mut line)
+        .map_err(|err| format!("could not read stdin: {}", err))?
+        == 0
+    {
+        return Ok(false);
+    }
+    let contents = if ascii { &line[..] } else { line.trim() };
+    for value in parse_input(contents, ascii)? {
+        machine.feed(value);
+    }
+    Ok(true)
+}
+
+fn write_output(out: &mut impl Write, value: i64, ascii: bool) -> io::Result<()> {
+    if ascii && (0..128).contains(&value) {
+        out.write_all(&[value as u8])
+    } else {
+        writeln!(out, "{}", value)
+    }
+}
+
+fn load_program(path: &str) -> Result<Vec<i64>, String> {
+    let data = fs::read(path).map_err(|err| format!("could not read {}: {}", path, err))?;
+    if is_binary_program(&data) {
+        decode_program(&data).map_err(|err| format!("{}: {}", path, err))
+    } else {
+        parse_numbers(&String::from_utf8_lossy(&data)).map_err(|err| format!("{}: {}", path, err))
+    }
+}
+
+fn save_program(path: &str, program: &[i64]) -> Result<(), String> {
+    let data = if path.ends_with(".icb") {
+        encode_program(program)
+    } else {
+        (format_ascii_program(program) + "\n").into_bytes()
+    };
+    fs::write(path, data).map_err(|err| format!("could not write {}: {}", path, err))
+}
+
+fn run(opts: &Options, path: &str) -> Result<bool, String> {
+    let mut machine = Machine::new(&load_program(path)?);
+    for &(addr, value) in &opts.patches {
+        machine
+            .try_mem_set(addr, value)
+            .map_err(|_| format!("--set {}={}: address out of range", addr, value))?;
+    }
+    for path in &opts.patch_files {
+        let source =
+            fs::read_to_string(path).map_err(|err| format!("could not read {}: {}", path, err))?;
+        Patch::parse(&source)
+            .and_then(|patch| patch.apply(&mut machine))
+            .map_err(|err| format!("{}: {}", path, err))?;
+    }
+    if let Some(ref out) = opts.convert {
+        save_program(out, machine.mem())?;
+        return Ok(true);
+    }
+    if let Some(budget) = opts.budget {
+        machine.set_instruction_budget(budget);
+    }
+    if opts.dump.is_some() {
+        machine.set_history_limit(DUMP_HISTORY);
+    }
+    if let Some(ref path) = opts.replay {
+        return replay(&mut machine, opts, path);
+    }
+    if opts.record.is_some() {
+        machine.start_recording();
+    }
+    for value in read_inputs(opts)? {
+        machine.feed(value);
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    let mut interactive = opts.inputs.iter().any(|x| matches!(*x, InputSource::Stdin));
+    let mut outputs = 0;
+    let result = loop {
+        if interactive && machine.needs_input() {
+            out.flush().ok();
+            interactive = feed_line(&mut machine, &mut stdin, opts.ascii_input)?;
+            continue;
+        }
+        match machine.try_step_instruction() {
+            Ok(false) => {}
+            Ok(true) if machine.halted() => break Ok(()),
+            Ok(true) => {
+                outputs += 1;
+                write_output(&mut out, machine.last_output(), opts.ascii)
+                    .map_err(|err| format!("could not write output: {}", err))?;
+            }
+            Err(err) => break Err(err),
+        }
+    };
+    for &addr in &opts.peeks {
+        writeln!(out, "{}={}", addr, machine.mem_get(addr))
+            .map_err(|err| format!("could not write output: {}", err))?;
+    }
+    out.flush().ok();
+
+    if let (Some(path), Some(transcript)) = (opts.record.as_ref(), machine.transcript()) {
+        fs::write(path, transcript.to_string())
+            .map_err(|err| format!("could not write {}: {}", path, err))?;
+    }
+
+    if opts.stats {
+        eprintln!("instructions: {}", machine.instruction_count());
+        eprintln!("outputs: {}", outputs);
+        eprintln!("ip: {}", machine.ip());
+    }
+
+    match result {
+        Ok(()) => {
+            if opts.stats {
+                eprintln!("status: halted");
+            }
+            Ok(true)
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            write_dump(&machine, opts, &err)?;
+            Ok(false)
+        }
+    }
+}
+
+fn write_dump(machine: &Machine, opts: &Options, err: &Error) -> Result<(), String> {
+    if let Some(ref path) = opts.dump {
+        fs::write(path, machine.crash_dump(Some(err)).to_string())
+            .map_err(|err| format!("could not write {}: {}", path, err))?;
+        eprintln!("crash dump written to {}", path);
+    }
+    Ok(())
+}
+
+fn replay(machine: &mut Machine, opts: &Options, path: &str) -> Result<bool, String> {
+    let source =
+        fs::read_to_string(path).map_err(|err| format!("could not read {}: {}", path, err))?;
+    let transcript = Transcript::parse(&source).map_err(|err| format!("{}: {}", path, err))?;
+    match transcript.replay(machine) {
+        Ok(()) => {}
+        Err(TranscriptError::Machine(err)) => {
+            eprintln!("error: replay failed: {}", err);
+            write_dump(machine, opts, &err)?;
+            return Ok(false);
+        }
+        Err(err) => {
+            eprintln!("error: replay diverged: {}", err);
+            return Ok(false);
+        }
+    }
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for value in transcript.outputs() {
+        write_output(&mut out, value, opts.ascii)
+            .map_err(|err| format!("could not write output: {}", err))?;
+    }
+    Ok(true)
+}
+
+fn main() {
+    let opts = match parse_args() {
+        Ok(Options { program: None, .. }) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+        Ok(opts) => opts,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    match run(&opts, opts.program.as_ref().unwrap()) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(2);
+        }
+    }
+}
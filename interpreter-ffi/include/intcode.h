@@ -0,0 +1,85 @@
+/* C API for the Intcode machine, generated from src/lib.rs. */
+
+#ifndef INTCODE_H
+#define INTCODE_H
+
+/* Do not edit, run INTCODE_UPDATE_HEADER=1 cargo test -p interpreter-ffi instead. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// An instruction produced an output.
+#define INTCODE_OUTPUT 1
+
+// The machine halted.
+#define INTCODE_HALTED 2
+
+// The machine needs input before it can continue.
+#define INTCODE_NEEDS_INPUT 3
+
+// The step budget ran out, calling again continues the run.
+#define INTCODE_BUDGET_EXCEEDED 4
+
+// The call succeeded.
+#define INTCODE_OK 0
+
+// The machine failed, see `intcode_error_message`.
+#define INTCODE_ERROR -1
+
+// An argument was invalid.
+#define INTCODE_INVALID_ARGUMENT -2
+
+// A machine together with the state the C API needs.
+typedef struct IntcodeMachine IntcodeMachine;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a machine from `len` cells at `program`.
+IntcodeMachine *intcode_new(const int64_t *program, size_t len);
+
+// Creates an independent copy of a machine.
+IntcodeMachine *intcode_snapshot(const IntcodeMachine *machine);
+
+// Releases a machine.  Passing null is allowed.
+void intcode_free(IntcodeMachine *machine);
+
+// Limits the addresses the machine can access.
+int32_t intcode_set_memory_limit(IntcodeMachine *machine, size_t limit);
+
+// Queues an input value.
+int32_t intcode_feed(IntcodeMachine *machine, int64_t value);
+
+// Runs until the next output, a halt, missing input or an error.
+int32_t intcode_step(IntcodeMachine *machine, uint64_t max_steps);
+
+// Runs until the machine halts, needs input or fails.
+int32_t intcode_run(IntcodeMachine *machine, uint64_t max_steps);
+
+// Takes the oldest queued output.
+int32_t intcode_read_output(IntcodeMachine *machine, int64_t *out);
+
+// Returns the number of queued outputs.
+size_t intcode_pending_outputs(const IntcodeMachine *machine);
+
+// Reads a memory cell into `out`.
+int32_t intcode_mem_get(const IntcodeMachine *machine, size_t addr, int64_t *out);
+
+// Writes a memory cell.
+int32_t intcode_mem_set(IntcodeMachine *machine, size_t addr, int64_t value);
+
+// Returns the size of the memory in cells.
+size_t intcode_mem_len(const IntcodeMachine *machine);
+
+// Returns the instruction pointer.
+size_t intcode_ip(const IntcodeMachine *machine);
+
+// Returns the message of the error reported by the last call or null.
+const char *intcode_error_message(const IntcodeMachine *machine);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* INTCODE_H */
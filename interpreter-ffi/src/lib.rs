@@ -0,0 +1,314 @@
+//! A C API for the Intcode machine.
+//!
+//! The declarations in `include/intcode.h` are generated from this file
+//! with cbindgen, see `tests/test_c.rs`.  All functions take the
+//! machine as an opaque pointer created by `intcode_new` or
+//! `intcode_snapshot` which has to be released with `intcode_free`.
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
+use std::slice;
+
+use interpreter::Machine;
+
+/// An instruction produced an output.
+pub const INTCODE_OUTPUT: i32 = 1;
+/// The machine halted.
+pub const INTCODE_HALTED: i32 = 2;
+/// The machine needs input before it can continue.
+pub const INTCODE_NEEDS_INPUT: i32 = 3;
+/// The step budget ran out, calling again continues the run.
+pub const INTCODE_BUDGET_EXCEEDED: i32 = 4;
+/// The call succeeded.
+pub const INTCODE_OK: i32 = 0;
+/// The machine failed, see `intcode_error_message`.
+pub const INTCODE_ERROR: i32 = -1;
+/// An argument was invalid.
+pub const INTCODE_INVALID_ARGUMENT: i32 = -2;
+
+/// A machine together with the state the C API needs.
+#[derive(Clone)]
+pub struct IntcodeMachine {
+    machine: Machine,
+    outputs: VecDeque<i64>,
+    error: Option<CString>,
+}
+
+impl IntcodeMachine {
+    /// Runs until the next event, executing at most `steps` instructions.
+    fn run_to_event(&mut self, steps: &mut u64) -> i32 {
+        loop {
+            if self.machine.halted() {
+                return INTCODE_HALTED;
+            }
+            if self.machine.needs_input() {
+                return INTCODE_NEEDS_INPUT;
+            }
+            if *steps == 0 {
+                return INTCODE_BUDGET_EXCEEDED;
+            }
+            *steps -= 1;
+            match self.machine.try_step_instruction() {
+                Ok(false) => {}
+                Ok(true) if self.machine.halted() => return INTCODE_HALTED,
+                Ok(true) => {
+                    self.outputs.push_back(self.machine.last_output());
+                    return INTCODE_OUTPUT;
+                }
+                Err(err) => {
+                    self.error = CString::new(err.to_string()).ok();
+                    return INTCODE_ERROR;
+                }
+            }
+        }
+    }
+}
+
+/// Borrows the machine for a call that can fail and forgets the error of
+/// the previous call.
+unsafe fn begin_call<'a>(machine: *mut IntcodeMachine) -> Option<&'a mut IntcodeMachine> {
+    let machine = machine.as_mut()?;
+    machine.error = None;
+    Some(machine)
+}
+
+/// Creates a machine from `len` cells at `program`.
+///
+/// Returns null if `program` is null and `len` is not zero.
+///
+/// # Safety
+///
+/// `program` must point to `len` readable cells.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_new(program: *const i64, len: usize) -> *mut IntcodeMachine {
+    let program = if len == 0 {
+        &[]
+    } else if program.is_null() {
+        return ptr::null_mut();
+    } else {
+        slice::from_raw_parts(program, len)
+    };
+    Box::into_raw(Box::new(IntcodeMachine {
+        machine: Machine::new(program),
+        outputs: VecDeque::new(),
+        error: None,
+    }))
+}
+
+/// Creates an independent copy of a machine.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_snapshot(machine: *const IntcodeMachine) -> *mut IntcodeMachine {
+    match machine.as_ref() {
+        Some(machine) => Box::into_raw(Box::new(machine.clone())),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Releases a machine.  Passing null is allowed.
+///
+/// # Safety
+///
+/// `machine` must be null or a valid machine pointer that is not used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_free(machine: *mut IntcodeMachine) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+/// Limits the addresses the machine can access.
+///
+/// Machines start out with the default limit of the interpreter.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_set_memory_limit(
+    machine: *mut IntcodeMachine,
+    limit: usize,
+) -> i32 {
+    let machine = match begin_call(machine) {
+        Some(machine) => machine,
+        None => return INTCODE_INVALID_ARGUMENT,
+    };
+    machine.machine.set_memory_limit(limit);
+    INTCODE_OK
+}
+
+/// Queues an input value.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_feed(machine: *mut IntcodeMachine, value: i64) -> i32 {
+    match begin_call(machine) {
+        Some(machine) => {
+            machine.machine.feed(value);
+            INTCODE_OK
+        }
+        None => INTCODE_INVALID_ARGUMENT,
+    }
+}
+
+/// Runs until the next output, a halt, missing input or an error.
+///
+/// Returns `INTCODE_OUTPUT`, `INTCODE_HALTED`, `INTCODE_NEEDS_INPUT`,
+/// `INTCODE_ERROR` or `INTCODE_BUDGET_EXCEEDED` if `max_steps`
+/// instructions ran without any of these.  Outputs are queued and read
+/// with `intcode_read_output`.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_step(machine: *mut IntcodeMachine, max_steps: u64) -> i32 {
+    match begin_call(machine) {
+        Some(machine) => {
+            let mut steps = max_steps;
+            machine.run_to_event(&mut steps)
+        }
+        None => INTCODE_INVALID_ARGUMENT,
+    }
+}
+
+/// Runs until the machine halts, needs input or fails.
+///
+/// At most `max_steps` instructions are executed in total, after that
+/// `INTCODE_BUDGET_EXCEEDED` is returned.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_run(machine: *mut IntcodeMachine, max_steps: u64) -> i32 {
+    let machine = match begin_call(machine) {
+        Some(machine) => machine,
+        None => return INTCODE_INVALID_ARGUMENT,
+    };
+    let mut steps = max_steps;
+    loop {
+        match machine.run_to_event(&mut steps) {
+            INTCODE_OUTPUT => {}
+            status => return status,
+        }
+    }
+}
+
+/// Takes the oldest queued output.
+///
+/// Returns `INTCODE_OK` and stores the value in `out` or
+/// `INTCODE_INVALID_ARGUMENT` if there is no output.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_read_output(machine: *mut IntcodeMachine, out: *mut i64) -> i32 {
+    match (begin_call(machine), out.is_null()) {
+        (Some(machine), false) => match machine.outputs.pop_front() {
+            Some(value) => {
+                *out = value;
+                INTCODE_OK
+            }
+            None => INTCODE_INVALID_ARGUMENT,
+        },
+        _ => INTCODE_INVALID_ARGUMENT,
+    }
+}
+
+/// Returns the number of queued outputs.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_pending_outputs(machine: *const IntcodeMachine) -> usize {
+    machine.as_ref().map_or(0, |x| x.outputs.len())
+}
+
+/// Reads a memory cell into `out`.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_mem_get(
+    machine: *const IntcodeMachine,
+    addr: usize,
+    out: *mut i64,
+) -> i32 {
+    match (machine.as_ref(), out.is_null()) {
+        (Some(machine), false) => match machine.machine.try_mem_get(addr) {
+            Ok(value) => {
+                *out = value;
+                INTCODE_OK
+            }
+            Err(_) => INTCODE_INVALID_ARGUMENT,
+        },
+        _ => INTCODE_INVALID_ARGUMENT,
+    }
+}
+
+/// Writes a memory cell.
+///
+/// Fails with `INTCODE_INVALID_ARGUMENT` if the address is outside the
+/// memory limit.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_mem_set(
+    machine: *mut IntcodeMachine,
+    addr: usize,
+    value: i64,
+) -> i32 {
+    match begin_call(machine).map(|x| x.machine.try_mem_set(addr, value)) {
+        Some(Ok(())) => INTCODE_OK,
+        _ => INTCODE_INVALID_ARGUMENT,
+    }
+}
+
+/// Returns the size of the memory in cells.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_mem_len(machine: *const IntcodeMachine) -> usize {
+    machine.as_ref().map_or(0, |x| x.machine.mem().len())
+}
+
+/// Returns the instruction pointer.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_ip(machine: *const IntcodeMachine) -> usize {
+    machine.as_ref().map_or(0, |x| x.machine.ip())
+}
+
+/// Returns the message of the error reported by the last call or null.
+///
+/// The string stays valid until the next call that changes the machine,
+/// every such call also clears the message.
+///
+/// # Safety
+///
+/// `machine` must be a valid machine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_error_message(machine: *const IntcodeMachine) -> *const c_char {
+    machine
+        .as_ref()
+        .and_then(|x| x.error.as_ref())
+        .map_or(ptr::null(), |x| x.as_ptr())
+}
@@ -0,0 +1,122 @@
+/* Drives the Intcode machine through the C API. */
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "intcode.h"
+
+static int failures = 0;
+
+#define CHECK(expr)                                                      \
+    do {                                                                 \
+        if (!(expr)) {                                                   \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,       \
+                    __LINE__, #expr);                                    \
+            failures++;                                                  \
+        }                                                                \
+    } while (0)
+
+static void test_io(void)
+{
+    /* compares the input with 8: outputs 999 below, 1000 equal, 1001 above */
+    static const int64_t program[] = {
+        3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31,
+        1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104,
+        999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99};
+    IntcodeMachine *machine =
+        intcode_new(program, sizeof(program) / sizeof(program[0]));
+    int64_t value = 0;
+
+    CHECK(machine != NULL);
+    CHECK(intcode_step(machine, 1000) == INTCODE_NEEDS_INPUT);
+    CHECK(intcode_feed(machine, 8) == INTCODE_OK);
+    CHECK(intcode_step(machine, 1000) == INTCODE_OUTPUT);
+    CHECK(intcode_pending_outputs(machine) == 1);
+    CHECK(intcode_read_output(machine, &value) == INTCODE_OK);
+    CHECK(value == 1000);
+    CHECK(intcode_read_output(machine, &value) == INTCODE_INVALID_ARGUMENT);
+    CHECK(intcode_step(machine, 1000) == INTCODE_HALTED);
+    intcode_free(machine);
+}
+
+static void test_memory_and_snapshot(void)
+{
+    static const int64_t program[] = {1, 0, 0, 0, 99};
+    IntcodeMachine *machine = intcode_new(program, 5);
+    IntcodeMachine *snapshot;
+    int64_t value = 0;
+
+    CHECK(intcode_mem_set(machine, 1, 4) == INTCODE_OK);
+    snapshot = intcode_snapshot(machine);
+    CHECK(intcode_run(machine, 1000) == INTCODE_HALTED);
+    CHECK(intcode_mem_get(machine, 0, &value) == INTCODE_OK);
+    CHECK(value == 100);
+
+    /* the snapshot is unaffected by running the original */
+    CHECK(intcode_ip(snapshot) == 0);
+    CHECK(intcode_mem_get(snapshot, 0, &value) == INTCODE_OK);
+    CHECK(value == 1);
+    CHECK(intcode_mem_set(snapshot, 2, 4) == INTCODE_OK);
+    CHECK(intcode_run(snapshot, 1000) == INTCODE_HALTED);
+    CHECK(intcode_mem_get(snapshot, 0, &value) == INTCODE_OK);
+    CHECK(value == 198);
+    CHECK(intcode_mem_len(snapshot) == 5);
+
+    intcode_free(snapshot);
+    intcode_free(machine);
+}
+
+static void test_errors(void)
+{
+    static const int64_t program[] = {1101, 1, 1, 5000, 42};
+    IntcodeMachine *machine = intcode_new(program, 5);
+
+    CHECK(intcode_new(NULL, 3) == NULL);
+    CHECK(intcode_feed(NULL, 1) == INTCODE_INVALID_ARGUMENT);
+    CHECK(intcode_error_message(machine) == NULL);
+    CHECK(intcode_mem_set(machine, SIZE_MAX, 1) == INTCODE_INVALID_ARGUMENT);
+    CHECK(intcode_set_memory_limit(machine, 4096) == INTCODE_OK);
+    CHECK(intcode_mem_set(machine, 4096, 1) == INTCODE_INVALID_ARGUMENT);
+    CHECK(intcode_run(machine, 1000) == INTCODE_ERROR);
+    CHECK(intcode_error_message(machine) != NULL);
+    CHECK(strcmp(intcode_error_message(machine), "invalid address at ip 0") == 0);
+
+    /* the next call clears the message */
+    CHECK(intcode_set_memory_limit(machine, 8192) == INTCODE_OK);
+    CHECK(intcode_error_message(machine) == NULL);
+    CHECK(intcode_run(machine, 1000) == INTCODE_ERROR);
+    CHECK(strcmp(intcode_error_message(machine), "invalid opcode 42 at ip 4") == 0);
+    CHECK(intcode_mem_set(machine, 4, 99) == INTCODE_OK);
+    CHECK(intcode_error_message(machine) == NULL);
+    CHECK(intcode_run(machine, 1000) == INTCODE_HALTED);
+    CHECK(intcode_error_message(machine) == NULL);
+    intcode_free(machine);
+    intcode_free(NULL);
+}
+
+static void test_budget(void)
+{
+    /* jumps back to itself forever */
+    static const int64_t program[] = {1105, 1, 0};
+    IntcodeMachine *machine = intcode_new(program, 3);
+
+    CHECK(intcode_step(machine, 10) == INTCODE_BUDGET_EXCEEDED);
+    CHECK(intcode_run(machine, 10) == INTCODE_BUDGET_EXCEEDED);
+    CHECK(intcode_ip(machine) == 0);
+    CHECK(intcode_error_message(machine) == NULL);
+    intcode_free(machine);
+}
+
+int main(void)
+{
+    test_io();
+    test_memory_and_snapshot();
+    test_errors();
+    test_budget();
+    if (failures) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    printf("all checks passed\n");
+    return 0;
+}
@@ -0,0 +1,67 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Renders the header from the exported items in `src/lib.rs`.
+fn generate_header() -> String {
+    let crate_dir = env!("CARGO_MANIFEST_DIR");
+    let config = cbindgen::Config::from_file(Path::new(crate_dir).join("cbindgen.toml")).unwrap();
+    let mut rv = Vec::new();
+    cbindgen::generate_with_config(crate_dir, config)
+        .unwrap()
+        .write(&mut rv);
+    String::from_utf8(rv).unwrap()
+}
+
+#[test]
+fn test_header_is_generated() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("include/intcode.h");
+    let header = generate_header();
+    if env::var_os("INTCODE_UPDATE_HEADER").is_some() {
+        fs::write(&path, &header).unwrap();
+    }
+    assert!(
+        fs::read_to_string(&path).unwrap() == header,
+        "include/intcode.h is out of date, rerun with INTCODE_UPDATE_HEADER=1"
+    );
+}
+
+#[test]
+fn test_c_harness() {
+    // cargo test builds the cdylib into the deps folder next to the test
+    // binary, the copy one level up is only refreshed by cargo build
+    let lib_dir: PathBuf = env::current_exe().unwrap().parent().unwrap().into();
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let binary = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("intcode-harness");
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".into());
+    let status = Command::new(&compiler)
+        .arg(manifest_dir.join("tests/harness.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-linterpreter_ffi")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-o")
+        .arg(&binary)
+        .status()
+        .unwrap_or_else(|err| panic!("could not run c compiler {}, set CC: {}", compiler, err));
+    assert!(status.success(), "compiling the c harness failed");
+
+    // cargo puts the stale copy on the library path which wins over rpath
+    let output = Command::new(&binary)
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "all checks passed\n"
+    );
+}
@@ -0,0 +1,428 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+
+use crate::instruction::decode;
+
+/// The mnemonics of the built-in opcodes with their opcode and arity.
+const MNEMONICS: &[(&str, i64, usize)] = &[
+    ("add", 1, 3),
+    ("mul", 2, 3),
+    ("in", 3, 1),
+    ("out", 4, 1),
+    ("jt", 5, 2),
+    ("jf", 6, 2),
+    ("lt", 7, 3),
+    ("eq", 8, 3),
+    ("arb", 9, 1),
+    ("hlt", 99, 0),
+];
+
+/// The column instructions start at in formatted assembly.
+const FORMAT_INDENT: usize = 8;
+
+/// The column trailing comments start at in formatted assembly.
+const FORMAT_COMMENT_COLUMN: usize = 32;
+
+/// An error produced by the assembler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    line: usize,
+    message: String,
+}
+
+impl AsmError {
+    fn new<S: Into<String>>(line: usize, message: S) -> AsmError {
+        AsmError {
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the one-based line number the error happened on.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for AsmError {}
+
+enum Value<'a> {
+    Number(i64),
+    Label(&'a str, i64),
+}
+
+struct Operand<'a> {
+    mode: i64,
+    value: Value<'a>,
+}
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            1 => write!(f, "#")?,
+            2 => write!(f, "@")?,
+            _ => {}
+        }
+        match self.value {
+            Value::Number(value) => write!(f, "{}", value),
+            Value::Label(name, 0) => write!(f, "{}", name),
+            Value::Label(name, offset) if offset > 0 => write!(f, "{}+{}", name, offset),
+            Value::Label(name, offset) => write!(f, "{}{}", name, offset),
+        }
+    }
+}
+
+pub(crate) fn is_label(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_value(s: &str, line: usize) -> Result<Value<'_>, AsmError> {
+    if let Ok(value) = s.parse() {
+        return Ok(Value::Number(value));
+    }
+    let (name, offset) = match s.find(['+', '-']) {
+        Some(idx) => {
+            let offset = s[idx..]
+                .trim_start_matches('+')
+                .parse()
+                .map_err(|_| AsmError::new(line, format!("invalid offset in '{}'", s)))?;
+            (s[..idx].trim(), offset)
+        }
+        None => (s, 0),
+    };
+    if !is_label(name) {
+        return Err(AsmError::new(line, format!("invalid operand '{}'", s)));
+    }
+    Ok(Value::Label(name, offset))
+}
+
+fn parse_operand(s: &str, line: usize) -> Result<Operand<'_>, AsmError> {
+    let (mode, rest) = if let Some(rest) = s.strip_prefix('#') {
+        (1, rest)
+    } else if let Some(rest) = s.strip_prefix('@') {
+        (2, rest)
+    } else {
+        (0, s)
+    };
+    Ok(Operand {
+        mode,
+        value: parse_value(rest.trim(), line)?,
+    })
+}
+
+/// Assembled code with label references that are not resolved yet.
+pub(crate) struct Assembly<'a> {
+    pub code: Vec<i64>,
+    pub labels: BTreeMap<&'a str, i64>,
+    /// The lines the labels are declared on.
+    pub label_lines: BTreeMap<&'a str, usize>,
+    /// The addresses of the instructions with their lines.
+    pub instructions: Vec<(usize, usize)>,
+    /// The cells referring to labels as address, label, offset and line.
+    pub fixups: Vec<(usize, &'a str, i64, usize)>,
+    /// The labels named in `global` directives.
+    pub globals: Vec<&'a str>,
+}
+
+impl Assembly<'_> {
+    /// Fills in the addresses of all referenced labels.
+    pub fn resolve(&mut self) -> Result<(), AsmError> {
+        for &(addr, name, offset, lineno) in &self.fixups {
+            let target = self
+                .labels
+                .get(name)
+                .ok_or_else(|| AsmError::new(lineno, format!("undefined label '{}'", name)))?;
+            self.code[addr] = label_offset(*target, name, offset, lineno)?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds an offset to the address of a label.
+pub(crate) fn label_offset(
+    target: i64,
+    name: &str,
+    offset: i64,
+    lineno: usize,
+) -> Result<i64, AsmError> {
+    target.checked_add(offset).ok_or_else(|| {
+        AsmError::new(
+            lineno,
+            format!("offset from label '{}' is out of range", name),
+        )
+    })
+}
+
+/// Assembles source code without resolving label references.
+pub(crate) fn assemble_unresolved(source: &str) -> Result<Assembly<'_>, AsmError> {
+    let mut labels = BTreeMap::new();
+    let mut label_lines = BTreeMap::new();
+    let mut instructions = Vec::new();
+    let mut fixups = Vec::new();
+    let mut globals = Vec::new();
+    let mut rv = Vec::new();
+
+    for (idx, line) in source.lines().enumerate() {
+        let lineno = idx + 1;
+        let mut line = line.split(';').next().unwrap_or("").trim();
+        while let Some(colon) = line.find(':') {
+            let label = line[..colon].trim();
+            if !is_label(label) {
+                return Err(AsmError::new(lineno, format!("invalid label '{}'", label)));
+            }
+            if labels.insert(label, rv.len() as i64).is_some() {
+                return Err(AsmError::new(
+                    lineno,
+                    format!("duplicate label '{}'", label),
+                ));
+            }
+            label_lines.insert(label, lineno);
+            line = line[colon + 1..].trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = match line.find(char::is_whitespace) {
+            Some(idx) => (&line[..idx], line[idx..].trim()),
+            None => (line, ""),
+        };
+        let operands = if rest.is_empty() {
+            vec![]
+        } else {
+            rest.split(',')
+                .map(|x| parse_operand(x.trim(), lineno))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mnemonic = mnemonic.to_ascii_lowercase();
+        if mnemonic == "data" {
+            for operand in operands {
+                if operand.mode != 0 {
+                    return Err(AsmError::new(lineno, "data cannot have parameter modes"));
+                }
+                if let Value::Label(name, offset) = operand.value {
+                    fixups.push((rv.len(), name, offset, lineno));
+                }
+                rv.push(match operand.value {
+                    Value::Number(value) => value,
+                    Value::Label(..) => 0,
+                });
+            }
+            continue;
+        }
+        if mnemonic == "global" {
+            for operand in operands {
+                match operand.value {
+                    Value::Label(name, 0) if operand.mode == 0 => globals.push((name, lineno)),
+                    _ => return Err(AsmError::new(lineno, "global expects label names")),
+                }
+            }
+            continue;
+        }
+
+        let &(_, opcode, arity) = MNEMONICS
+            .iter()
+            .find(|x| x.0 == mnemonic)
+            .ok_or_else(|| AsmError::new(lineno, format!("unknown mnemonic '{}'", mnemonic)))?;
+        if operands.len() != arity {
+            return Err(AsmError::new(
+                lineno,
+                format!(
+                    "{} takes {} operands, got {}",
+                    mnemonic,
+                    arity,
+                    operands.len()
+                ),
+            ));
+        }
+
+        instructions.push((rv.len(), lineno));
+        let mut instr = opcode;
+        let mut factor = 100;
+        for operand in &operands {
+            instr += operand.mode * factor;
+            factor *= 10;
+        }
+        rv.push(instr);
+        for operand in operands {
+            rv.push(match operand.value {
+                Value::Number(value) => value,
+                Value::Label(name, offset) => {
+                    fixups.push((rv.len(), name, offset, lineno));
+                    0
+                }
+            });
+        }
+    }
+
+    for &(name, lineno) in &globals {
+        if !labels.contains_key(name) {
+            return Err(AsmError::new(lineno, format!("undefined label '{}'", name)));
+        }
+    }
+
+    Ok(Assembly {
+        code: rv,
+        labels,
+        label_lines,
+        instructions,
+        fixups,
+        globals: globals.into_iter().map(|x| x.0).collect(),
+    })
+}
+
+/// Assembles Intcode assembly into a program.
+///
+/// Every line holds an optional `label:`, followed by either an
+/// instruction or a `data` directive.  Comments start with `;`.  Plain
+/// operands use position mode, `#` prefixes immediate and `@` relative
+/// operands.  Labels can be used wherever numbers are accepted, optionally
+/// with an offset like `label+1`:
+///
+/// ```text
+/// loop:   in 100
+///         out 100
+///         jt #1, #loop
+/// ```
+///
+/// The `global` directive names labels that are exported when the source
+/// is assembled into an `Object` and has no effect here.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut assembly = assemble_unresolved(source)?;
+    assembly.resolve()?;
+    Ok(assembly.code)
+}
+
+/// Formats assembly in a uniform layout.
+///
+/// Instructions are indented to a common column with lowercase mnemonics
+/// and operands separated by a comma and a space.  Short labels share a
+/// line with their instruction, longer ones get a line of their own.
+/// Trailing comments are aligned, comments on their own line keep their
+/// text and runs of blank lines collapse into one.  The source has to be
+/// valid but may refer to labels it does not define.
+pub fn format_assembly(source: &str) -> Result<String, AsmError> {
+    assemble_unresolved(source)?;
+
+    let mut rv = String::new();
+    let mut pending_blank = false;
+    for (idx, line) in source.lines().enumerate() {
+        let lineno = idx + 1;
+        let (code, comment) = match line.find(';') {
+            Some(idx) => (&line[..idx], Some(line[idx..].trim_end())),
+            None => (line, None),
+        };
+        let mut code = code.trim();
+        let mut labels = vec![];
+        while let Some(colon) = code.find(':') {
+            labels.push(code[..colon].trim());
+            code = code[colon + 1..].trim();
+        }
+
+        if labels.is_empty() && code.is_empty() {
+            match comment {
+                None => pending_blank = !rv.is_empty(),
+                Some(comment) => {
+                    if pending_blank {
+                        rv.push('\n');
+                        pending_blank = false;
+                    }
+                    if !line.starts_with(';') {
+                        rv.push_str(&" ".repeat(FORMAT_INDENT));
+                    }
+                    rv.push_str(comment);
+                    rv.push('\n');
+                }
+            }
+            continue;
+        }
+        if pending_blank {
+            rv.push('\n');
+            pending_blank = false;
+        }
+
+        let mut out = String::new();
+        for (idx, label) in labels.iter().enumerate() {
+            out.push_str(label);
+            out.push(':');
+            if idx + 1 < labels.len() || (!code.is_empty() && label.len() + 1 >= FORMAT_INDENT) {
+                rv.push_str(&out);
+                rv.push('\n');
+                out.clear();
+            }
+        }
+        if !code.is_empty() {
+            out.push_str(&" ".repeat(FORMAT_INDENT - out.len()));
+            let (mnemonic, rest) = match code.find(char::is_whitespace) {
+                Some(idx) => (&code[..idx], code[idx..].trim()),
+                None => (code, ""),
+            };
+            out.push_str(&mnemonic.to_ascii_lowercase());
+            if !rest.is_empty() {
+                let operands = rest
+                    .split(',')
+                    .map(|x| parse_operand(x.trim(), lineno).map(|x| x.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                out.push(' ');
+                out.push_str(&operands.join(", "));
+            }
+        }
+        if let Some(comment) = comment {
+            if out.len() < FORMAT_COMMENT_COLUMN {
+                out.push_str(&" ".repeat(FORMAT_COMMENT_COLUMN - out.len()));
+            } else {
+                out.push_str("  ");
+            }
+            out.push_str(comment);
+        }
+        rv.push_str(&out);
+        rv.push('\n');
+    }
+    Ok(rv)
+}
+
+/// Disassembles the instruction at the given address.
+///
+/// Returns the textual form of the instruction and the number of cells
+/// it occupies.  Cells that do not decode into a valid instruction are
+/// rendered as a `data` directive of length one.
+pub fn disassemble_instruction(mem: &[i64], addr: usize) -> (String, usize) {
+    match decode(mem, addr) {
+        Ok(instruction) => (instruction.to_string(), instruction.size()),
+        Err(_) => (format!("data {}", mem.get(addr).copied().unwrap_or(0)), 1),
+    }
+}
+
+/// Disassembles a whole program into a listing with one line per
+/// instruction, prefixed with its address.
+pub fn disassemble(mem: &[i64]) -> String {
+    let mut rv = String::new();
+    let mut addr = 0;
+    while addr < mem.len() {
+        let (instr, len) = disassemble_instruction(mem, addr);
+        rv.push_str(&format!("{:>5}: {}\n", addr, instr));
+        addr += len;
+    }
+    rv
+}
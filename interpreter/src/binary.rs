@@ -0,0 +1,190 @@
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+/// The magic bytes every binary program starts with.
+pub const BINARY_MAGIC: &[u8; 4] = b"\x7fICB";
+
+/// The version of the binary format written by this crate.
+pub const BINARY_VERSION: u8 = 1;
+
+/// Errors that can happen when reading binary programs.
+#[derive(Debug)]
+pub enum BinaryError {
+    /// The data does not start with the magic bytes.
+    BadMagic,
+    /// The format version is not supported.
+    UnsupportedVersion(u8),
+    /// The data ended prematurely.
+    Truncated,
+    /// A cell was encoded with too many bytes.
+    InvalidVarint,
+    /// The checksum of the cells does not match the header.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// Reading failed.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            BinaryError::BadMagic => write!(f, "not a binary intcode program"),
+            BinaryError::UnsupportedVersion(version) => {
+                write!(f, "unsupported binary format version {}", version)
+            }
+            BinaryError::Truncated => write!(f, "binary program is truncated"),
+            BinaryError::InvalidVarint => write!(f, "invalid varint in binary program"),
+            BinaryError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch (expected {:08x}, got {:08x})",
+                expected, actual
+            ),
+            #[cfg(feature = "std")]
+            BinaryError::Io(ref err) => write!(f, "could not read binary program: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for BinaryError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            BinaryError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for BinaryError {
+    fn from(err: io::Error) -> BinaryError {
+        BinaryError::Io(err)
+    }
+}
+
+/// Computes the CRC-32 (IEEE) checksum of the given bytes.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64, BinaryError> {
+    let mut rv = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos).ok_or(BinaryError::Truncated)?;
+        *pos += 1;
+        if shift == 63 && byte > 1 {
+            return Err(BinaryError::InvalidVarint);
+        }
+        rv |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(rv);
+        }
+    }
+    Err(BinaryError::InvalidVarint)
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// Encodes a program into the compact binary format.
+///
+/// The format starts with the magic bytes and a version byte, followed by
+/// the number of cells as varint, the CRC-32 of the cell data as four
+/// little endian bytes and finally the cells as zigzag encoded varints.
+pub fn encode_program(program: &[i64]) -> Vec<u8> {
+    let mut cells = Vec::with_capacity(program.len() * 2);
+    for &cell in program {
+        write_varint(&mut cells, zigzag(cell));
+    }
+
+    let mut rv = Vec::with_capacity(cells.len() + 16);
+    rv.extend_from_slice(BINARY_MAGIC);
+    rv.push(BINARY_VERSION);
+    write_varint(&mut rv, program.len() as u64);
+    rv.extend_from_slice(&crc32(&cells).to_le_bytes());
+    rv.extend_from_slice(&cells);
+    rv
+}
+
+/// Decodes a program from the compact binary format.
+pub fn decode_program(data: &[u8]) -> Result<Vec<i64>, BinaryError> {
+    if !is_binary_program(data) {
+        return Err(BinaryError::BadMagic);
+    }
+    let version = *data.get(4).ok_or(BinaryError::Truncated)?;
+    if version != BINARY_VERSION {
+        return Err(BinaryError::UnsupportedVersion(version));
+    }
+
+    let mut pos = 5;
+    let count = read_varint(data, &mut pos)?;
+    let checksum = data.get(pos..pos + 4).ok_or(BinaryError::Truncated)?;
+    let expected = u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+    pos += 4;
+
+    let actual = crc32(&data[pos..]);
+    if actual != expected {
+        return Err(BinaryError::ChecksumMismatch { expected, actual });
+    }
+
+    // every cell takes at least one byte which bounds the allocation
+    let mut rv = Vec::with_capacity((count as usize).min(data.len() - pos));
+    for _ in 0..count {
+        rv.push(unzigzag(read_varint(data, &mut pos)?));
+    }
+    if pos != data.len() {
+        return Err(BinaryError::InvalidVarint);
+    }
+    Ok(rv)
+}
+
+/// Returns true if the data looks like a binary program.
+pub fn is_binary_program(data: &[u8]) -> bool {
+    data.starts_with(BINARY_MAGIC)
+}
+
+/// Writes a program in the compact binary format.
+#[cfg(feature = "std")]
+pub fn write_binary_program<W: Write>(mut w: W, program: &[i64]) -> io::Result<()> {
+    w.write_all(&encode_program(program))
+}
+
+/// Reads a program in the compact binary format.
+#[cfg(feature = "std")]
+pub fn read_binary_program<R: Read>(mut r: R) -> Result<Vec<i64>, BinaryError> {
+    let mut data = Vec::new();
+    r.read_to_end(&mut data)?;
+    decode_program(&data)
+}
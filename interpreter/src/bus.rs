@@ -0,0 +1,146 @@
+#[cfg(not(feature = "std"))]
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use core::cell::{RefCell, RefMut};
+use core::fmt;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::cell::Cell;
+
+/// A peripheral that is mapped into the memory of a machine.
+///
+/// Reads and writes of mapped cells are routed through the device.  The
+/// offset passed is relative to the start of the mapped range.
+/// Instructions themselves are fetched from memory directly, only the
+/// cells they read and write go through devices.  By
+/// default a device behaves like plain memory so implementations only
+/// need to override what they care about.
+pub trait Device<C: Cell = i64>: Send {
+    /// Called when a mapped cell is read, `stored` is the value in memory.
+    fn read(&mut self, offset: usize, stored: &C) -> C {
+        let _ = offset;
+        stored.clone()
+    }
+
+    /// Called when a mapped cell is written and returns the value that
+    /// should end up in memory.
+    fn write(&mut self, offset: usize, value: C) -> C {
+        let _ = offset;
+        value
+    }
+}
+
+/// A handle to a device that is shared between the caller and machines.
+///
+/// With `std` devices are behind a mutex so that machines can be sent to
+/// other threads, without it they are behind a `RefCell`.
+#[cfg(feature = "std")]
+pub type DeviceHandle<D> = Arc<Mutex<D>>;
+
+/// A handle to a device that is shared between the caller and machines.
+#[cfg(not(feature = "std"))]
+pub type DeviceHandle<D> = Rc<RefCell<D>>;
+
+pub(crate) type SharedDevice<C> = DeviceHandle<dyn Device<C>>;
+
+/// A mapped device panicked during an earlier access or is already in use.
+#[derive(Debug)]
+pub(crate) struct Poisoned;
+
+#[cfg(feature = "std")]
+fn lock<C: Cell>(
+    device: &SharedDevice<C>,
+) -> Result<MutexGuard<'_, dyn Device<C> + 'static>, Poisoned> {
+    device.lock().map_err(|_| Poisoned)
+}
+
+#[cfg(not(feature = "std"))]
+fn lock<C: Cell>(
+    device: &SharedDevice<C>,
+) -> Result<RefMut<'_, dyn Device<C> + 'static>, Poisoned> {
+    device.try_borrow_mut().map_err(|_| Poisoned)
+}
+
+/// Routes memory accesses to mapped devices.
+pub(crate) struct Bus<C: Cell> {
+    mappings: Vec<(Range<usize>, SharedDevice<C>)>,
+}
+
+impl<C: Cell> Bus<C> {
+    pub fn map(&mut self, range: Range<usize>, device: SharedDevice<C>) {
+        assert!(range.start < range.end, "device range is empty");
+        assert!(
+            self.mappings
+                .iter()
+                .all(|(x, _)| x.end <= range.start || range.end <= x.start),
+            "device ranges overlap"
+        );
+        self.mappings.push((range, device));
+    }
+
+    pub fn unmap(&mut self, addr: usize) -> bool {
+        let len = self.mappings.len();
+        self.mappings.retain(|(range, _)| !range.contains(&addr));
+        self.mappings.len() != len
+    }
+
+    fn find(&self, addr: usize) -> Option<(usize, &SharedDevice<C>)> {
+        self.mappings
+            .iter()
+            .find(|(range, _)| range.contains(&addr))
+            .map(|(range, device)| (addr - range.start, device))
+    }
+
+    pub fn read(&self, addr: usize, stored: &C) -> Result<Option<C>, Poisoned> {
+        if self.mappings.is_empty() {
+            return Ok(None);
+        }
+        match self.find(addr) {
+            Some((offset, device)) => {
+                let mut device = lock(device)?;
+                Ok(Some(device.read(offset, stored)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn write(&self, addr: usize, value: C) -> Result<C, Poisoned> {
+        if self.mappings.is_empty() {
+            return Ok(value);
+        }
+        match self.find(addr) {
+            Some((offset, device)) => {
+                let mut device = lock(device)?;
+                Ok(device.write(offset, value))
+            }
+            None => Ok(value),
+        }
+    }
+}
+
+impl<C: Cell> Default for Bus<C> {
+    fn default() -> Bus<C> {
+        Bus {
+            mappings: Vec::new(),
+        }
+    }
+}
+
+impl<C: Cell> Clone for Bus<C> {
+    fn clone(&self) -> Bus<C> {
+        Bus {
+            mappings: self.mappings.clone(),
+        }
+    }
+}
+
+impl<C: Cell> fmt::Debug for Bus<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.mappings.iter().map(|x| &x.0))
+            .finish()
+    }
+}
@@ -0,0 +1,83 @@
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
+
+/// A value that can be stored in a memory cell of a machine.
+///
+/// All arithmetic is checked so that overflows surface as errors
+/// instead of silently wrapping around.
+pub trait Cell:
+    Clone + Default + PartialEq + PartialOrd + FromStr + fmt::Debug + fmt::Display
+{
+    /// Converts a small integer into a cell value.
+    fn from_i64(value: i64) -> Self;
+
+    /// Converts the cell value into an `i64` if it fits.
+    fn to_i64(&self) -> Option<i64>;
+
+    /// Adds two cells, returning `None` on overflow.
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+
+    /// Multiplies two cells, returning `None` on overflow.
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+
+    /// Returns true if the cell is zero.
+    fn is_zero(&self) -> bool {
+        *self == Self::from_i64(0)
+    }
+}
+
+impl Cell for i64 {
+    fn from_i64(value: i64) -> i64 {
+        value
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        Some(*self)
+    }
+
+    fn checked_add(&self, other: &i64) -> Option<i64> {
+        i64::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &i64) -> Option<i64> {
+        i64::checked_mul(*self, *other)
+    }
+}
+
+impl Cell for i128 {
+    fn from_i64(value: i64) -> i128 {
+        value.into()
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        i64::try_from(*self).ok()
+    }
+
+    fn checked_add(&self, other: &i128) -> Option<i128> {
+        i128::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &i128) -> Option<i128> {
+        i128::checked_mul(*self, *other)
+    }
+}
+
+#[cfg(feature = "bignum")]
+impl Cell for num_bigint::BigInt {
+    fn from_i64(value: i64) -> num_bigint::BigInt {
+        value.into()
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        i64::try_from(self).ok()
+    }
+
+    fn checked_add(&self, other: &num_bigint::BigInt) -> Option<num_bigint::BigInt> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &num_bigint::BigInt) -> Option<num_bigint::BigInt> {
+        Some(self * other)
+    }
+}
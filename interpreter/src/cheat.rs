@@ -0,0 +1,135 @@
+use alloc::vec::Vec;
+
+use crate::Machine;
+
+/// A copy of the memory of a machine at one point in time.
+///
+/// Snapshots read the raw memory so mapped devices are not consulted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemorySnapshot {
+    mem: Vec<i64>,
+}
+
+impl MemorySnapshot {
+    /// Takes a snapshot of the memory of a machine.
+    pub fn take(machine: &Machine) -> MemorySnapshot {
+        MemorySnapshot {
+            mem: machine.mem().to_vec(),
+        }
+    }
+
+    /// Returns the value of a cell, cells past the end read as zero.
+    pub fn get(&self, addr: usize) -> i64 {
+        self.mem.get(addr).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of cells in the snapshot.
+    pub fn len(&self) -> usize {
+        self.mem.len()
+    }
+
+    /// Returns true if the snapshot holds no cells.
+    pub fn is_empty(&self) -> bool {
+        self.mem.is_empty()
+    }
+
+    /// Returns the cells that differ from another snapshot as address, old
+    /// and new value.
+    pub fn diff(&self, other: &MemorySnapshot) -> Vec<(usize, i64, i64)> {
+        (0..self.len().max(other.len()))
+            .filter_map(|addr| {
+                let (old, new) = (self.get(addr), other.get(addr));
+                if old != new {
+                    Some((addr, old, new))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// A predicate comparing a cell between two snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellFilter {
+    /// The value is different.
+    Changed,
+    /// The value is the same.
+    Unchanged,
+    /// The value went up.
+    Increased,
+    /// The value went down.
+    Decreased,
+    /// The value now equals the given one.
+    Equals(i64),
+}
+
+impl CellFilter {
+    /// Checks the filter for a cell that went from `old` to `new`.
+    pub fn matches(self, old: i64, new: i64) -> bool {
+        match self {
+            CellFilter::Changed => old != new,
+            CellFilter::Unchanged => old == new,
+            CellFilter::Increased => new > old,
+            CellFilter::Decreased => new < old,
+            CellFilter::Equals(value) => new == value,
+        }
+    }
+}
+
+/// Narrows down memory cells by how they change over a run.
+///
+/// The finder starts with every cell of the machine as a candidate and
+/// remembers a snapshot.  Every call to `filter` compares the current
+/// memory with the last snapshot, drops the candidates that do not match
+/// and takes a new snapshot.  After a few rounds only the cells that hold
+/// the value of interest remain.
+///
+/// Cells the memory grows into later read as zero before and become
+/// candidates if a zero cell would have matched every filter so far.  Like
+/// snapshots the finder reads the raw memory.
+#[derive(Debug, Clone)]
+pub struct CheatFinder {
+    candidates: Vec<usize>,
+    last: MemorySnapshot,
+    zeros_match: bool,
+}
+
+impl CheatFinder {
+    /// Creates a finder with all cells of the machine as candidates.
+    pub fn new(machine: &Machine) -> CheatFinder {
+        let last = MemorySnapshot::take(machine);
+        CheatFinder {
+            candidates: (0..last.len()).collect(),
+            last,
+            zeros_match: true,
+        }
+    }
+
+    /// Keeps the candidates matching the filter and returns how many remain.
+    pub fn filter(&mut self, machine: &Machine, filter: CellFilter) -> usize {
+        let current = MemorySnapshot::take(machine);
+        let last = &self.last;
+        if self.zeros_match {
+            self.candidates.extend(last.len()..current.len());
+        }
+        self.candidates
+            .retain(|&addr| filter.matches(last.get(addr), current.get(addr)));
+        self.zeros_match &= filter.matches(0, 0);
+        self.last = current;
+        self.candidates.len()
+    }
+
+    /// Returns the remaining candidate addresses.
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+
+    /// Returns the remaining candidates with their current values.
+    pub fn values(&self, machine: &Machine) -> Vec<(usize, i64)> {
+        self.candidates
+            .iter()
+            .map(|&addr| (addr, machine.mem().get(addr).copied().unwrap_or(0)))
+            .collect()
+    }
+}
@@ -0,0 +1,940 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+
+use crate::asm::assemble;
+
+/// The punctuation of the language, longest first.
+const PUNCTUATION: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "(", ")", "{", "}", ",", ";", "=", "+", "-", "*", "<", ">",
+    "!",
+];
+
+const KEYWORDS: &[&str] = &[
+    "fn", "let", "if", "else", "while", "return", "break", "continue",
+];
+
+/// The built-in functions with their number of arguments.
+const BUILTINS: &[(&str, usize)] = &[("input", 0), ("output", 1), ("halt", 0)];
+
+/// How deeply expressions and blocks may nest.
+///
+/// The parser and the code generator recurse along the nesting, the limit
+/// keeps them from overflowing the stack.
+const MAX_NESTING: usize = 128;
+
+/// An error produced by the compiler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    line: usize,
+    message: String,
+}
+
+impl CompileError {
+    fn new<S: Into<String>>(line: usize, message: S) -> CompileError {
+        CompileError {
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the one-based line number the error happened on.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for CompileError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    Punct(&'static str),
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Token::Ident(ref name) => write!(f, "'{}'", name),
+            Token::Number(value) => write!(f, "'{}'", value),
+            Token::Punct(punct) => write!(f, "'{}'", punct),
+            Token::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+fn parse_char(s: &str, line: usize) -> Result<(i64, usize), CompileError> {
+    let invalid = || CompileError::new(line, "invalid character literal");
+    let mut chars = s.chars();
+    let c = match chars.next().ok_or_else(invalid)? {
+        '\\' => match chars.next().ok_or_else(invalid)? {
+            'n' => '\n',
+            't' => '\t',
+            '0' => '\0',
+            c @ ('\\' | '\'') => c,
+            _ => return Err(invalid()),
+        },
+        '\'' => return Err(invalid()),
+        c => c,
+    };
+    if chars.next() != Some('\'') {
+        return Err(invalid());
+    }
+    Ok((c as i64, s.len() - chars.as_str().len()))
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, CompileError> {
+    let mut rv = Vec::new();
+    for (idx, line) in source.lines().enumerate() {
+        let lineno = idx + 1;
+        let mut rest = line.split("//").next().unwrap_or("").trim_start();
+        while !rest.is_empty() {
+            let token = if rest.starts_with(|c: char| c.is_ascii_digit()) {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(rest.len());
+                let value = rest[..end].parse().map_err(|_| {
+                    CompileError::new(lineno, format!("invalid number '{}'", &rest[..end]))
+                })?;
+                rest = &rest[end..];
+                Token::Number(value)
+            } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                let ident = rest[..end].to_string();
+                rest = &rest[end..];
+                Token::Ident(ident)
+            } else if let Some(literal) = rest.strip_prefix('\'') {
+                let (value, len) = parse_char(literal, lineno)?;
+                rest = &literal[len..];
+                Token::Number(value)
+            } else {
+                let punct = PUNCTUATION
+                    .iter()
+                    .find(|x| rest.starts_with(*x))
+                    .ok_or_else(|| {
+                        let c = rest.chars().next().unwrap();
+                        CompileError::new(lineno, format!("unexpected character '{}'", c))
+                    })?;
+                rest = &rest[punct.len()..];
+                Token::Punct(punct)
+            };
+            rv.push((token, lineno));
+            rest = rest.trim_start();
+        }
+    }
+    let last_line = source.lines().count().max(1);
+    rv.push((Token::Eof, last_line));
+    Ok(rv)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(i64),
+    Var(String, usize),
+    Call(String, Vec<Expr>, usize),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug)]
+enum Stmt {
+    Let(String, Expr),
+    Assign(String, Expr, usize),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Return(Option<Expr>),
+    Break(usize),
+    Continue(usize),
+    Expr(Expr),
+}
+
+#[derive(Debug)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Vec<Stmt>,
+    line: usize,
+}
+
+#[derive(Debug, Default)]
+struct Program {
+    functions: Vec<Function>,
+    globals: Vec<(String, i64)>,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error<T, S: Into<String>>(&self, message: S) -> Result<T, CompileError> {
+        Err(CompileError::new(self.line(), message))
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, CompileError> {
+        self.error(format!("expected {}, found {}", expected, self.peek()))
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(*self.peek(), Token::Punct(x) if x == punct)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(*self.peek(), Token::Ident(ref x) if x == keyword)
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if self.is_punct(punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), CompileError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("'{}'", punct))
+        }
+    }
+
+    /// Runs `func` one nesting level deeper.
+    fn nested<T, F>(&mut self, func: F) -> Result<T, CompileError>
+    where
+        F: FnOnce(&mut Parser) -> Result<T, CompileError>,
+    {
+        if self.depth >= MAX_NESTING {
+            return self.error("nesting too deep");
+        }
+        self.depth += 1;
+        let rv = func(self);
+        self.depth -= 1;
+        rv
+    }
+
+    fn ident(&mut self) -> Result<String, CompileError> {
+        match *self.peek() {
+            Token::Ident(ref name) if !KEYWORDS.contains(&name.as_str()) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => self.unexpected("a name"),
+        }
+    }
+
+    fn program(&mut self) -> Result<Program, CompileError> {
+        let mut program = Program::default();
+        while *self.peek() != Token::Eof {
+            if self.is_keyword("fn") {
+                program.functions.push(self.function()?);
+            } else if self.is_keyword("let") {
+                self.pos += 1;
+                let line = self.line();
+                let name = self.ident()?;
+                if program.globals.iter().any(|x| x.0 == name) {
+                    return Err(CompileError::new(
+                        line,
+                        format!("duplicate global '{}'", name),
+                    ));
+                }
+                self.expect("=")?;
+                let negative = self.eat("-");
+                let value = match self.next() {
+                    Token::Number(value) if negative => -value,
+                    Token::Number(value) => value,
+                    _ => return self.error("globals must be initialized with a number"),
+                };
+                self.expect(";")?;
+                program.globals.push((name, value));
+            } else {
+                return self.unexpected("'fn' or 'let'");
+            }
+        }
+        Ok(program)
+    }
+
+    fn function(&mut self) -> Result<Function, CompileError> {
+        let line = self.line();
+        self.pos += 1;
+        let name = self.ident()?;
+        self.expect("(")?;
+        let mut params: Vec<String> = Vec::new();
+        if !self.eat(")") {
+            loop {
+                let param_line = self.line();
+                let param = self.ident()?;
+                if params.contains(&param) {
+                    return Err(CompileError::new(
+                        param_line,
+                        format!("duplicate parameter '{}'", param),
+                    ));
+                }
+                params.push(param);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        Ok(Function {
+            name,
+            params,
+            body: self.block()?,
+            line,
+        })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        self.expect("{")?;
+        self.nested(|p| {
+            let mut rv = Vec::new();
+            while !p.eat("}") {
+                rv.push(p.statement()?);
+            }
+            Ok(rv)
+        })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, CompileError> {
+        let line = self.line();
+        let keyword = match *self.peek() {
+            Token::Ident(ref x) if KEYWORDS.contains(&x.as_str()) => x.clone(),
+            _ => String::new(),
+        };
+        let stmt = match keyword.as_str() {
+            "let" => {
+                self.pos += 1;
+                let name = self.ident()?;
+                self.expect("=")?;
+                Stmt::Let(name, self.expr()?)
+            }
+            "if" => return self.if_statement(),
+            "while" => {
+                self.pos += 1;
+                let cond = self.expr()?;
+                return Ok(Stmt::While(cond, self.block()?));
+            }
+            "return" => {
+                self.pos += 1;
+                if self.is_punct(";") {
+                    Stmt::Return(None)
+                } else {
+                    Stmt::Return(Some(self.expr()?))
+                }
+            }
+            "break" => {
+                self.pos += 1;
+                Stmt::Break(line)
+            }
+            "continue" => {
+                self.pos += 1;
+                Stmt::Continue(line)
+            }
+            _ => {
+                let assignment =
+                    matches!(self.tokens.get(self.pos + 1), Some(&(Token::Punct("="), _)));
+                if let (Token::Ident(_), true) = (self.peek(), assignment) {
+                    let name = self.ident()?;
+                    self.pos += 1;
+                    Stmt::Assign(name, self.expr()?, line)
+                } else {
+                    Stmt::Expr(self.expr()?)
+                }
+            }
+        };
+        self.expect(";")?;
+        Ok(stmt)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, CompileError> {
+        self.pos += 1;
+        let cond = self.expr()?;
+        let then = self.block()?;
+        let otherwise = if self.is_keyword("else") {
+            self.pos += 1;
+            if self.is_keyword("if") {
+                vec![self.nested(Parser::if_statement)?]
+            } else {
+                self.block()?
+            }
+        } else {
+            vec![]
+        };
+        Ok(Stmt::If(cond, then, otherwise))
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[&'static str],
+        operand: fn(&mut Parser) -> Result<Expr, CompileError>,
+    ) -> Result<Expr, CompileError> {
+        let depth = self.depth;
+        let rv = self.operator_chain(ops, operand);
+        self.depth = depth;
+        rv
+    }
+
+    fn operator_chain(
+        &mut self,
+        ops: &[&'static str],
+        operand: fn(&mut Parser) -> Result<Expr, CompileError>,
+    ) -> Result<Expr, CompileError> {
+        let mut lhs = operand(self)?;
+        while let Some(&op) = ops.iter().find(|x| self.is_punct(x)) {
+            self.pos += 1;
+            // every operator nests the expression so far one level deeper
+            if self.depth >= MAX_NESTING {
+                return self.error("nesting too deep");
+            }
+            self.depth += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(operand(self)?));
+        }
+        Ok(lhs)
+    }
+
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        self.binary(&["||"], |p| {
+            p.binary(&["&&"], |p| {
+                p.binary(&["==", "!=", "<", "<=", ">", ">="], |p| {
+                    p.binary(&["+", "-"], |p| p.binary(&["*"], Parser::unary))
+                })
+            })
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        for &op in &["-", "!"] {
+            if self.eat(op) {
+                return Ok(match (op, self.nested(Parser::unary)?) {
+                    ("-", Expr::Number(value)) => Expr::Number(-value),
+                    (op, expr) => Expr::Unary(op, Box::new(expr)),
+                });
+            }
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let line = self.line();
+        if let Token::Number(value) = *self.peek() {
+            self.pos += 1;
+            return Ok(Expr::Number(value));
+        }
+        if self.eat("(") {
+            let expr = self.nested(Parser::expr)?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+        let name = match self.ident() {
+            Ok(name) => name,
+            Err(_) => return self.unexpected("an expression"),
+        };
+        if !self.eat("(") {
+            return Ok(Expr::Var(name, line));
+        }
+        let mut args = Vec::new();
+        if !self.eat(")") {
+            loop {
+                args.push(self.nested(Parser::expr)?);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        Ok(Expr::Call(name, args, line))
+    }
+}
+
+/// An operand in the generated code.
+#[derive(Debug, Clone, PartialEq)]
+enum Arg {
+    /// An immediate value.
+    Imm(i64),
+    /// A slot of the current stack frame.
+    Slot(i64),
+    /// A slot of the frame of a called function.
+    Callee(i64),
+    /// The size of the current frame times a factor as immediate.
+    FrameSize(i64),
+    /// A cell with a label.
+    Cell(String),
+    /// The address of a label as immediate.
+    Addr(String),
+}
+
+enum Line {
+    Label(String),
+    Instr(&'static str, Vec<Arg>),
+}
+
+/// The register holding the return value of the last call.
+const RETURN_VALUE: &str = "_ret";
+
+struct FunctionCompiler<'a> {
+    arities: &'a BTreeMap<&'a str, usize>,
+    globals: &'a BTreeMap<&'a str, String>,
+    label_count: &'a mut usize,
+    lines: Vec<Line>,
+    vars: Vec<(String, i64)>,
+    next_slot: i64,
+    max_slot: i64,
+    loops: Vec<(String, String)>,
+}
+
+impl<'a> FunctionCompiler<'a> {
+    fn label(&mut self) -> String {
+        *self.label_count += 1;
+        format!("_l{}", self.label_count)
+    }
+
+    fn emit(&mut self, mnemonic: &'static str, args: Vec<Arg>) {
+        self.lines.push(Line::Instr(mnemonic, args));
+    }
+
+    fn place(&mut self, label: String) {
+        self.lines.push(Line::Label(label));
+    }
+
+    fn alloc(&mut self) -> i64 {
+        let slot = self.next_slot;
+        self.next_slot += 1;
+        self.max_slot = self.max_slot.max(slot);
+        slot
+    }
+
+    fn lookup(&self, name: &str) -> Option<Arg> {
+        if let Some(&(_, slot)) = self.vars.iter().rev().find(|x| x.0 == name) {
+            return Some(Arg::Slot(slot));
+        }
+        self.globals.get(name).map(|x| Arg::Cell(x.clone()))
+    }
+
+    /// Generates code for an expression and returns where its value is.
+    ///
+    /// Temporaries above the slot counter at the start stay in use until
+    /// the caller resets it.
+    fn expr(&mut self, expr: &Expr) -> Result<Arg, CompileError> {
+        let mark = self.next_slot;
+        Ok(match *expr {
+            Expr::Number(value) => Arg::Imm(value),
+            Expr::Var(ref name, line) => match self.lookup(name) {
+                Some(Arg::Slot(slot)) => Arg::Slot(slot),
+                // globals are copied so that calls later in the expression
+                // cannot change the value
+                Some(cell) => {
+                    let slot = self.alloc();
+                    self.emit("add", vec![cell, Arg::Imm(0), Arg::Slot(slot)]);
+                    Arg::Slot(slot)
+                }
+                None => {
+                    return Err(CompileError::new(
+                        line,
+                        format!("undefined variable '{}'", name),
+                    ))
+                }
+            },
+            Expr::Call(ref name, ref args, line) => self.call(name, args, line)?,
+            Expr::Unary(op, ref operand) => {
+                let value = self.expr(operand)?;
+                self.next_slot = mark;
+                let slot = Arg::Slot(self.alloc());
+                match op {
+                    "-" => self.emit("mul", vec![value, Arg::Imm(-1), slot.clone()]),
+                    _ => self.emit("eq", vec![value, Arg::Imm(0), slot.clone()]),
+                }
+                slot
+            }
+            Expr::Binary(op @ "&&", ref lhs, ref rhs)
+            | Expr::Binary(op @ "||", ref lhs, ref rhs) => {
+                let slot = Arg::Slot(self.alloc());
+                let (short, end) = (self.label(), self.label());
+                let jump = if op == "&&" { "jf" } else { "jt" };
+                for operand in &[lhs, rhs] {
+                    let value = self.expr(operand)?;
+                    self.next_slot = mark + 1;
+                    self.emit(jump, vec![value, Arg::Addr(short.clone())]);
+                }
+                let (full, shorted) = if op == "&&" { (1, 0) } else { (0, 1) };
+                self.emit("add", vec![Arg::Imm(full), Arg::Imm(0), slot.clone()]);
+                self.emit("jt", vec![Arg::Imm(1), Arg::Addr(end.clone())]);
+                self.place(short);
+                self.emit("add", vec![Arg::Imm(shorted), Arg::Imm(0), slot.clone()]);
+                self.place(end);
+                slot
+            }
+            Expr::Binary(op, ref lhs, ref rhs) => {
+                let lhs = self.expr(lhs)?;
+                let mut rhs = self.expr(rhs)?;
+                if op == "-" {
+                    rhs = match rhs {
+                        Arg::Imm(value) if value != i64::MIN => Arg::Imm(-value),
+                        rhs => {
+                            let slot = Arg::Slot(self.alloc());
+                            self.emit("mul", vec![rhs, Arg::Imm(-1), slot.clone()]);
+                            slot
+                        }
+                    };
+                }
+                self.next_slot = mark;
+                let slot = Arg::Slot(self.alloc());
+                let (mnemonic, args, negate) = match op {
+                    "+" | "-" => ("add", [lhs, rhs], false),
+                    "*" => ("mul", [lhs, rhs], false),
+                    "==" => ("eq", [lhs, rhs], false),
+                    "!=" => ("eq", [lhs, rhs], true),
+                    "<" => ("lt", [lhs, rhs], false),
+                    ">" => ("lt", [rhs, lhs], false),
+                    "<=" => ("lt", [rhs, lhs], true),
+                    _ => ("lt", [lhs, rhs], true),
+                };
+                let [a, b] = args;
+                self.emit(mnemonic, vec![a, b, slot.clone()]);
+                if negate {
+                    self.emit("eq", vec![slot.clone(), Arg::Imm(0), slot.clone()]);
+                }
+                slot
+            }
+        })
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], line: usize) -> Result<Arg, CompileError> {
+        let arity = match BUILTINS.iter().find(|x| x.0 == name) {
+            Some(&(_, arity)) => arity,
+            None => *self
+                .arities
+                .get(name)
+                .ok_or_else(|| CompileError::new(line, format!("undefined function '{}'", name)))?,
+        };
+        if args.len() != arity {
+            return Err(CompileError::new(
+                line,
+                format!("{} takes {} arguments, got {}", name, arity, args.len()),
+            ));
+        }
+
+        let mark = self.next_slot;
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.expr(arg)?);
+        }
+        match name {
+            "input" => {
+                let slot = Arg::Slot(self.alloc());
+                self.emit("in", vec![slot.clone()]);
+                return Ok(slot);
+            }
+            "output" => {
+                self.emit("out", values);
+                self.next_slot = mark;
+                return Ok(Arg::Imm(0));
+            }
+            "halt" => {
+                self.emit("hlt", vec![]);
+                return Ok(Arg::Imm(0));
+            }
+            _ => {}
+        }
+
+        // the frame of the callee starts right after the current one with
+        // the return address followed by the arguments
+        for (idx, value) in values.into_iter().enumerate() {
+            self.emit("add", vec![value, Arg::Imm(0), Arg::Callee(idx as i64 + 1)]);
+        }
+        let ret = self.label();
+        self.emit(
+            "add",
+            vec![Arg::Addr(ret.clone()), Arg::Imm(0), Arg::Callee(0)],
+        );
+        self.emit("arb", vec![Arg::FrameSize(1)]);
+        self.emit("jt", vec![Arg::Imm(1), Arg::Addr(format!("fn_{}", name))]);
+        self.place(ret);
+        self.emit("arb", vec![Arg::FrameSize(-1)]);
+        self.next_slot = mark;
+        let slot = Arg::Slot(self.alloc());
+        self.emit(
+            "add",
+            vec![Arg::Cell(RETURN_VALUE.into()), Arg::Imm(0), slot.clone()],
+        );
+        Ok(slot)
+    }
+
+    fn ret(&mut self, value: Arg) {
+        self.emit(
+            "add",
+            vec![value, Arg::Imm(0), Arg::Cell(RETURN_VALUE.into())],
+        );
+        self.emit("jt", vec![Arg::Imm(1), Arg::Slot(0)]);
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Result<(), CompileError> {
+        let (vars, next_slot) = (self.vars.len(), self.next_slot);
+        for stmt in stmts {
+            self.statement(stmt)?;
+        }
+        self.vars.truncate(vars);
+        self.next_slot = next_slot;
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+        let mark = self.next_slot;
+        match *stmt {
+            Stmt::Let(ref name, ref expr) => {
+                let value = self.expr(expr)?;
+                self.next_slot = mark;
+                let slot = self.alloc();
+                self.emit("add", vec![value, Arg::Imm(0), Arg::Slot(slot)]);
+                self.vars.push((name.clone(), slot));
+                return Ok(());
+            }
+            Stmt::Assign(ref name, ref expr, line) => {
+                let target = self.lookup(name).ok_or_else(|| {
+                    CompileError::new(line, format!("undefined variable '{}'", name))
+                })?;
+                let value = self.expr(expr)?;
+                self.emit("add", vec![value, Arg::Imm(0), target]);
+            }
+            Stmt::If(ref cond, ref then, ref otherwise) => {
+                let value = self.expr(cond)?;
+                self.next_slot = mark;
+                let (other, end) = (self.label(), self.label());
+                self.emit("jf", vec![value, Arg::Addr(other.clone())]);
+                self.block(then)?;
+                if !otherwise.is_empty() {
+                    self.emit("jt", vec![Arg::Imm(1), Arg::Addr(end.clone())]);
+                }
+                self.place(other);
+                if !otherwise.is_empty() {
+                    self.block(otherwise)?;
+                    self.place(end);
+                }
+            }
+            Stmt::While(ref cond, ref body) => {
+                let (start, end) = (self.label(), self.label());
+                self.place(start.clone());
+                let value = self.expr(cond)?;
+                self.next_slot = mark;
+                self.emit("jf", vec![value, Arg::Addr(end.clone())]);
+                self.loops.push((start.clone(), end.clone()));
+                self.block(body)?;
+                self.loops.pop();
+                self.emit("jt", vec![Arg::Imm(1), Arg::Addr(start)]);
+                self.place(end);
+            }
+            Stmt::Return(ref expr) => {
+                let value = match *expr {
+                    Some(ref expr) => self.expr(expr)?,
+                    None => Arg::Imm(0),
+                };
+                self.ret(value);
+            }
+            Stmt::Break(line) | Stmt::Continue(line) => {
+                let target = match (self.loops.last(), stmt) {
+                    (Some((_, end)), Stmt::Break(_)) => end.clone(),
+                    (Some((start, _)), _) => start.clone(),
+                    (None, _) => return Err(CompileError::new(line, "not inside a loop")),
+                };
+                self.emit("jt", vec![Arg::Imm(1), Arg::Addr(target)]);
+            }
+            Stmt::Expr(ref expr) => {
+                self.expr(expr)?;
+            }
+        }
+        self.next_slot = mark;
+        Ok(())
+    }
+
+    /// Renders the generated code as assembly.
+    fn finish(self, out: &mut String) {
+        let frame = self.max_slot + 1;
+        for line in &self.lines {
+            match *line {
+                Line::Label(ref label) => {
+                    out.push_str(label);
+                    out.push_str(":\n");
+                }
+                Line::Instr(mnemonic, ref args) => {
+                    let args: Vec<_> = args
+                        .iter()
+                        .map(|arg| match *arg {
+                            Arg::Imm(value) => format!("#{}", value),
+                            Arg::Slot(slot) => format!("@{}", slot),
+                            Arg::Callee(slot) => format!("@{}", frame + slot),
+                            Arg::FrameSize(factor) => format!("#{}", frame * factor),
+                            Arg::Cell(ref label) => label.clone(),
+                            Arg::Addr(ref label) => format!("#{}", label),
+                        })
+                        .collect();
+                    out.push_str(&format!("    {} {}\n", mnemonic, args.join(", ")));
+                }
+            }
+        }
+    }
+}
+
+/// Compiles a program to Intcode assembly.
+///
+/// See `compile` for the language.
+pub fn compile_to_assembly(source: &str) -> Result<String, CompileError> {
+    let program = Parser {
+        tokens: tokenize(source)?,
+        pos: 0,
+        depth: 0,
+    }
+    .program()?;
+
+    let mut arities = BTreeMap::new();
+    for function in &program.functions {
+        if BUILTINS.iter().any(|x| x.0 == function.name)
+            || arities
+                .insert(function.name.as_str(), function.params.len())
+                .is_some()
+        {
+            return Err(CompileError::new(
+                function.line,
+                format!("duplicate function '{}'", function.name),
+            ));
+        }
+    }
+    match arities.get("main") {
+        Some(0) => {}
+        Some(_) => {
+            let line = program
+                .functions
+                .iter()
+                .find(|x| x.name == "main")
+                .unwrap()
+                .line;
+            return Err(CompileError::new(line, "main cannot take arguments"));
+        }
+        None => return Err(CompileError::new(1, "missing function 'main'")),
+    }
+    let globals: BTreeMap<_, _> = program
+        .globals
+        .iter()
+        .map(|x| (x.0.as_str(), format!("g_{}", x.0)))
+        .collect();
+
+    // the stack starts after the program and main returns to the halt
+    let mut out = String::new();
+    out.push_str("    arb #_stack\n");
+    out.push_str("    add #_exit, #0, @0\n");
+    out.push_str("    jt #1, #fn_main\n");
+    out.push_str("_exit:\n    hlt\n");
+
+    let mut label_count = 0;
+    for function in &program.functions {
+        let mut compiler = FunctionCompiler {
+            arities: &arities,
+            globals: &globals,
+            label_count: &mut label_count,
+            lines: vec![Line::Label(format!("fn_{}", function.name))],
+            vars: function
+                .params
+                .iter()
+                .enumerate()
+                .map(|(idx, name)| (name.clone(), idx as i64 + 1))
+                .collect(),
+            next_slot: function.params.len() as i64 + 1,
+            max_slot: function.params.len() as i64,
+            loops: vec![],
+        };
+        compiler.block(&function.body)?;
+        compiler.ret(Arg::Imm(0));
+        compiler.finish(&mut out);
+    }
+
+    out.push_str(&format!("{}:\n    data 0\n", RETURN_VALUE));
+    for &(ref name, value) in &program.globals {
+        out.push_str(&format!(
+            "{}:\n    data {}\n",
+            globals[name.as_str()],
+            value
+        ));
+    }
+    out.push_str("_stack:\n");
+    Ok(out)
+}
+
+/// Compiles a program written in a small structured language to Intcode.
+///
+/// Programs consist of functions and global variables.  Execution starts
+/// in `main` and the machine halts when it returns.  Every value is a
+/// number; comparisons and logic operators produce zero or one:
+///
+/// ```text
+/// let calls = 0;
+///
+/// fn fib(n) {
+///     calls = calls + 1;
+///     if n < 2 {
+///         return n;
+///     }
+///     return fib(n - 1) + fib(n - 2);
+/// }
+///
+/// fn main() {
+///     let n = input();
+///     while n > 0 {
+///         output(fib(n));
+///         n = n - 1;
+///     }
+/// }
+/// ```
+///
+/// Statements are `let`, assignments, `if`/`else`, `while` with `break`
+/// and `continue`, `return` and expressions.  The operators are `+`, `-`,
+/// `*`, the comparisons, `!` and the short circuiting `&&` and `||`.
+/// Character literals like `'a'` are numbers.  `input()` reads a value,
+/// `output(x)` writes one and `halt()` stops the machine.  Locals live on
+/// a stack addressed through the relative base, so functions can recurse.
+/// Blocks and expressions can nest at most 128 levels deep.
+pub fn compile(source: &str) -> Result<Vec<i64>, CompileError> {
+    let assembly = compile_to_assembly(source)?;
+    Ok(assemble(&assembly)
+        .unwrap_or_else(|err| panic!("compiler produced invalid assembly: {}", err)))
+}
@@ -0,0 +1,107 @@
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::cell::Cell;
+use crate::error::Error;
+use crate::Machine;
+
+/// The number of instructions executed in one poll before giving other
+/// tasks a turn.
+const INSTRUCTIONS_PER_POLL: u32 = 10_000;
+
+/// A machine that reads its inputs from an async stream.
+///
+/// The machine itself is a stream of its outputs.  Whenever it needs
+/// input it polls the input stream, so awaiting the next output suspends
+/// the task until input arrives.  Long computations without output yield
+/// every now and then so that machines sharing an executor make progress.
+/// This works with any executor:
+///
+/// ```ignore
+/// let mut machine = AsyncMachine::new(Machine::new(&program), inputs);
+/// while let Some(output) = machine.next().await {
+///     println!("{}", output?);
+/// }
+/// ```
+///
+/// The stream ends when the machine halts or when the inputs end while
+/// the machine waits for input.  An error is produced once and ends the
+/// stream as well.  Custom input opcodes are run without waiting.
+pub struct AsyncMachine<S, C: Cell = i64> {
+    machine: Machine<C>,
+    inputs: S,
+    done: bool,
+}
+
+impl<S, C> AsyncMachine<S, C>
+where
+    S: Stream<Item = C> + Unpin,
+    C: Cell,
+{
+    /// Wraps a machine together with the stream of its inputs.
+    ///
+    /// Inputs already fed to the machine are consumed first.
+    pub fn new(machine: Machine<C>, inputs: S) -> AsyncMachine<S, C> {
+        AsyncMachine {
+            machine,
+            inputs,
+            done: false,
+        }
+    }
+
+    /// Returns the wrapped machine.
+    pub fn machine(&self) -> &Machine<C> {
+        &self.machine
+    }
+
+    /// Returns the wrapped machine mutably.
+    pub fn machine_mut(&mut self) -> &mut Machine<C> {
+        &mut self.machine
+    }
+
+    /// Returns the machine and the input stream.
+    pub fn into_inner(self) -> (Machine<C>, S) {
+        (self.machine, self.inputs)
+    }
+}
+
+impl<S, C> Stream for AsyncMachine<S, C>
+where
+    S: Stream<Item = C> + Unpin,
+    C: Cell + Unpin,
+{
+    type Item = Result<C, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut budget = INSTRUCTIONS_PER_POLL;
+        while !this.done && !this.machine.halted() {
+            if this.machine.needs_input() {
+                match Pin::new(&mut this.inputs).poll_next(cx) {
+                    Poll::Ready(Some(value)) => this.machine.feed(value),
+                    Poll::Ready(None) => this.done = true,
+                    Poll::Pending => return Poll::Pending,
+                }
+                continue;
+            }
+            if budget == 0 {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            budget -= 1;
+            match this.machine.try_step_instruction() {
+                Ok(true) if !this.machine.halted() => {
+                    return Poll::Ready(Some(Ok(this.machine.last_output())));
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+        Poll::Ready(None)
+    }
+}
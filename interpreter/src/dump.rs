@@ -0,0 +1,423 @@
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+
+use crate::asm::disassemble_instruction;
+use crate::cell::Cell;
+use crate::error::Error;
+use crate::transcript::{Event, Transcript, TranscriptError};
+use crate::Machine;
+
+/// The number of traced instructions leading up to the ip that are shown
+/// in the disassembly of a dump.
+const DISASSEMBLY_BEFORE: usize = 4;
+
+/// The number of instructions from the ip on shown in the disassembly.
+const DISASSEMBLY_AFTER: usize = 6;
+
+/// The most recent instructions and I/O of a machine.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct History<C: Cell> {
+    limit: usize,
+    trace: VecDeque<(usize, Vec<C>)>,
+    io: VecDeque<Event<C>>,
+}
+
+impl<C: Cell> History<C> {
+    pub fn new(limit: usize) -> History<C> {
+        History {
+            limit,
+            trace: VecDeque::with_capacity(limit),
+            io: VecDeque::with_capacity(limit),
+        }
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        while self.trace.len() > limit {
+            self.trace.pop_front();
+        }
+        while self.io.len() > limit {
+            self.io.pop_front();
+        }
+        self.limit = limit;
+    }
+
+    pub fn push_instruction(&mut self, ip: usize, cells: Vec<C>) {
+        if self.trace.len() == self.limit {
+            self.trace.pop_front();
+        }
+        self.trace.push_back((ip, cells));
+    }
+
+    pub fn push_event(&mut self, event: Event<C>) {
+        if self.io.len() == self.limit {
+            self.io.pop_front();
+        }
+        self.io.push_back(event);
+    }
+}
+
+/// An error produced when parsing a crash dump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpError {
+    line: usize,
+    message: String,
+}
+
+impl DumpError {
+    fn new<S: Into<String>>(line: usize, message: S) -> DumpError {
+        DumpError {
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the one-based line the error belongs to.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for DumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for DumpError {}
+
+/// A post-mortem snapshot of a machine.
+///
+/// Dumps are created with `Machine::crash_dump` and hold the complete
+/// state of the machine together with the most recently executed
+/// instructions and I/O if a history limit was set.  Their text form can
+/// be parsed again and turned back into a machine to continue debugging:
+///
+/// ```text
+/// # intcode crash dump
+/// error: invalid opcode 42 at ip 4
+/// ip: 4
+/// relative base: 0
+/// ...
+///
+/// [trace]
+///     0: 1101,1,2,9  # add #1, #2, 9
+///
+/// [io]
+/// out 1 3
+///
+/// [disassembly]
+///      0: add #1, #2, 9
+/// >    4: data 42
+///
+/// [memory]
+/// 1101,1,2,9,42,0,0,0,0,3
+/// ```
+///
+/// The disassembly section is informational and ignored when parsing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CrashDump<C: Cell = i64> {
+    error: Option<String>,
+    ip: usize,
+    relative_base: C,
+    halted: bool,
+    instruction_count: u64,
+    last_output: C,
+    pending_inputs: Vec<C>,
+    trace: Vec<(usize, Vec<C>)>,
+    io: Vec<Event<C>>,
+    mem: Vec<C>,
+}
+
+impl CrashDump {
+    /// Parses the text form of a dump.
+    pub fn parse(source: &str) -> Result<CrashDump, DumpError> {
+        CrashDump::parse_as(source)
+    }
+}
+
+impl<C: Cell> CrashDump<C> {
+    pub(crate) fn new(machine: &Machine<C>, error: Option<&Error>) -> CrashDump<C> {
+        let (trace, io) = match machine.history {
+            Some(ref history) => (
+                history.trace.iter().cloned().collect(),
+                history.io.iter().cloned().collect(),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        CrashDump {
+            error: error.map(|x| x.to_string()),
+            ip: machine.ip,
+            relative_base: machine.relative_base.clone(),
+            halted: machine.halted,
+            instruction_count: machine.instruction_count,
+            last_output: machine.output.clone(),
+            pending_inputs: machine.inputs.clone(),
+            trace,
+            io,
+            mem: machine.mem.clone(),
+        }
+    }
+
+    /// Returns the message of the error that caused the dump.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Returns the instruction pointer.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Returns the relative base.
+    pub fn relative_base(&self) -> C {
+        self.relative_base.clone()
+    }
+
+    /// Returns true if the machine had halted.
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Returns the number of instructions executed before the dump.
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    /// Returns the last output produced.
+    pub fn last_output(&self) -> C {
+        self.last_output.clone()
+    }
+
+    /// Returns the inputs that were fed but not consumed yet.
+    pub fn pending_inputs(&self) -> &[C] {
+        &self.pending_inputs
+    }
+
+    /// Returns the most recent instructions as address and cells, oldest
+    /// first.
+    ///
+    /// The instruction that failed is the last entry.
+    pub fn trace(&self) -> &[(usize, Vec<C>)] {
+        &self.trace
+    }
+
+    /// Returns the most recent inputs and outputs, oldest first.
+    pub fn io(&self) -> &[Event<C>] {
+        &self.io
+    }
+
+    /// Returns the memory.
+    pub fn mem(&self) -> &[C] {
+        &self.mem
+    }
+
+    /// Creates a machine in the state the dump was taken in.
+    ///
+    /// The recent instructions and I/O become the history of the machine.
+    /// Custom opcodes, restrictions, limits and devices are not part of a
+    /// dump and have to be set up again.
+    pub fn to_machine(&self) -> Machine<C> {
+        let mut machine = Machine::new(&self.mem);
+        if !self.trace.is_empty() || !self.io.is_empty() {
+            machine.history = Some(History {
+                limit: self.trace.len().max(self.io.len()),
+                trace: self.trace.iter().cloned().collect(),
+                io: self.io.iter().cloned().collect(),
+            });
+        }
+        machine.ip = self.ip;
+        machine.relative_base = self.relative_base.clone();
+        machine.halted = self.halted;
+        machine.instruction_count = self.instruction_count;
+        machine.output = self.last_output.clone();
+        machine.inputs = self.pending_inputs.clone();
+        machine
+    }
+
+    /// Parses the text form of a dump with an arbitrary cell type.
+    pub fn parse_as(source: &str) -> Result<CrashDump<C>, DumpError> {
+        let mut rv = CrashDump::default();
+        let mut section = "";
+
+        for (idx, line) in source.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = &line[1..line.len() - 1];
+                if !["trace", "io", "disassembly", "memory"].contains(&section) {
+                    return Err(DumpError::new(
+                        line_no,
+                        format!("unknown section '{}'", section),
+                    ));
+                }
+                continue;
+            }
+            match section {
+                "" => rv.parse_field(line, line_no)?,
+                "trace" => {
+                    let colon = line
+                        .find(':')
+                        .ok_or_else(|| DumpError::new(line_no, "expected ':'"))?;
+                    rv.trace.push((
+                        parse_number(&line[..colon], line_no)?,
+                        parse_values(&line[colon + 1..], line_no)?,
+                    ));
+                }
+                "io" => {
+                    let events = Transcript::<C>::parse(line).map_err(|err| match err {
+                        TranscriptError::Syntax { message, .. } => DumpError::new(line_no, message),
+                        err => DumpError::new(line_no, err.to_string()),
+                    })?;
+                    rv.io.extend_from_slice(events.events());
+                }
+                "disassembly" => {}
+                _ => rv.mem.extend(parse_values(line, line_no)?),
+            }
+        }
+
+        Ok(rv)
+    }
+
+    fn parse_field(&mut self, line: &str, line_no: usize) -> Result<(), DumpError> {
+        let colon = line
+            .find(':')
+            .ok_or_else(|| DumpError::new(line_no, format!("invalid field '{}'", line)))?;
+        let value = line[colon + 1..].trim();
+        match &line[..colon] {
+            "error" => self.error = Some(value.to_string()),
+            "ip" => self.ip = parse_number(value, line_no)?,
+            "relative base" => self.relative_base = parse_number(value, line_no)?,
+            "halted" => self.halted = parse_number(value, line_no)?,
+            "instructions" => self.instruction_count = parse_number(value, line_no)?,
+            "last output" => self.last_output = parse_number(value, line_no)?,
+            "inputs" => self.pending_inputs = parse_values(value, line_no)?,
+            name => return Err(DumpError::new(line_no, format!("unknown field '{}'", name))),
+        }
+        Ok(())
+    }
+
+    fn disassembly_start(&self) -> usize {
+        // walk back over the straight line code that led to the ip
+        let mut start = self.ip;
+        for &(addr, ref cells) in self
+            .trace
+            .iter()
+            .rev()
+            .skip_while(|x| x.0 == self.ip)
+            .take(DISASSEMBLY_BEFORE)
+        {
+            if addr.checked_add(cells.len()) != Some(start) {
+                break;
+            }
+            start = addr;
+        }
+        start
+    }
+}
+
+fn parse_number<T: core::str::FromStr>(s: &str, line: usize) -> Result<T, DumpError> {
+    s.trim()
+        .parse()
+        .map_err(|_| DumpError::new(line, format!("invalid value '{}'", s.trim())))
+}
+
+fn parse_values<C: Cell>(s: &str, line: usize) -> Result<Vec<C>, DumpError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| parse_number(x, line))
+        .collect()
+}
+
+fn join<C: Cell>(values: &[C]) -> String {
+    values
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Disassembles the instruction at `addr`, cells that do not fit an `i64`
+/// are rendered as data.
+fn disassemble_cells<C: Cell>(mem: &[C], addr: usize) -> (String, usize) {
+    let cells: Vec<i64> = mem
+        .iter()
+        .skip(addr)
+        .take(4)
+        .map_while(Cell::to_i64)
+        .collect();
+    let fits = cells.len() == mem.len().saturating_sub(addr).min(4);
+    match disassemble_instruction(&cells, 0) {
+        (instr, len) if fits || len <= cells.len() => (instr, len),
+        _ => (
+            format!("data {}", mem.get(addr).cloned().unwrap_or_default()),
+            1,
+        ),
+    }
+}
+
+impl<C: Cell> fmt::Display for CrashDump<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# intcode crash dump")?;
+        if let Some(ref error) = self.error {
+            writeln!(f, "error: {}", error)?;
+        }
+        writeln!(f, "ip: {}", self.ip)?;
+        writeln!(f, "relative base: {}", self.relative_base)?;
+        writeln!(f, "halted: {}", self.halted)?;
+        writeln!(f, "instructions: {}", self.instruction_count)?;
+        writeln!(f, "last output: {}", self.last_output)?;
+        if self.pending_inputs.is_empty() {
+            writeln!(f, "inputs:")?;
+        } else {
+            writeln!(f, "inputs: {}", join(&self.pending_inputs))?;
+        }
+
+        writeln!(f, "\n[trace]")?;
+        for &(addr, ref cells) in &self.trace {
+            let (instr, _) = disassemble_cells(cells, 0);
+            writeln!(f, "{:>5}: {}  # {}", addr, join(cells), instr)?;
+        }
+
+        writeln!(f, "\n[io]")?;
+        for event in &self.io {
+            match *event {
+                Event::Input { step, ref value } => writeln!(f, "in {} {}", step, value)?,
+                Event::Output { step, ref value } => writeln!(f, "out {} {}", step, value)?,
+            }
+        }
+
+        writeln!(f, "\n[disassembly]")?;
+        let mut addr = self.disassembly_start();
+        let mut after = 0;
+        while after < DISASSEMBLY_AFTER && addr < self.mem.len().max(self.ip.saturating_add(1)) {
+            let (instr, len) = disassemble_cells(&self.mem, addr);
+            let marker = if addr == self.ip { ">" } else { " " };
+            writeln!(f, "{}{:>5}: {}", marker, addr, instr)?;
+            if addr >= self.ip {
+                after += 1;
+            }
+            addr = addr.saturating_add(len);
+        }
+
+        writeln!(f, "\n[memory]")?;
+        for chunk in self.mem.chunks(16) {
+            writeln!(f, "{}", join(chunk))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use alloc::string::{String, ToString};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+
+/// Errors that can happen while running a machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// An instruction produced a value that does not fit the cell type.
+    Overflow { ip: usize },
+    /// An unknown opcode was encountered.
+    InvalidOpcode { ip: usize, opcode: i64 },
+    /// An unknown parameter mode was encountered.
+    InvalidMode { ip: usize, mode: i64 },
+    /// An instruction referred to a negative or unaddressable cell.
+    InvalidAddress { ip: usize },
+    /// The instruction budget was used up.
+    BudgetExceeded { ip: usize },
+    /// A mapped device panicked during an earlier access.
+    DeviceFailed { ip: usize },
+}
+
+impl Error {
+    /// Returns the instruction pointer at which the error happened.
+    pub fn ip(&self) -> usize {
+        match *self {
+            Error::Overflow { ip }
+            | Error::InvalidOpcode { ip, .. }
+            | Error::InvalidMode { ip, .. }
+            | Error::InvalidAddress { ip }
+            | Error::BudgetExceeded { ip }
+            | Error::DeviceFailed { ip } => ip,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::Overflow { ip } => write!(f, "arithmetic overflow at ip {}", ip),
+            Error::InvalidOpcode { ip, opcode } => {
+                write!(f, "invalid opcode {} at ip {}", opcode, ip)
+            }
+            Error::InvalidMode { ip, mode } => {
+                write!(f, "invalid parameter mode {} at ip {}", mode, ip)
+            }
+            Error::InvalidAddress { ip } => write!(f, "invalid address at ip {}", ip),
+            Error::BudgetExceeded { ip } => write!(f, "instruction budget exceeded at ip {}", ip),
+            Error::DeviceFailed { ip } => write!(f, "mapped device failed at ip {}", ip),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {}
+
+/// A token that is not a number, see `parse_numbers`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumberError {
+    token: String,
+}
+
+impl NumberError {
+    pub(crate) fn new(token: &str) -> NumberError {
+        NumberError {
+            token: token.to_string(),
+        }
+    }
+
+    /// Returns the offending token.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid number '{}'", self.token)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for NumberError {}
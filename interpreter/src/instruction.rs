@@ -0,0 +1,237 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::error::Error;
+
+/// The mode of an instruction parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParameterMode {
+    /// The parameter is the address of the value.
+    Position,
+    /// The parameter is the value itself.
+    Immediate,
+    /// The parameter is an address relative to the relative base.
+    Relative,
+}
+
+impl ParameterMode {
+    /// Converts a mode digit into a parameter mode.
+    pub fn from_digit(digit: i64) -> Option<ParameterMode> {
+        match digit {
+            0 => Some(ParameterMode::Position),
+            1 => Some(ParameterMode::Immediate),
+            2 => Some(ParameterMode::Relative),
+            _ => None,
+        }
+    }
+
+    /// Returns the mode digit used in encoded instructions.
+    pub fn digit(self) -> i64 {
+        match self {
+            ParameterMode::Position => 0,
+            ParameterMode::Immediate => 1,
+            ParameterMode::Relative => 2,
+        }
+    }
+}
+
+/// A parameter of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Operand {
+    pub mode: ParameterMode,
+    pub value: i64,
+}
+
+impl Operand {
+    /// Creates a position mode operand.
+    pub fn position(value: i64) -> Operand {
+        Operand {
+            mode: ParameterMode::Position,
+            value,
+        }
+    }
+
+    /// Creates an immediate mode operand.
+    pub fn immediate(value: i64) -> Operand {
+        Operand {
+            mode: ParameterMode::Immediate,
+            value,
+        }
+    }
+
+    /// Creates a relative mode operand.
+    pub fn relative(value: i64) -> Operand {
+        Operand {
+            mode: ParameterMode::Relative,
+            value,
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            ParameterMode::Position => write!(f, "{}", self.value),
+            ParameterMode::Immediate => write!(f, "#{}", self.value),
+            ParameterMode::Relative => write!(f, "@{}", self.value),
+        }
+    }
+}
+
+/// A decoded instruction of the built-in instruction set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    Add(Operand, Operand, Operand),
+    Mul(Operand, Operand, Operand),
+    Input(Operand),
+    Output(Operand),
+    JumpIfTrue(Operand, Operand),
+    JumpIfFalse(Operand, Operand),
+    LessThan(Operand, Operand, Operand),
+    Equals(Operand, Operand, Operand),
+    AdjustBase(Operand),
+    Halt,
+}
+
+impl Instruction {
+    /// Returns the opcode without parameter modes.
+    pub fn opcode(&self) -> i64 {
+        match *self {
+            Instruction::Add(..) => 1,
+            Instruction::Mul(..) => 2,
+            Instruction::Input(..) => 3,
+            Instruction::Output(..) => 4,
+            Instruction::JumpIfTrue(..) => 5,
+            Instruction::JumpIfFalse(..) => 6,
+            Instruction::LessThan(..) => 7,
+            Instruction::Equals(..) => 8,
+            Instruction::AdjustBase(..) => 9,
+            Instruction::Halt => 99,
+        }
+    }
+
+    /// Returns the assembler mnemonic.
+    pub fn mnemonic(&self) -> &'static str {
+        match *self {
+            Instruction::Add(..) => "add",
+            Instruction::Mul(..) => "mul",
+            Instruction::Input(..) => "in",
+            Instruction::Output(..) => "out",
+            Instruction::JumpIfTrue(..) => "jt",
+            Instruction::JumpIfFalse(..) => "jf",
+            Instruction::LessThan(..) => "lt",
+            Instruction::Equals(..) => "eq",
+            Instruction::AdjustBase(..) => "arb",
+            Instruction::Halt => "hlt",
+        }
+    }
+
+    /// Returns the operands in order.
+    pub fn operands(&self) -> Vec<Operand> {
+        match *self {
+            Instruction::Add(a, b, c)
+            | Instruction::Mul(a, b, c)
+            | Instruction::LessThan(a, b, c)
+            | Instruction::Equals(a, b, c) => vec![a, b, c],
+            Instruction::JumpIfTrue(a, b) | Instruction::JumpIfFalse(a, b) => vec![a, b],
+            Instruction::Input(a) | Instruction::Output(a) | Instruction::AdjustBase(a) => {
+                vec![a]
+            }
+            Instruction::Halt => vec![],
+        }
+    }
+
+    /// Returns the number of cells the instruction occupies.
+    pub fn size(&self) -> usize {
+        self.operands().len() + 1
+    }
+
+    /// Returns the index of the operand the instruction writes to.
+    pub fn write_operand(&self) -> Option<usize> {
+        match *self {
+            Instruction::Add(..)
+            | Instruction::Mul(..)
+            | Instruction::LessThan(..)
+            | Instruction::Equals(..) => Some(2),
+            Instruction::Input(..) => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Appends the encoded instruction to a program.
+    pub fn encode_into(&self, program: &mut Vec<i64>) {
+        let operands = self.operands();
+        let mut cell = self.opcode();
+        let mut factor = 100;
+        for operand in &operands {
+            cell += operand.mode.digit() * factor;
+            factor *= 10;
+        }
+        program.push(cell);
+        program.extend(operands.iter().map(|x| x.value));
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+        for (idx, operand) in self.operands().iter().enumerate() {
+            write!(f, "{}{}", if idx == 0 { " " } else { ", " }, operand)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes the instruction at the given address.
+///
+/// Unknown opcodes, unknown modes and immediate mode for written operands
+/// are rejected.  Mode digits beyond the last operand are ignored like the
+/// machine does, so encoding such an instruction drops them.
+pub fn decode(mem: &[i64], ip: usize) -> Result<Instruction, Error> {
+    let cell = mem.get(ip).copied().unwrap_or(0);
+    let invalid_opcode = Error::InvalidOpcode { ip, opcode: cell };
+    if cell < 0 {
+        return Err(invalid_opcode);
+    }
+
+    let mut modes = cell / 100;
+    let mut operand = |off: usize, writes: bool| {
+        let digit = modes % 10;
+        modes /= 10;
+        let mode = ParameterMode::from_digit(digit)
+            .filter(|&x| !writes || x != ParameterMode::Immediate)
+            .ok_or(Error::InvalidMode { ip, mode: digit })?;
+        Ok(Operand {
+            mode,
+            value: ip
+                .checked_add(off)
+                .and_then(|x| mem.get(x))
+                .copied()
+                .unwrap_or(0),
+        })
+    };
+
+    Ok(match cell % 100 {
+        1 => Instruction::Add(operand(1, false)?, operand(2, false)?, operand(3, true)?),
+        2 => Instruction::Mul(operand(1, false)?, operand(2, false)?, operand(3, true)?),
+        3 => Instruction::Input(operand(1, true)?),
+        4 => Instruction::Output(operand(1, false)?),
+        5 => Instruction::JumpIfTrue(operand(1, false)?, operand(2, false)?),
+        6 => Instruction::JumpIfFalse(operand(1, false)?, operand(2, false)?),
+        7 => Instruction::LessThan(operand(1, false)?, operand(2, false)?, operand(3, true)?),
+        8 => Instruction::Equals(operand(1, false)?, operand(2, false)?, operand(3, true)?),
+        9 => Instruction::AdjustBase(operand(1, false)?),
+        99 => Instruction::Halt,
+        _ => return Err(invalid_opcode),
+    })
+}
+
+/// Encodes a sequence of instructions into a program.
+pub fn encode(instructions: &[Instruction]) -> Vec<i64> {
+    let mut rv = Vec::new();
+    for instruction in instructions {
+        instruction.encode_into(&mut rv);
+    }
+    rv
+}
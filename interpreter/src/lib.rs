@@ -1,53 +1,281 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::Debug;
+use core::ops::Range;
+
+mod asm;
+mod binary;
+mod bus;
+mod cell;
+mod cheat;
+mod compiler;
+#[cfg(feature = "async")]
+mod driver;
+mod dump;
+mod error;
+mod instruction;
+mod lint;
+mod macros;
+mod object;
+mod opcodes;
+mod optimize;
+mod outcome;
+mod patch;
+mod reference;
+#[cfg(feature = "std")]
+mod search;
+mod symbolic;
+mod transcript;
+
+pub use crate::asm::{assemble, disassemble, disassemble_instruction, format_assembly, AsmError};
+pub use crate::binary::{
+    decode_program, encode_program, is_binary_program, BinaryError, BINARY_MAGIC, BINARY_VERSION,
+};
+#[cfg(feature = "std")]
+pub use crate::binary::{read_binary_program, write_binary_program};
+use crate::bus::{Bus, Poisoned};
+pub use crate::bus::{Device, DeviceHandle};
+pub use crate::cell::Cell;
+pub use crate::cheat::{CellFilter, CheatFinder, MemorySnapshot};
+pub use crate::compiler::{compile, compile_to_assembly, CompileError};
+#[cfg(feature = "async")]
+pub use crate::driver::AsyncMachine;
+use crate::dump::History;
+pub use crate::dump::{CrashDump, DumpError};
+pub use crate::error::{Error, NumberError};
+pub use crate::instruction::{decode, encode, Instruction, Operand, ParameterMode};
+pub use crate::lint::{lint, lint_assembly, Lint, LintKind};
+pub use crate::object::{link, LinkError, Object, ObjectError, Relocation};
+pub use crate::opcodes::{Control, Opcode, DAY2_OPCODES, DAY5_OPCODES, DAY9_OPCODES};
+pub use crate::optimize::optimize;
+pub use crate::outcome::Outcome;
+pub use crate::patch::{Patch, PatchError};
+pub use crate::reference::{lockstep, Divergence, DivergenceKind, ReferenceMachine};
+#[cfg(feature = "std")]
+pub use crate::search::{
+    default_threads, parallel_find_map, parallel_max_by_key, Candidate, Run, Search,
+};
+pub use crate::symbolic::{Constraint, Expr, Path, Poly, SymbolicError, SymbolicMachine};
+pub use crate::transcript::{Event, Transcript, TranscriptError};
+
 pub fn parse_ascii_program(code: &str) -> Vec<i64> {
+    parse_ascii_program_as(code)
+}
+
+/// Formats a program as comma separated ASCII code.
+pub fn format_ascii_program(program: &[i64]) -> String {
+    let cells: Vec<_> = program.iter().map(|x| x.to_string()).collect();
+    cells.join(",")
+}
+
+/// Parses numbers separated by commas or whitespace.
+///
+/// Unlike `parse_ascii_program` this does not panic on malformed input
+/// but reports the first token that is not a number.
+pub fn parse_numbers(text: &str) -> Result<Vec<i64>, NumberError> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.parse().map_err(|_| NumberError::new(x)))
+        .collect()
+}
+
+/// Parses ASCII code into cells of an arbitrary cell type.
+pub fn parse_ascii_program_as<C: Cell>(code: &str) -> Vec<C>
+where
+    C::Err: Debug,
+{
     code.trim()
         .split(',')
         .map(|op| op.parse().unwrap())
         .collect()
 }
 
-#[derive(Default)]
-pub struct Machine {
-    mem: Vec<i64>,
-    inputs: Vec<i64>,
-    mem_input: i64,
-    output: i64,
-    relative_base: i64,
+/// The memory limit of machines that did not set one explicitly.
+///
+/// This is large enough for any real program but keeps a stray address
+/// from allocating gigabytes of memory.
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 24;
+
+#[derive(Debug, Clone, Default)]
+pub struct Machine<C: Cell = i64> {
+    mem: Vec<C>,
+    inputs: Vec<C>,
+    mem_input: C,
+    output: C,
+    relative_base: C,
     ip: usize,
     halted: bool,
+    instruction_count: u64,
+    opcodes: BTreeMap<i64, Opcode<C>>,
+    allowed_opcodes: Option<BTreeSet<i64>>,
+    memory_limit: Option<usize>,
+    instruction_budget: Option<u64>,
+    history: Option<History<C>>,
+    transcript: Option<Transcript<C>>,
+    bus: Bus<C>,
 }
 
 impl Machine {
+    /// Loads a machine from ASCII code.
+    pub fn from_ascii_program(code: &str) -> Machine {
+        Machine::new(&parse_ascii_program(code))
+    }
+
+    /// Loads a machine from a program in the compact binary format.
+    pub fn from_binary_program(data: &[u8]) -> Result<Machine, BinaryError> {
+        Ok(Machine::new(&decode_program(data)?))
+    }
+}
+
+impl<C: Cell> Machine<C> {
     /// Creates the machine from the given memory instruction slice.
-    pub fn new(mem: &[i64]) -> Machine {
+    pub fn new(mem: &[C]) -> Machine<C> {
         Machine {
             mem: mem.to_vec(),
             ..Default::default()
         }
     }
 
-    /// Loads a machine from ASCII code.
-    pub fn from_ascii_program(code: &str) -> Machine {
-        Machine::new(&parse_ascii_program(code))
+    /// Loads a machine with an arbitrary cell type from ASCII code.
+    pub fn from_ascii_program_as(code: &str) -> Machine<C>
+    where
+        C::Err: Debug,
+    {
+        Machine::new(&parse_ascii_program_as(code))
+    }
+
+    /// Creates a post-mortem dump of the machine.
+    ///
+    /// Pass the error that stopped the machine, if any.  The dump only
+    /// contains recent instructions and I/O if a history limit was set
+    /// with `set_history_limit`.
+    pub fn crash_dump(&self, error: Option<&Error>) -> CrashDump<C> {
+        CrashDump::new(self, error)
     }
 
     /// Feed some input into the machine.
-    pub fn feed(&mut self, value: i64) {
+    pub fn feed(&mut self, value: C) {
         self.inputs.push(value);
     }
 
     /// Sets memory input.
-    pub fn set_mem_input(&mut self, value: i64) {
+    pub fn set_mem_input(&mut self, value: C) {
         self.mem_input = value;
     }
 
+    /// Registers a custom opcode.
+    ///
+    /// Custom opcodes take precedence over the built-in ones so this can
+    /// also be used to change the behavior of an existing opcode.  If the
+    /// allowed opcodes are restricted the new opcode is added to them.
+    pub fn register_opcode(&mut self, opcode: Opcode<C>) {
+        if let Some(ref mut allowed) = self.allowed_opcodes {
+            allowed.insert(opcode.code());
+        }
+        self.opcodes.insert(opcode.code(), opcode);
+    }
+
+    /// Restricts the machine to the given opcodes.
+    ///
+    /// Executing any other opcode fails with an invalid opcode error.
+    pub fn restrict_opcodes(&mut self, opcodes: &[i64]) {
+        self.allowed_opcodes = Some(opcodes.iter().copied().collect());
+    }
+
+    /// Limits the addresses instructions can access.
+    ///
+    /// Instructions referring to an address at or above the limit fail
+    /// with an invalid address error instead of growing the memory.  The
+    /// default is `DEFAULT_MEMORY_LIMIT`.
+    pub fn set_memory_limit(&mut self, limit: usize) {
+        self.memory_limit = Some(limit);
+    }
+
+    /// Returns the memory limit.
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit.unwrap_or(DEFAULT_MEMORY_LIMIT)
+    }
+
+    /// Limits the total number of instructions the machine executes.
+    ///
+    /// Once the instruction count reaches the budget every further step
+    /// fails with a budget exceeded error.
+    pub fn set_instruction_budget(&mut self, budget: u64) {
+        self.instruction_budget = Some(budget);
+    }
+
+    /// Keeps the most recent instructions and I/O events for crash dumps.
+    ///
+    /// Up to `limit` instructions and as many I/O events are kept.  A limit
+    /// of zero turns the history off.  Changing the limit keeps the most
+    /// recent entries.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        match self.history {
+            _ if limit == 0 => self.history = None,
+            Some(ref mut history) => history.set_limit(limit),
+            None => self.history = Some(History::new(limit)),
+        }
+    }
+
+    /// Starts recording consumed inputs and produced outputs.
+    ///
+    /// Any transcript recorded so far is discarded.
+    pub fn start_recording(&mut self) {
+        self.transcript = Some(Transcript::new());
+    }
+
+    /// Returns the transcript recorded so far.
+    pub fn transcript(&self) -> Option<&Transcript<C>> {
+        self.transcript.as_ref()
+    }
+
+    /// Stops recording and returns the transcript.
+    pub fn take_transcript(&mut self) -> Option<Transcript<C>> {
+        self.transcript.take()
+    }
+
+    /// Maps a device into the given address range.
+    ///
+    /// All reads and writes through `mem_get` and `mem_set`, including the
+    /// ones performed by instructions, are routed through the device.  The
+    /// caller can keep a clone of the handle to inspect the device later.
+    /// Clones of the machine share the device.  Once the device panicked,
+    /// or while it is borrowed elsewhere without `std`, accesses fail with
+    /// `Error::DeviceFailed`.
+    ///
+    /// Panics if the range is empty or overlaps with an already mapped
+    /// device.
+    pub fn map_device<D: Device<C> + 'static>(
+        &mut self,
+        range: Range<usize>,
+        device: DeviceHandle<D>,
+    ) {
+        self.bus.map(range, device);
+    }
+
+    /// Removes the device mapped at the given address.
+    ///
+    /// Returns true if a device was mapped there.
+    pub fn unmap_device(&mut self, addr: usize) -> bool {
+        self.bus.unmap(addr)
+    }
+
     /// Returns an immutable view of the memory.
-    pub fn mem(&self) -> &[i64] {
+    ///
+    /// This is the raw memory which does not go through mapped devices.
+    pub fn mem(&self) -> &[C] {
         &self.mem
     }
 
     /// Returns the last output produced
-    pub fn last_output(&self) -> i64 {
-        self.output
+    pub fn last_output(&self) -> C {
+        self.output.clone()
     }
 
     /// Returns the instruction pointer.
@@ -55,139 +283,460 @@ impl Machine {
         self.ip
     }
 
+    /// Moves the instruction pointer.
+    ///
+    /// This also resumes a halted machine.
+    pub fn set_ip(&mut self, ip: usize) {
+        self.ip = ip;
+        self.halted = false;
+    }
+
+    /// Returns the relative base.
+    pub fn relative_base(&self) -> C {
+        self.relative_base.clone()
+    }
+
+    /// Returns the inputs that were fed but not consumed yet.
+    pub fn pending_inputs(&self) -> &[C] {
+        &self.inputs
+    }
+
+    /// Returns the number of instructions executed so far.
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
     /// Returns true if the machine halted.
     pub fn halted(&self) -> bool {
         self.halted
     }
 
     /// Runs until the machine stops returning the last output.
-    pub fn eval(&mut self) -> i64 {
+    pub fn eval(&mut self) -> C {
+        self.try_eval().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `eval` but returns an error instead of panicking.
+    pub fn try_eval(&mut self) -> Result<C, Error> {
         while !self.halted() {
-            self.step();
+            self.try_step()?;
         }
-        self.last_output()
+        Ok(self.last_output())
     }
 
     /// Runs until the machine stops returning a vector of outputs.
-    pub fn eval_multi(&mut self) -> Vec<i64> {
+    pub fn eval_multi(&mut self) -> Vec<C> {
+        self.try_eval_multi()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `eval_multi` but returns an error instead of panicking.
+    pub fn try_eval_multi(&mut self) -> Result<Vec<C>, Error> {
         let mut rv = Vec::new();
         loop {
-            self.step();
+            self.try_step()?;
             if self.halted() {
                 break;
             } else {
                 rv.push(self.last_output());
             }
         }
-        rv
+        Ok(rv)
     }
 
     /// Runs a single iteration until either output happens or the
     /// machine halts without output.
     pub fn step(&mut self) {
+        self.try_step().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `step` but returns an error instead of panicking.
+    pub fn try_step(&mut self) -> Result<(), Error> {
+        while !self.try_step_instruction()? {}
+        Ok(())
+    }
+
+    /// Executes a single instruction.
+    ///
+    /// Returns true if the instruction produced output or halted the
+    /// machine.
+    pub fn try_step_instruction(&mut self) -> Result<bool, Error> {
+        if self
+            .instruction_budget
+            .is_some_and(|budget| self.instruction_count >= budget)
+        {
+            return Err(Error::BudgetExceeded { ip: self.ip });
+        }
+        if self.history.is_some() {
+            let cells = (self.ip..self.ip.saturating_add(self.instruction_len()))
+                .map(|addr| self.mem_raw(addr))
+                .collect();
+            if let Some(ref mut history) = self.history {
+                history.push_instruction(self.ip, cells);
+            }
+        }
+        let done = self.execute()?;
+        self.instruction_count += 1;
+        Ok(done)
+    }
+
+    /// Runs until the predicate returns true or the machine halts.
+    ///
+    /// The predicate is checked before every instruction, including the
+    /// first one.
+    pub fn run_until<F: FnMut(&Machine<C>) -> bool>(&mut self, predicate: F) -> Outcome<C> {
+        self.try_run_until(predicate)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `run_until` but returns an error instead of panicking.
+    pub fn try_run_until<F: FnMut(&Machine<C>) -> bool>(
+        &mut self,
+        mut predicate: F,
+    ) -> Result<Outcome<C>, Error> {
+        self.run_bounded(|machine, _| predicate(machine))
+    }
+
+    /// Runs until the given number of outputs was produced.
+    pub fn run_for_outputs(&mut self, count: usize) -> Outcome<C> {
+        self.try_run_for_outputs(count)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `run_for_outputs` but returns an error instead of panicking.
+    pub fn try_run_for_outputs(&mut self, count: usize) -> Result<Outcome<C>, Error> {
+        self.run_bounded(|_, outputs| outputs >= count)
+    }
+
+    /// Runs until the next instruction reads input and none is queued.
+    ///
+    /// This ignores the value set with `set_mem_input`.
+    pub fn run_until_input_needed(&mut self) -> Outcome<C> {
+        self.try_run_until_input_needed()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `run_until_input_needed` but returns an error instead of panicking.
+    pub fn try_run_until_input_needed(&mut self) -> Result<Outcome<C>, Error> {
+        self.run_bounded(|machine, _| machine.needs_input())
+    }
+
+    /// Runs until the instruction pointer reaches the given address.
+    pub fn run_until_ip(&mut self, addr: usize) -> Outcome<C> {
+        self.try_run_until_ip(addr)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `run_until_ip` but returns an error instead of panicking.
+    pub fn try_run_until_ip(&mut self, addr: usize) -> Result<Outcome<C>, Error> {
+        self.run_bounded(|machine, _| machine.ip == addr)
+    }
+
+    /// Returns true if the next instruction reads input but none is queued.
+    ///
+    /// This ignores the value set with `set_mem_input`.
+    pub fn needs_input(&self) -> bool {
+        self.inputs.is_empty()
+            && !self.opcodes.contains_key(&3)
+            && self.mem_raw(self.ip).to_i64().map(|x| x % 100) == Some(3)
+    }
+
+    /// Executes instructions until `done` returns true for the machine and
+    /// the number of outputs so far, or the machine halts.
+    fn run_bounded<F: FnMut(&Machine<C>, usize) -> bool>(
+        &mut self,
+        mut done: F,
+    ) -> Result<Outcome<C>, Error> {
+        let mut outputs = Vec::new();
         loop {
-            match self.mem_get(self.ip) % 100 {
-                1 => {
-                    let a = self.arg(1);
-                    let b = self.arg(2);
-                    self.put(3, a + b);
-                    self.ip += 4;
-                }
-                2 => {
-                    let a = self.arg(1);
-                    let b = self.arg(2);
-                    self.put(3, a * b);
-                    self.ip += 4;
-                }
-                3 => {
-                    let input = if self.inputs.is_empty() {
-                        self.mem_input
-                    } else {
-                        self.inputs.remove(0)
-                    };
-                    self.put(1, input);
-                    self.ip += 2;
-                }
-                4 => {
-                    self.output = self.arg(1);
-                    self.ip += 2;
-                    return;
-                }
-                5 => {
-                    if self.arg(1) != 0 {
-                        self.ip = self.arg(2) as usize;
-                    } else {
-                        self.ip += 3;
-                    }
-                }
-                6 => {
-                    if self.arg(1) == 0 {
-                        self.ip = self.arg(2) as usize;
-                    } else {
-                        self.ip += 3;
-                    }
-                }
-                7 => {
-                    if self.arg(1) < self.arg(2) {
-                        self.put(3, 1);
-                    } else {
-                        self.put(3, 0);
-                    }
-                    self.ip += 4;
-                }
-                8 => {
-                    if self.arg(1) == self.arg(2) {
-                        self.put(3, 1);
-                    } else {
-                        self.put(3, 0);
-                    }
-                    self.ip += 4;
-                }
-                9 => {
-                    self.relative_base += self.arg(1);
-                    self.ip += 2;
+            if self.halted {
+                return Ok(Outcome::Halted(outputs));
+            }
+            if done(self, outputs.len()) {
+                return Ok(Outcome::Reached(outputs));
+            }
+            if self.try_step_instruction()? && !self.halted {
+                outputs.push(self.last_output());
+            }
+        }
+    }
+
+    fn execute(&mut self) -> Result<bool, Error> {
+        let opcode = self.mem_raw(self.ip).to_i64().ok_or(self.overflow())?;
+        if let Some(ref allowed) = self.allowed_opcodes {
+            if !allowed.contains(&(opcode % 100)) {
+                return Err(Error::InvalidOpcode {
+                    ip: self.ip,
+                    opcode,
+                });
+            }
+        }
+        if let Some(custom) = self.opcodes.get(&(opcode % 100)).cloned() {
+            return self.run_custom_opcode(&custom);
+        }
+        match opcode % 100 {
+            1 => {
+                let a = self.arg(1)?;
+                let b = self.arg(2)?;
+                let out = self.write_addr(3)?;
+                let rv = a.checked_add(&b).ok_or(self.overflow())?;
+                self.try_mem_set(out, rv)?;
+                self.ip += 4;
+            }
+            2 => {
+                let a = self.arg(1)?;
+                let b = self.arg(2)?;
+                let out = self.write_addr(3)?;
+                let rv = a.checked_mul(&b).ok_or(self.overflow())?;
+                self.try_mem_set(out, rv)?;
+                self.ip += 4;
+            }
+            3 => {
+                let input = match self.inputs.first() {
+                    Some(value) => value.clone(),
+                    None => self.mem_input.clone(),
+                };
+                self.put(1, input.clone())?;
+                if !self.inputs.is_empty() {
+                    self.inputs.remove(0);
                 }
-                99 => {
-                    self.halted = true;
-                    return;
+                self.record(|step| Event::Input { step, value: input });
+                self.ip += 2;
+            }
+            4 => {
+                self.output = self.arg(1)?;
+                self.record_output();
+                self.ip += 2;
+                return Ok(true);
+            }
+            5 => {
+                if !self.arg(1)?.is_zero() {
+                    self.ip = self.jump_target()?;
+                } else {
+                    self.ip += 3;
                 }
-                _ => {
-                    panic!("this should not happen");
+            }
+            6 => {
+                if self.arg(1)?.is_zero() {
+                    self.ip = self.jump_target()?;
+                } else {
+                    self.ip += 3;
                 }
             }
+            7 => {
+                let rv = self.arg(1)? < self.arg(2)?;
+                self.put(3, C::from_i64(rv as i64))?;
+                self.ip += 4;
+            }
+            8 => {
+                let rv = self.arg(1)? == self.arg(2)?;
+                self.put(3, C::from_i64(rv as i64))?;
+                self.ip += 4;
+            }
+            9 => {
+                let offset = self.arg(1)?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(&offset)
+                    .ok_or(self.overflow())?;
+                self.ip += 2;
+            }
+            99 => {
+                self.halted = true;
+                return Ok(true);
+            }
+            _ => {
+                return Err(Error::InvalidOpcode {
+                    ip: self.ip,
+                    opcode,
+                });
+            }
         }
+        Ok(false)
     }
 
-    pub fn mem_get(&self, addr: usize) -> i64 {
-        self.mem.get(addr).copied().unwrap_or(0)
+    /// Reads a memory cell through the device mapped there if any.
+    pub fn mem_get(&self, addr: usize) -> C {
+        self.try_mem_get(addr)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `mem_get` but returns an error instead of panicking.
+    pub fn try_mem_get(&self, addr: usize) -> Result<C, Error> {
+        let stored = self.mem_raw(addr);
+        match self.bus.read(addr, &stored) {
+            Ok(value) => Ok(value.unwrap_or(stored)),
+            Err(Poisoned) => Err(Error::DeviceFailed { ip: self.ip }),
+        }
     }
 
-    pub fn mem_set(&mut self, addr: usize, value: i64) {
-        self.mem.resize(self.mem.len().max(addr + 1), 0);
+    /// Writes a memory cell through the device mapped there if any.
+    ///
+    /// Panics if the address is at or above the memory limit.
+    pub fn mem_set(&mut self, addr: usize, value: C) {
+        self.try_mem_set(addr, value)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `mem_set` but returns an error instead of panicking.
+    ///
+    /// Writing at or above the memory limit fails with an invalid address
+    /// error.
+    pub fn try_mem_set(&mut self, addr: usize, value: C) -> Result<(), Error> {
+        if addr >= self.memory_limit() {
+            return Err(Error::InvalidAddress { ip: self.ip });
+        }
+        let value = self
+            .bus
+            .write(addr, value)
+            .map_err(|Poisoned| Error::DeviceFailed { ip: self.ip })?;
+        self.mem.resize(self.mem.len().max(addr + 1), C::default());
         self.mem[addr] = value;
+        Ok(())
+    }
+
+    /// Reads a memory cell without going through devices.
+    fn mem_raw(&self, addr: usize) -> C {
+        self.mem.get(addr).cloned().unwrap_or_default()
+    }
+
+    /// Runs a custom opcode and returns true if the step is over.
+    fn run_custom_opcode(&mut self, opcode: &Opcode<C>) -> Result<bool, Error> {
+        let mut args = Vec::with_capacity(opcode.arity());
+        for off in 1..=opcode.arity() {
+            args.push(if opcode.is_write(off) {
+                C::default()
+            } else {
+                self.arg(off)?
+            });
+        }
+        let mut targets = Vec::new();
+        for off in 1..=opcode.arity() {
+            if opcode.is_write(off) {
+                targets.push(self.write_addr(off)?);
+            }
+        }
+        let control = opcode.invoke(self, &mut args)?;
+        let writes = (1..=opcode.arity()).filter(|&off| opcode.is_write(off));
+        for (off, addr) in writes.zip(targets) {
+            self.try_mem_set(addr, args[off - 1].clone())?;
+        }
+        match control {
+            Control::Next => {
+                self.ip += opcode.arity() + 1;
+                Ok(false)
+            }
+            Control::Jump(addr) => {
+                self.ip = addr;
+                Ok(false)
+            }
+            Control::Output(value) => {
+                self.output = value;
+                self.record_output();
+                self.ip += opcode.arity() + 1;
+                Ok(true)
+            }
+            Control::Halt => {
+                self.halted = true;
+                Ok(true)
+            }
+        }
     }
 
-    fn get_mode(&self, arg: usize) -> i64 {
-        let arg_modes = self.mem[self.ip] / 100;
-        arg_modes / 10i64.pow((arg - 1) as u32) % 10
+    fn record<F: FnOnce(u64) -> Event<C>>(&mut self, event: F) {
+        if self.transcript.is_none() && self.history.is_none() {
+            return;
+        }
+        let event = event(self.instruction_count);
+        if let Some(ref mut history) = self.history {
+            history.push_event(event.clone());
+        }
+        if let Some(ref mut transcript) = self.transcript {
+            transcript.push(event);
+        }
     }
 
-    fn arg(&self, off: usize) -> i64 {
-        let val = self.mem_get(self.ip + off);
-        match self.get_mode(off) {
-            0 => self.mem_get(val as usize),
-            1 => val,
-            2 => self.mem_get((self.relative_base + val) as usize),
-            _ => panic!("wat"),
+    /// Returns the number of cells of the instruction at the ip.
+    fn instruction_len(&self) -> usize {
+        let opcode = self.mem_raw(self.ip).to_i64().unwrap_or(0) % 100;
+        if let Some(custom) = self.opcodes.get(&opcode) {
+            return custom.arity() + 1;
         }
+        match opcode {
+            1 | 2 | 7 | 8 => 4,
+            5 | 6 => 3,
+            3 | 4 | 9 => 2,
+            _ => 1,
+        }
+    }
+
+    fn record_output(&mut self) {
+        let value = self.output.clone();
+        self.record(|step| Event::Output { step, value });
+    }
+
+    fn overflow(&self) -> Error {
+        Error::Overflow { ip: self.ip }
+    }
+
+    fn get_mode(&self, arg: usize) -> Result<ParameterMode, Error> {
+        let arg_modes = self.mem_raw(self.ip).to_i64().ok_or(self.overflow())? / 100;
+        let mode = 10i64
+            .checked_pow((arg - 1) as u32)
+            .map_or(0, |x| arg_modes / x % 10);
+        ParameterMode::from_digit(mode).ok_or(Error::InvalidMode { ip: self.ip, mode })
+    }
+
+    fn addr(&self, value: &C) -> Result<usize, Error> {
+        value
+            .to_i64()
+            .and_then(|x| usize::try_from(x).ok())
+            .filter(|&x| x < self.memory_limit())
+            .ok_or(Error::InvalidAddress { ip: self.ip })
+    }
+
+    fn jump_target(&self) -> Result<usize, Error> {
+        let target = self.arg(2)?;
+        self.addr(&target)
+    }
+
+    fn arg(&self, off: usize) -> Result<C, Error> {
+        let val = self.mem_raw(self.ip + off);
+        match self.get_mode(off)? {
+            ParameterMode::Position => self.try_mem_get(self.addr(&val)?),
+            ParameterMode::Immediate => Ok(val),
+            ParameterMode::Relative => {
+                let addr = self
+                    .relative_base
+                    .checked_add(&val)
+                    .ok_or(self.overflow())?;
+                self.try_mem_get(self.addr(&addr)?)
+            }
+        }
+    }
+
+    fn write_addr(&self, off: usize) -> Result<usize, Error> {
+        let target = self.mem_raw(self.ip + off);
+        Ok(match self.get_mode(off)? {
+            ParameterMode::Position => self.addr(&target)?,
+            ParameterMode::Relative => {
+                let addr = self
+                    .relative_base
+                    .checked_add(&target)
+                    .ok_or(self.overflow())?;
+                self.addr(&addr)?
+            }
+            ParameterMode::Immediate => {
+                return Err(Error::InvalidMode {
+                    ip: self.ip,
+                    mode: 1,
+                })
+            }
+        })
     }
 
-    fn put(&mut self, off: usize, val: i64) {
-        let out = match self.get_mode(off) {
-            0 => self.mem_get(self.ip + off) as usize,
-            2 => (self.relative_base + self.mem_get(self.ip + off)) as usize,
-            _ => panic!("wat"),
-        };
-        self.mem_set(out, val);
+    fn put(&mut self, off: usize, val: C) -> Result<(), Error> {
+        let out = self.write_addr(off)?;
+        self.try_mem_set(out, val)
     }
 }
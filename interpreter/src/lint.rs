@@ -0,0 +1,334 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+
+use crate::asm::{assemble_unresolved, AsmError};
+use crate::instruction::{decode, Instruction, ParameterMode};
+use crate::optimize::{as_jump, constant, immediate};
+
+/// What the linter found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    /// An instruction writes to an immediate mode operand.
+    ImmediateWrite,
+    /// A jump goes to an address that does not hold an instruction.
+    InvalidJumpTarget { target: i64 },
+    /// An instruction reads a cell past the end of the program that
+    /// nothing writes to.
+    UninitializedRead { addr: i64 },
+    /// Instructions that can never run.
+    UnreachableCode,
+    /// A label that is never referred to.
+    UnusedLabel { name: String },
+}
+
+/// A likely mistake in a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    addr: usize,
+    line: Option<usize>,
+    kind: LintKind,
+}
+
+impl Lint {
+    /// Returns the address the lint belongs to.
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    /// Returns the one-based source line for lints of assembly.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Returns what was found.
+    pub fn kind(&self) -> &LintKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: ", line)?,
+            None => write!(f, "addr {}: ", self.addr)?,
+        }
+        match self.kind {
+            LintKind::ImmediateWrite => write!(f, "write to an immediate operand"),
+            LintKind::InvalidJumpTarget { target } => {
+                write!(f, "jump to {} which is not an instruction", target)
+            }
+            LintKind::UninitializedRead { addr } => {
+                write!(f, "read of uninitialized address {}", addr)
+            }
+            LintKind::UnreachableCode => write!(f, "unreachable code"),
+            LintKind::UnusedLabel { ref name } => write!(f, "unused label '{}'", name),
+        }
+    }
+}
+
+/// Decodes an instruction, reading an immediate written operand as
+/// position mode.
+///
+/// Also returns whether the written operand was immediate.
+fn decode_lenient(mem: &[i64], ip: usize) -> Option<(Instruction, bool)> {
+    if let Ok(instruction) = decode(mem, ip) {
+        return Some((instruction, false));
+    }
+    let cell = mem[ip];
+    let digit = match cell % 100 {
+        1 | 2 | 7 | 8 => 10000,
+        3 => 100,
+        _ => return None,
+    };
+    if cell / digit % 10 != 1 {
+        return None;
+    }
+    let mut cells = mem[ip..mem.len().min(ip + 4)].to_vec();
+    cells[0] -= digit;
+    decode(&cells, 0).ok().map(|x| (x, true))
+}
+
+/// The instructions reachable from a set of addresses.
+struct Reachable {
+    code: BTreeMap<usize, (Instruction, bool)>,
+    /// The immediate targets of the jumps by address.
+    jumps: BTreeMap<usize, i64>,
+    /// Whether a jump goes to an address only known at runtime.
+    computed: bool,
+}
+
+/// Finds the instructions reachable from the roots.
+///
+/// Cells outside of `written` are known to hold their initial value, if
+/// it is `None` any cell could have been written to.
+fn explore(
+    mem: &[i64],
+    roots: &[usize],
+    starts: Option<&BTreeSet<usize>>,
+    written: Option<&BTreeSet<usize>>,
+) -> Reachable {
+    let known = |operand| match written {
+        Some(written) => constant(mem, written, operand),
+        None => immediate(operand),
+    };
+    let mut rv = Reachable {
+        code: BTreeMap::new(),
+        jumps: BTreeMap::new(),
+        computed: false,
+    };
+    let mut todo = roots.to_vec();
+    while let Some(ip) = todo.pop() {
+        if ip >= mem.len() || rv.code.contains_key(&ip) {
+            continue;
+        }
+        let (instruction, immediate_write) = match decode_lenient(mem, ip) {
+            Some(decoded) => decoded,
+            None => continue,
+        };
+        rv.code.insert(ip, (instruction, immediate_write));
+        if let Some((cond, target, if_true)) = as_jump(&instruction) {
+            let taken = known(cond).map(|x| (x != 0) == if_true);
+            if taken != Some(false) {
+                match known(target) {
+                    Some(target) => {
+                        rv.jumps.insert(ip, target);
+                        // leave invalid targets alone instead of decoding data
+                        let valid = usize::try_from(target)
+                            .ok()
+                            .filter(|&x| x < mem.len() && decode_lenient(mem, x).is_some())
+                            .filter(|x| starts.is_none_or(|starts| starts.contains(x)));
+                        todo.extend(valid);
+                    }
+                    None => rv.computed = true,
+                }
+            }
+            if taken == Some(true) {
+                continue;
+            }
+        }
+        if instruction != Instruction::Halt {
+            todo.push(ip + instruction.size());
+        }
+    }
+    rv
+}
+
+/// Returns the cells written with position mode and whether any
+/// instruction writes with relative mode.
+fn writes(reachable: &Reachable) -> (BTreeSet<usize>, bool) {
+    let mut written = BTreeSet::new();
+    let mut relative = false;
+    for &(instruction, _) in reachable.code.values() {
+        if let Some(idx) = instruction.write_operand() {
+            let operand = instruction.operands()[idx];
+            match operand.mode {
+                ParameterMode::Relative => relative = true,
+                _ => written.extend(usize::try_from(operand.value).ok()),
+            }
+        }
+    }
+    (written, relative)
+}
+
+/// Explores the program until the written cells agree with the
+/// reachable code.
+///
+/// Marking a cell as written can turn a known jump target into an unknown
+/// one and make code unreachable, so the written cells are accumulated
+/// across rounds instead of replaced to make sure this terminates.
+fn reach(
+    mem: &[i64],
+    roots: &[usize],
+    starts: Option<&BTreeSet<usize>>,
+) -> (Reachable, BTreeSet<usize>, bool) {
+    let mut written = BTreeSet::new();
+    let mut relative = false;
+    loop {
+        let reachable = explore(mem, roots, starts, Some(&written).filter(|_| !relative));
+        let (now_written, now_relative) = writes(&reachable);
+        let before = written.len();
+        written.extend(now_written);
+        if written.len() == before && (relative || !now_relative) {
+            return (reachable, written, relative);
+        }
+        relative |= now_relative;
+    }
+}
+
+/// Lints a program, optionally knowing where its instructions start.
+///
+/// `taken` are the instructions whose address is used as a value and
+/// could be the target of a computed jump.
+fn check(mem: &[i64], starts: Option<&BTreeSet<usize>>, taken: &[usize]) -> Vec<Lint> {
+    let (mut reachable, mut written, mut relative_writes) = reach(mem, &[0], starts);
+    if reachable.computed && !taken.is_empty() {
+        let mut roots = vec![0];
+        roots.extend_from_slice(taken);
+        let (more, more_written, more_relative) = reach(mem, &roots, starts);
+        reachable = more;
+        written = more_written;
+        relative_writes = more_relative;
+    }
+
+    let mut rv = vec![];
+    let mut lint = |addr, kind| {
+        rv.push(Lint {
+            addr,
+            line: None,
+            kind,
+        })
+    };
+
+    // the cells holding operands of reachable instructions
+    let mut operands = BTreeSet::new();
+    for (&ip, &(instruction, _)) in &reachable.code {
+        operands.extend(ip + 1..ip + instruction.size());
+    }
+    // without known instruction boundaries code that patches itself could
+    // jump anywhere
+    let self_modifying = written
+        .iter()
+        .any(|x| operands.contains(x) || reachable.code.contains_key(x));
+
+    for (&ip, &(instruction, immediate_write)) in &reachable.code {
+        if immediate_write {
+            lint(ip, LintKind::ImmediateWrite);
+        }
+        let write = instruction.write_operand();
+        for (idx, operand) in instruction.operands().into_iter().enumerate() {
+            if Some(idx) == write || operand.mode != ParameterMode::Position {
+                continue;
+            }
+            let addr = operand.value;
+            let unwritten =
+                usize::try_from(addr).is_ok_and(|x| x >= mem.len() && !written.contains(&x));
+            // relative writes could go anywhere past the program
+            if unwritten && !relative_writes {
+                lint(ip, LintKind::UninitializedRead { addr });
+            }
+        }
+        if let Some(&target) = reachable.jumps.get(&ip) {
+            let valid = usize::try_from(target).is_ok_and(|x| match starts {
+                Some(starts) => starts.contains(&x),
+                None if self_modifying => x < mem.len(),
+                None => reachable.code.contains_key(&x) && !operands.contains(&x),
+            });
+            if !valid {
+                lint(ip, LintKind::InvalidJumpTarget { target });
+            }
+        }
+    }
+
+    // without instruction boundaries data could not be told apart from
+    // unreachable code
+    if let Some(starts) = starts {
+        let mut previous_unreachable = false;
+        for &ip in starts {
+            let unreachable = !reachable.code.contains_key(&ip);
+            if unreachable && !previous_unreachable {
+                lint(ip, LintKind::UnreachableCode);
+            }
+            previous_unreachable = unreachable;
+        }
+    }
+    rv
+}
+
+/// Lints a program.
+///
+/// Follows the control flow from address zero and reports instructions
+/// that write to immediate operands, jumps to addresses that are no
+/// instructions and reads of cells past the end of the program that are
+/// never written to.  Jumps to targets computed at runtime are not
+/// followed and programs that modify their own code can jump anywhere
+/// within the program.  The lints are ordered by address.
+pub fn lint(program: &[i64]) -> Vec<Lint> {
+    let mut rv = check(program, None, &[]);
+    rv.sort_by_key(|x| x.addr);
+    rv
+}
+
+/// Lints assembly source code.
+///
+/// In addition to what `lint` finds this knows where instructions start,
+/// so jumps to data and unreachable instructions are reported as well as
+/// labels that are never used.  Labels of instructions that are used as
+/// values are considered possible targets of computed jumps.  The lints
+/// are ordered by line.
+pub fn lint_assembly(source: &str) -> Result<Vec<Lint>, AsmError> {
+    let mut assembly = assemble_unresolved(source)?;
+    assembly.resolve()?;
+
+    let lines: BTreeMap<_, _> = assembly.instructions.iter().copied().collect();
+    let starts: BTreeSet<_> = lines.keys().copied().collect();
+    let taken: Vec<_> = assembly
+        .fixups
+        .iter()
+        .filter_map(|&(_, name, offset, _)| usize::try_from(assembly.labels[name] + offset).ok())
+        .filter(|x| starts.contains(x))
+        .collect();
+
+    let mut rv = check(&assembly.code, Some(&starts), &taken);
+    for lint in &mut rv {
+        lint.line = lines.get(&lint.addr).copied();
+    }
+
+    let used: BTreeSet<_> = assembly.fixups.iter().map(|x| x.1).collect();
+    for (&name, &addr) in &assembly.labels {
+        if !used.contains(name) && !assembly.globals.contains(&name) {
+            rv.push(Lint {
+                addr: addr as usize,
+                line: Some(assembly.label_lines[name]),
+                kind: LintKind::UnusedLabel {
+                    name: name.to_string(),
+                },
+            });
+        }
+    }
+    rv.sort_by_key(|x| (x.line, x.addr));
+    Ok(rv)
+}
@@ -0,0 +1,183 @@
+/// Assembles an Intcode program at compile time.
+///
+/// This accepts the same mnemonics as the assembler, with every
+/// instruction terminated by a semicolon.  Plain operands use position
+/// mode, `#` prefixes immediate and `@` relative operands.  Labels are
+/// declared as `name:` and can be used in any operand, including simple
+/// arithmetic like `name + 1`.  The result is a `&'static [i64]`:
+///
+/// ```
+/// # use interpreter::{intcode, Machine};
+/// let program = intcode! {
+///     start:
+///         in value;
+///         jf value, #end;
+///         mul value, #2, value;
+///         out value;
+///         jt #1, #start;
+///     end:
+///         hlt;
+///     value:
+///         data 0;
+/// };
+/// let mut machine = Machine::new(program);
+/// machine.feed(21);
+/// machine.feed(0);
+/// assert_eq!(machine.eval_multi(), vec![42]);
+/// ```
+#[macro_export]
+macro_rules! intcode {
+    ($($body:tt)*) => {
+        $crate::__intcode_parse!([] $($body)*)
+    };
+}
+
+/// Splits the macro input into labels and instructions.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __intcode_parse {
+    ([$($items:tt)*]) => {{
+        $crate::__intcode_labels!((0); $($items)*);
+        const __INTCODE_PROGRAM: &[i64] = &$crate::__intcode_cells!([] $($items)*);
+        __INTCODE_PROGRAM
+    }};
+    ([$($items:tt)*] $name:ident : $($rest:tt)*) => {
+        $crate::__intcode_parse!([$($items)* {label $name}] $($rest)*)
+    };
+    ([$($items:tt)*] $mnemonic:tt $($rest:tt)*) => {
+        $crate::__intcode_parse!(@instr [$($items)*] $mnemonic [] [] $($rest)*)
+    };
+    (@instr [$($items:tt)*] $m:tt [$($ops:tt)*] [] ; $($rest:tt)*) => {
+        $crate::__intcode_parse!([$($items)* {$m $($ops)*}] $($rest)*)
+    };
+    (@instr [$($items:tt)*] $m:tt [$($ops:tt)*] [$($cur:tt)+] ; $($rest:tt)*) => {
+        $crate::__intcode_parse!([$($items)* {$m $($ops)* [$($cur)+]}] $($rest)*)
+    };
+    (@instr [$($items:tt)*] $m:tt [$($ops:tt)*] [$($cur:tt)+] , $($rest:tt)*) => {
+        $crate::__intcode_parse!(@instr [$($items)*] $m [$($ops)* [$($cur)+]] [] $($rest)*)
+    };
+    (@instr [$($items:tt)*] $m:tt [$($ops:tt)*] [$($cur:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::__intcode_parse!(@instr [$($items)*] $m [$($ops)*] [$($cur)* $t] $($rest)*)
+    };
+}
+
+/// Declares a constant for every label and checks operand counts.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __intcode_labels {
+    ($offset:expr;) => {};
+    ($offset:expr; {label $name:ident} $($rest:tt)*) => {
+        #[allow(non_upper_case_globals)]
+        const $name: i64 = $offset;
+        $crate::__intcode_labels!($offset; $($rest)*);
+    };
+    ($offset:expr; {data $($op:tt)*} $($rest:tt)*) => {
+        $crate::__intcode_labels!(($offset $(+ $crate::__intcode_one!($op))*); $($rest)*);
+    };
+    ($offset:expr; {$m:tt $($op:tt)*} $($rest:tt)*) => {
+        const _: () = assert!(
+            $crate::__intcode_opcode!($m).1 == 0 $(+ $crate::__intcode_one!($op))*,
+            concat!("wrong number of operands for ", stringify!($m))
+        );
+        $crate::__intcode_labels!(($offset + 1 $(+ $crate::__intcode_one!($op))*); $($rest)*);
+    };
+}
+
+/// Emits the cells of the program.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __intcode_cells {
+    ([$($acc:tt)*]) => {
+        [$($acc)*]
+    };
+    ([$($acc:tt)*] {label $name:ident} $($rest:tt)*) => {
+        $crate::__intcode_cells!([$($acc)*] $($rest)*)
+    };
+    ([$($acc:tt)*] {data $($op:tt)*} $($rest:tt)*) => {
+        $crate::__intcode_cells!([$($acc)* $($crate::__intcode_value!($op),)*] $($rest)*)
+    };
+    ([$($acc:tt)*] {$m:tt $($op:tt)*} $($rest:tt)*) => {
+        $crate::__intcode_cells!([
+            $($acc)*
+            $crate::__intcode_opcode!($m).0 + $crate::__intcode_modes!(100; $($op)*),
+            $($crate::__intcode_value!($op),)*
+        ] $($rest)*)
+    };
+}
+
+/// Returns the opcode and arity of a mnemonic.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __intcode_opcode {
+    (add) => {
+        (1i64, 3)
+    };
+    (mul) => {
+        (2i64, 3)
+    };
+    (in) => {
+        (3i64, 1)
+    };
+    (out) => {
+        (4i64, 1)
+    };
+    (jt) => {
+        (5i64, 2)
+    };
+    (jf) => {
+        (6i64, 2)
+    };
+    (lt) => {
+        (7i64, 3)
+    };
+    (eq) => {
+        (8i64, 3)
+    };
+    (arb) => {
+        (9i64, 1)
+    };
+    (hlt) => {
+        (99i64, 0)
+    };
+}
+
+/// Computes the parameter mode digits of the operands.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __intcode_modes {
+    ($factor:expr;) => {
+        0
+    };
+    ($factor:expr; [# $($value:tt)*] $($rest:tt)*) => {
+        $factor + $crate::__intcode_modes!($factor * 10; $($rest)*)
+    };
+    ($factor:expr; [@ $($value:tt)*] $($rest:tt)*) => {
+        2 * $factor + $crate::__intcode_modes!($factor * 10; $($rest)*)
+    };
+    ($factor:expr; [$($value:tt)*] $($rest:tt)*) => {
+        $crate::__intcode_modes!($factor * 10; $($rest)*)
+    };
+}
+
+/// Returns the value of an operand without its mode.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __intcode_value {
+    ([# $($value:tt)*]) => {
+        ($($value)*)
+    };
+    ([@ $($value:tt)*]) => {
+        ($($value)*)
+    };
+    ([$($value:tt)*]) => {
+        ($($value)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __intcode_one {
+    ($op:tt) => {
+        1
+    };
+}
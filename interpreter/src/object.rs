@@ -0,0 +1,335 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+
+use crate::asm::{assemble_unresolved, is_label, label_offset, AsmError};
+
+/// A cell of an object that has to be adjusted when linking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Relocation {
+    /// The cell holds an address within the object and is moved along
+    /// with it.
+    Local { offset: usize },
+    /// The cell holds an offset that is added to the address of a symbol
+    /// exported by another object.
+    External { offset: usize, name: String },
+}
+
+impl Relocation {
+    /// Returns the offset of the relocated cell within the object.
+    pub fn offset(&self) -> usize {
+        match *self {
+            Relocation::Local { offset } | Relocation::External { offset, .. } => offset,
+        }
+    }
+}
+
+/// An error produced when parsing the text form of an object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectError {
+    line: usize,
+    message: String,
+}
+
+impl ObjectError {
+    fn new<S: Into<String>>(line: usize, message: S) -> ObjectError {
+        ObjectError {
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the one-based line the error belongs to.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for ObjectError {}
+
+/// An error produced by the linker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// More than one object exports the symbol.
+    DuplicateSymbol { name: String },
+    /// No object exports a referenced symbol.
+    UndefinedSymbol { name: String },
+    /// Relocating the cell at the address of the linked program overflows.
+    Overflow { addr: usize },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            LinkError::DuplicateSymbol { ref name } => write!(f, "duplicate symbol '{}'", name),
+            LinkError::UndefinedSymbol { ref name } => write!(f, "undefined symbol '{}'", name),
+            LinkError::Overflow { addr } => write!(f, "relocating address {} overflows", addr),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for LinkError {}
+
+/// A relocatable fragment of Intcode.
+///
+/// Objects hold code assembled as if it started at address zero, the
+/// symbols they export and the cells that refer to addresses.  `link`
+/// places objects after each other and fixes up those cells.  Only
+/// references to labels are relocated, numeric addresses like the one in
+/// `in 100` stay absolute addresses of the linked program.
+///
+/// Objects have a text form that can be parsed again:
+///
+/// ```text
+/// # intcode object
+/// [symbols]
+/// double 0
+///
+/// [relocations]
+/// 1
+/// 3
+/// 6 double_ret
+///
+/// [code]
+/// 1002,7,2,7,105,1,0,0
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Object {
+    code: Vec<i64>,
+    symbols: BTreeMap<String, usize>,
+    relocations: Vec<Relocation>,
+}
+
+impl Object {
+    /// Assembles source code into an object.
+    ///
+    /// Labels named in a `global` directive are exported and references
+    /// to labels that are not defined become external relocations:
+    ///
+    /// ```text
+    ///         global double
+    /// double: mul value, #2, value
+    ///         jt #1, double_ret
+    /// value:  data 0
+    /// ```
+    pub fn assemble(source: &str) -> Result<Object, AsmError> {
+        let assembly = assemble_unresolved(source)?;
+        let labels = assembly.labels;
+        let mut code = assembly.code;
+        let mut relocations = Vec::with_capacity(assembly.fixups.len());
+        for (addr, name, offset, lineno) in assembly.fixups {
+            match labels.get(name) {
+                Some(&target) => {
+                    code[addr] = label_offset(target, name, offset, lineno)?;
+                    relocations.push(Relocation::Local { offset: addr });
+                }
+                None => {
+                    code[addr] = offset;
+                    relocations.push(Relocation::External {
+                        offset: addr,
+                        name: name.to_string(),
+                    });
+                }
+            }
+        }
+        let symbols = assembly
+            .globals
+            .into_iter()
+            .map(|name| (name.to_string(), labels[name] as usize))
+            .collect();
+        Ok(Object {
+            code,
+            symbols,
+            relocations,
+        })
+    }
+
+    /// Returns the code as if the object was placed at address zero.
+    pub fn code(&self) -> &[i64] {
+        &self.code
+    }
+
+    /// Returns the exported symbols with their offset in the object.
+    pub fn symbols(&self) -> &BTreeMap<String, usize> {
+        &self.symbols
+    }
+
+    /// Returns the relocations in the order of their cells.
+    pub fn relocations(&self) -> &[Relocation] {
+        &self.relocations
+    }
+
+    /// Returns the names of the symbols the object needs from others.
+    pub fn undefined_symbols(&self) -> Vec<&str> {
+        let mut rv: Vec<_> = self
+            .relocations
+            .iter()
+            .filter_map(|x| match *x {
+                Relocation::External { ref name, .. } => Some(name.as_str()),
+                Relocation::Local { .. } => None,
+            })
+            .collect();
+        rv.sort_unstable();
+        rv.dedup();
+        rv
+    }
+
+    /// Parses the text form of an object.
+    pub fn parse(source: &str) -> Result<Object, ObjectError> {
+        let mut rv = Object::default();
+        let mut section = "";
+        let mut checks = Vec::new();
+
+        for (idx, line) in source.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = &line[1..line.len() - 1];
+                if !["symbols", "relocations", "code"].contains(&section) {
+                    return Err(ObjectError::new(
+                        line_no,
+                        format!("unknown section '{}'", section),
+                    ));
+                }
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            match section {
+                "symbols" => {
+                    let (name, offset) = match (parts.next(), parts.next(), parts.next()) {
+                        (Some(name), Some(offset), None) if is_label(name) => {
+                            (name, parse_number(offset, line_no)?)
+                        }
+                        _ => return Err(ObjectError::new(line_no, "expected name and offset")),
+                    };
+                    if rv.symbols.insert(name.to_string(), offset).is_some() {
+                        return Err(ObjectError::new(
+                            line_no,
+                            format!("duplicate symbol '{}'", name),
+                        ));
+                    }
+                    // symbols may point right past the end of the code
+                    checks.push((offset, line_no, true));
+                }
+                "relocations" => {
+                    let offset = parse_number(parts.next().unwrap(), line_no)?;
+                    rv.relocations.push(match (parts.next(), parts.next()) {
+                        (None, _) => Relocation::Local { offset },
+                        (Some(name), None) if is_label(name) => Relocation::External {
+                            offset,
+                            name: name.to_string(),
+                        },
+                        _ => return Err(ObjectError::new(line_no, "expected offset and name")),
+                    });
+                    checks.push((offset, line_no, false));
+                }
+                "code" => {
+                    for value in line.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+                        rv.code.push(parse_number(value, line_no)?);
+                    }
+                }
+                _ => return Err(ObjectError::new(line_no, "expected a section")),
+            }
+        }
+
+        for (offset, line_no, inclusive) in checks {
+            if offset > rv.code.len() || (offset == rv.code.len() && !inclusive) {
+                return Err(ObjectError::new(
+                    line_no,
+                    format!("offset {} is outside the code", offset),
+                ));
+            }
+        }
+        Ok(rv)
+    }
+}
+
+fn parse_number<T: core::str::FromStr>(s: &str, line: usize) -> Result<T, ObjectError> {
+    s.parse()
+        .map_err(|_| ObjectError::new(line, format!("invalid value '{}'", s)))
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# intcode object")?;
+        writeln!(f, "[symbols]")?;
+        for (name, offset) in &self.symbols {
+            writeln!(f, "{} {}", name, offset)?;
+        }
+        writeln!(f, "\n[relocations]")?;
+        for relocation in &self.relocations {
+            match *relocation {
+                Relocation::Local { offset } => writeln!(f, "{}", offset)?,
+                Relocation::External { offset, ref name } => writeln!(f, "{} {}", offset, name)?,
+            }
+        }
+        writeln!(f, "\n[code]")?;
+        for chunk in self.code.chunks(16) {
+            let values: Vec<_> = chunk.iter().map(|x| x.to_string()).collect();
+            writeln!(f, "{}", values.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Links objects into a program.
+///
+/// The objects are placed after each other in the given order, so the
+/// first one holds the entry point at address zero.  Every exported symbol
+/// has to be unique and every external reference has to be exported by
+/// one of the objects.  Numeric addresses are not relocated, see
+/// `Object`.
+pub fn link(objects: &[Object]) -> Result<Vec<i64>, LinkError> {
+    let mut symbols = BTreeMap::new();
+    let mut base = 0;
+    for object in objects {
+        for (name, &offset) in &object.symbols {
+            if symbols
+                .insert(name.as_str(), (base + offset) as i64)
+                .is_some()
+            {
+                return Err(LinkError::DuplicateSymbol { name: name.clone() });
+            }
+        }
+        base += object.code.len();
+    }
+
+    let mut rv = Vec::with_capacity(base);
+    for object in objects {
+        let base = rv.len();
+        rv.extend_from_slice(&object.code);
+        for relocation in &object.relocations {
+            let addr = match *relocation {
+                Relocation::Local { .. } => base as i64,
+                Relocation::External { ref name, .. } => *symbols
+                    .get(name.as_str())
+                    .ok_or_else(|| LinkError::UndefinedSymbol { name: name.clone() })?,
+            };
+            let cell = base + relocation.offset();
+            rv[cell] = rv[cell]
+                .checked_add(addr)
+                .ok_or(LinkError::Overflow { addr: cell })?;
+        }
+    }
+    Ok(rv)
+}
@@ -0,0 +1,113 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::cell::Cell;
+use crate::error::Error;
+use crate::Machine;
+
+/// Opcodes understood by the day 2 machine.
+pub const DAY2_OPCODES: &[i64] = &[1, 2, 99];
+
+/// Opcodes understood by the day 5 machine.
+pub const DAY5_OPCODES: &[i64] = &[1, 2, 3, 4, 5, 6, 7, 8, 99];
+
+/// Opcodes understood by the complete day 9 machine.
+pub const DAY9_OPCODES: &[i64] = &[1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+
+/// Tells the machine what to do after a custom opcode ran.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Control<C> {
+    /// Continue with the next instruction.
+    Next,
+    /// Continue execution at the given address.
+    Jump(usize),
+    /// Produce an output and continue with the next instruction.
+    Output(C),
+    /// Halt the machine.
+    Halt,
+}
+
+type Handler<C> = dyn Fn(&mut Machine<C>, &mut [C]) -> Result<Control<C>, Error> + Send + Sync;
+
+/// A custom opcode that can be registered on a machine.
+///
+/// The handler is invoked with one argument per operand.  Read operands
+/// are resolved to their values according to their parameter modes, write
+/// operands start out as zero and whatever the handler leaves in them is
+/// stored to the address they refer to.
+pub struct Opcode<C: Cell> {
+    code: i64,
+    arity: usize,
+    writes: Vec<usize>,
+    handler: Arc<Handler<C>>,
+}
+
+impl<C: Cell> Opcode<C> {
+    /// Creates a new opcode.
+    ///
+    /// `writes` lists the one-based positions of the operands that are
+    /// written to rather than read from.  An instruction cell has room
+    /// for the modes of at most 18 operands.
+    pub fn new<F>(code: i64, arity: usize, writes: &[usize], handler: F) -> Opcode<C>
+    where
+        F: Fn(&mut Machine<C>, &mut [C]) -> Result<Control<C>, Error> + Send + Sync + 'static,
+    {
+        assert!((0..100).contains(&code), "opcodes must be between 0 and 99");
+        assert!(arity <= 18, "opcodes take at most 18 operands");
+        assert!(
+            writes.iter().all(|&x| x >= 1 && x <= arity),
+            "write operand out of range"
+        );
+        Opcode {
+            code,
+            arity,
+            writes: writes.to_vec(),
+            handler: Arc::new(handler),
+        }
+    }
+
+    /// Returns the opcode number.
+    pub fn code(&self) -> i64 {
+        self.code
+    }
+
+    /// Returns the number of operands.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// Returns true if the given one-based operand is written to.
+    pub fn is_write(&self, off: usize) -> bool {
+        self.writes.contains(&off)
+    }
+
+    pub(crate) fn invoke(
+        &self,
+        machine: &mut Machine<C>,
+        args: &mut [C],
+    ) -> Result<Control<C>, Error> {
+        (self.handler)(machine, args)
+    }
+}
+
+impl<C: Cell> Clone for Opcode<C> {
+    fn clone(&self) -> Opcode<C> {
+        Opcode {
+            code: self.code,
+            arity: self.arity,
+            writes: self.writes.clone(),
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+impl<C: Cell> fmt::Debug for Opcode<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Opcode")
+            .field("code", &self.code)
+            .field("arity", &self.arity)
+            .field("writes", &self.writes)
+            .finish()
+    }
+}
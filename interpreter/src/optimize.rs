@@ -0,0 +1,402 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::instruction::{decode, Instruction, Operand, ParameterMode};
+
+/// Returns the value of an operand if it is known before running.
+///
+/// Position mode operands are known if nothing writes to their cell.
+pub(crate) fn constant(mem: &[i64], written: &BTreeSet<usize>, operand: Operand) -> Option<i64> {
+    match operand.mode {
+        ParameterMode::Immediate => Some(operand.value),
+        ParameterMode::Position => {
+            let addr = usize::try_from(operand.value).ok()?;
+            if written.contains(&addr) {
+                None
+            } else {
+                Some(mem.get(addr).copied().unwrap_or(0))
+            }
+        }
+        ParameterMode::Relative => None,
+    }
+}
+
+pub(crate) fn immediate(operand: Operand) -> Option<i64> {
+    match operand.mode {
+        ParameterMode::Immediate => Some(operand.value),
+        _ => None,
+    }
+}
+
+/// Returns condition, target and whether the jump is taken on true.
+pub(crate) fn as_jump(instruction: &Instruction) -> Option<(Operand, Operand, bool)> {
+    match *instruction {
+        Instruction::JumpIfTrue(cond, target) => Some((cond, target, true)),
+        Instruction::JumpIfFalse(cond, target) => Some((cond, target, false)),
+        _ => None,
+    }
+}
+
+/// Returns true for jumps that are always taken.
+fn is_unconditional(instruction: &Instruction) -> bool {
+    match as_jump(instruction) {
+        Some((cond, _, if_true)) => immediate(cond).is_some_and(|x| (x != 0) == if_true),
+        None => false,
+    }
+}
+
+/// Returns the instruction with every operand passed through a function.
+fn map_operands<F: FnMut(Operand) -> Operand>(instruction: Instruction, mut f: F) -> Instruction {
+    match instruction {
+        Instruction::Add(a, b, c) => Instruction::Add(f(a), f(b), f(c)),
+        Instruction::Mul(a, b, c) => Instruction::Mul(f(a), f(b), f(c)),
+        Instruction::Input(a) => Instruction::Input(f(a)),
+        Instruction::Output(a) => Instruction::Output(f(a)),
+        Instruction::JumpIfTrue(a, b) => Instruction::JumpIfTrue(f(a), f(b)),
+        Instruction::JumpIfFalse(a, b) => Instruction::JumpIfFalse(f(a), f(b)),
+        Instruction::LessThan(a, b, c) => Instruction::LessThan(f(a), f(b), f(c)),
+        Instruction::Equals(a, b, c) => Instruction::Equals(f(a), f(b), f(c)),
+        Instruction::AdjustBase(a) => Instruction::AdjustBase(f(a)),
+        Instruction::Halt => Instruction::Halt,
+    }
+}
+
+fn position_operands(instruction: &Instruction) -> impl Iterator<Item = usize> {
+    instruction
+        .operands()
+        .into_iter()
+        .filter(|x| x.mode == ParameterMode::Position)
+        .map(|x| x.value as usize)
+}
+
+/// Finds the instructions reachable from address zero.
+///
+/// Fails if control flow or memory accesses cannot be determined
+/// statically or if the program could read or modify its own code.
+fn explore(mem: &[i64], written: &BTreeSet<usize>) -> Option<BTreeMap<usize, Instruction>> {
+    let mut code = BTreeMap::new();
+    let mut todo = vec![0];
+    while let Some(ip) = todo.pop() {
+        if code.contains_key(&ip) {
+            continue;
+        }
+        let instruction = decode(mem, ip).ok()?;
+        if ip + instruction.size() > mem.len() {
+            return None;
+        }
+        for operand in instruction.operands() {
+            match operand.mode {
+                ParameterMode::Relative => return None,
+                ParameterMode::Position if operand.value < 0 => return None,
+                _ => {}
+            }
+        }
+        code.insert(ip, instruction);
+        if let Some((cond, target, if_true)) = as_jump(&instruction) {
+            let taken = constant(mem, written, cond).map(|x| (x != 0) == if_true);
+            if taken != Some(false) {
+                let target = constant(mem, written, target)?;
+                todo.push(usize::try_from(target).ok()?);
+            }
+            if taken == Some(true) {
+                continue;
+            }
+        }
+        if instruction != Instruction::Halt {
+            todo.push(ip + instruction.size());
+        }
+    }
+
+    let mut code_cells = BTreeSet::new();
+    let mut end = 0;
+    for (&ip, instruction) in &code {
+        // a jump into the middle of another instruction
+        if ip < end {
+            return None;
+        }
+        end = ip + instruction.size();
+        code_cells.extend(ip..end);
+    }
+    if code
+        .values()
+        .flat_map(position_operands)
+        .any(|addr| code_cells.contains(&addr))
+    {
+        return None;
+    }
+    Some(code)
+}
+
+/// Returns the reachable instructions and the cells they write to.
+fn analyze(mem: &[i64]) -> Option<(BTreeMap<usize, Instruction>, BTreeSet<usize>)> {
+    // assuming fewer written cells can only find more constant jump
+    // targets, so grow the set until the reachable code agrees with it
+    let mut written = BTreeSet::new();
+    loop {
+        let code = explore(mem, &written)?;
+        let before = written.len();
+        written.extend(code.values().filter_map(|instruction| {
+            let operand = instruction.operands()[instruction.write_operand()?];
+            Some(operand.value as usize)
+        }));
+        if written.len() == before {
+            return Some((code, written));
+        }
+    }
+}
+
+/// Replaces known operands with immediates and folds the instruction.
+///
+/// Returns `None` for jumps that are never taken.
+fn fold(mem: &[i64], written: &BTreeSet<usize>, instruction: Instruction) -> Option<Instruction> {
+    let write = instruction.write_operand();
+    let mut idx = 0;
+    let instruction = map_operands(instruction, |operand| {
+        idx += 1;
+        match constant(mem, written, operand) {
+            Some(value) if write != Some(idx - 1) => Operand::immediate(value),
+            _ => operand,
+        }
+    });
+
+    let store = |value: i64, target: Operand| {
+        Instruction::Add(Operand::immediate(value), Operand::immediate(0), target)
+    };
+    let operands = instruction.operands();
+    let (a, b) = match operands.as_slice() {
+        [a, b, ..] => (immediate(*a), immediate(*b)),
+        _ => (None, None),
+    };
+    Some(match (instruction, a, b) {
+        (Instruction::Add(_, _, c), Some(a), Some(b)) => match a.checked_add(b) {
+            Some(value) => store(value, c),
+            None => instruction,
+        },
+        (Instruction::Mul(_, _, c), Some(a), Some(b)) => match a.checked_mul(b) {
+            Some(value) => store(value, c),
+            None => instruction,
+        },
+        (Instruction::LessThan(_, _, c), Some(a), Some(b)) => store((a < b) as i64, c),
+        (Instruction::Equals(_, _, c), Some(a), Some(b)) => store((a == b) as i64, c),
+        (Instruction::JumpIfTrue(_, target), Some(_), _)
+        | (Instruction::JumpIfFalse(_, target), Some(_), _) => {
+            if is_unconditional(&instruction) {
+                Instruction::JumpIfTrue(Operand::immediate(1), target)
+            } else {
+                return None;
+            }
+        }
+        _ => instruction,
+    })
+}
+
+/// The folded instructions by address together with their size.
+///
+/// Removed instructions are kept as `None` with the number of cells to
+/// skip to get to the next instruction until the final layout.
+struct Rewrite {
+    code: BTreeMap<usize, (usize, Option<Instruction>)>,
+}
+
+impl Rewrite {
+    /// Skips removed instructions starting at an address.
+    fn skip_removed(&self, mut addr: usize) -> usize {
+        while let Some(&(size, None)) = self.code.get(&addr) {
+            addr += size;
+        }
+        addr
+    }
+
+    /// Follows removed instructions and unconditional jumps from an address
+    /// to the first instruction that does something.
+    fn resolve(&self, mut addr: usize) -> usize {
+        let mut seen = BTreeSet::new();
+        while seen.insert(addr) {
+            addr = self.skip_removed(addr);
+            match self.code.get(&addr) {
+                Some(&(_, Some(ref jump))) if is_unconditional(jump) => {
+                    addr = jump.operands()[1].value as usize;
+                }
+                _ => break,
+            }
+        }
+        self.skip_removed(addr)
+    }
+
+    /// Returns true if execution continuing at `from` ends up at `to`
+    /// once unused cells are dropped from the layout.
+    fn falls_through(&self, mut from: usize, to: usize, data: &BTreeSet<usize>) -> bool {
+        while from < to {
+            match self.code.get(&from) {
+                Some(&(size, None)) => from += size,
+                Some(&(_, Some(_))) => return false,
+                None if data.contains(&from) => return false,
+                None => from += 1,
+            }
+        }
+        from == to
+    }
+
+    /// Retargets all jumps and removes jumps to where they would continue
+    /// anyway.  Returns true if an instruction was removed.
+    fn thread_jumps(&mut self) -> bool {
+        let data: BTreeSet<usize> = self
+            .code
+            .values()
+            .filter_map(|x| x.1.as_ref())
+            .flat_map(position_operands)
+            .collect();
+        let mut removed = false;
+        let addrs: Vec<_> = self.code.keys().copied().collect();
+        for ip in addrs {
+            let (size, instruction) = self.code[&ip];
+            let jump = match instruction {
+                Some(jump) if as_jump(&jump).is_some() => jump,
+                _ => continue,
+            };
+            let target = self.resolve(jump.operands()[1].value as usize);
+            // the entry point has to stay in place
+            if ip != 0 && target == self.resolve(ip + size) {
+                self.code.insert(ip, (size, None));
+                removed = true;
+                continue;
+            }
+            if ip != 0 && self.falls_through(ip + size, target, &data) {
+                // skipping the jump now skips the unused cells as well
+                self.code.insert(ip, (target - ip, None));
+                removed = true;
+                continue;
+            }
+            let target = target as i64;
+            let mut idx = 0;
+            let jump = map_operands(jump, |operand| {
+                idx += 1;
+                if idx == 2 {
+                    Operand::immediate(target)
+                } else {
+                    operand
+                }
+            });
+            self.code.insert(ip, (size, Some(jump)));
+        }
+        removed
+    }
+
+    /// Removes the instructions that cannot be reached anymore.
+    ///
+    /// Returns true if an instruction was removed.
+    fn remove_unreachable(&mut self) -> bool {
+        let reachable = self.reachable();
+        let mut removed = false;
+        for (ip, &mut (_, ref mut instruction)) in self.code.iter_mut() {
+            if instruction.is_some() && !reachable.contains_key(ip) {
+                *instruction = None;
+                removed = true;
+            }
+        }
+        removed
+    }
+
+    /// Returns the instructions reachable from the entry point.
+    fn reachable(&self) -> BTreeMap<usize, Instruction> {
+        let mut rv = BTreeMap::new();
+        let mut todo = vec![0];
+        while let Some(ip) = todo.pop() {
+            let ip = self.skip_removed(ip);
+            let instruction = match self.code.get(&ip) {
+                Some(&(_, Some(instruction))) if !rv.contains_key(&ip) => instruction,
+                _ => continue,
+            };
+            if let Some((_, target, _)) = as_jump(&instruction) {
+                todo.push(target.value as usize);
+            }
+            if instruction != Instruction::Halt && !is_unconditional(&instruction) {
+                todo.push(ip + instruction.size());
+            }
+            rv.insert(ip, instruction);
+        }
+        rv
+    }
+}
+
+/// Moves the used cells together and rewrites all addresses.
+fn relocate(mem: &[i64], code: &BTreeMap<usize, Instruction>) -> Vec<i64> {
+    let mut cells: BTreeSet<usize> = code.values().flat_map(position_operands).collect();
+    for (&ip, instruction) in code {
+        cells.extend(ip..ip + instruction.size());
+    }
+    // cells past the end of the program are zero either way and keep their
+    // address so they cannot collide with relocated ones
+    let moved: BTreeMap<usize, usize> = cells
+        .iter()
+        .filter(|&&addr| addr < mem.len())
+        .enumerate()
+        .map(|(new, &old)| (old, new))
+        .collect();
+    let map = |addr: i64| moved.get(&(addr as usize)).map_or(addr, |&x| x as i64);
+
+    let mut rv = Vec::with_capacity(moved.len());
+    let mut addr = 0;
+    while addr < mem.len() {
+        if let Some(instruction) = code.get(&addr) {
+            let jump = as_jump(instruction).is_some();
+            let mut idx = 0;
+            map_operands(*instruction, |mut operand| {
+                idx += 1;
+                if operand.mode == ParameterMode::Position || (jump && idx == 2) {
+                    operand.value = map(operand.value);
+                }
+                operand
+            })
+            .encode_into(&mut rv);
+            addr += instruction.size();
+        } else {
+            if moved.contains_key(&addr) {
+                rv.push(mem[addr]);
+            }
+            addr += 1;
+        }
+    }
+    rv
+}
+
+/// Rewrites a program into an equivalent one that runs faster.
+///
+/// The optimizer first checks that the program cannot modify or read its
+/// own code and that all jump targets and addresses are known without
+/// running it.  Programs using relative mode or computed jumps fail that
+/// check and are returned unchanged.  Otherwise operands with known values
+/// become immediates, arithmetic on immediates is folded, conditional
+/// jumps with known conditions are resolved, jumps to jumps are threaded
+/// and unreachable code and unused data are removed.  The remaining cells
+/// are moved together and all addresses relocated.
+///
+/// The optimized program produces the same outputs for the same inputs
+/// but executes at most as many instructions and has a different memory
+/// layout.
+pub fn optimize(program: &[i64]) -> Vec<i64> {
+    let (code, written) = match analyze(program) {
+        Some(analysis) => analysis,
+        None => return program.to_vec(),
+    };
+    let mut rewrite = Rewrite {
+        code: code
+            .into_iter()
+            .map(|(ip, instruction)| {
+                let folded = match fold(program, &written, instruction) {
+                    // the entry point has to stay in place
+                    None if ip == 0 => Some(Instruction::JumpIfFalse(
+                        Operand::immediate(1),
+                        Operand::immediate(0),
+                    )),
+                    folded => folded,
+                };
+                (ip, (instruction.size(), folded))
+            })
+            .collect(),
+    };
+    while rewrite.thread_jumps() | rewrite.remove_unreachable() {}
+    relocate(program, &rewrite.reachable())
+}
@@ -0,0 +1,51 @@
+use alloc::vec::Vec;
+
+use crate::cell::Cell;
+
+/// Describes why a bounded run of a machine stopped.
+///
+/// Both variants carry the outputs produced during the run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome<C: Cell = i64> {
+    /// The requested condition was reached.
+    Reached(Vec<C>),
+    /// The machine halted before the condition was reached.
+    Halted(Vec<C>),
+}
+
+impl<C: Cell> Outcome<C> {
+    /// Returns true if the condition was reached.
+    pub fn is_reached(&self) -> bool {
+        match *self {
+            Outcome::Reached(_) => true,
+            Outcome::Halted(_) => false,
+        }
+    }
+
+    /// Returns true if the machine halted.
+    pub fn is_halted(&self) -> bool {
+        !self.is_reached()
+    }
+
+    /// Returns the outputs produced during the run.
+    pub fn outputs(&self) -> &[C] {
+        match *self {
+            Outcome::Reached(ref outputs) | Outcome::Halted(ref outputs) => outputs,
+        }
+    }
+
+    /// Returns the outputs if the condition was reached.
+    pub fn reached(self) -> Option<Vec<C>> {
+        match self {
+            Outcome::Reached(outputs) => Some(outputs),
+            Outcome::Halted(_) => None,
+        }
+    }
+
+    /// Consumes the outcome and returns the outputs.
+    pub fn into_outputs(self) -> Vec<C> {
+        match self {
+            Outcome::Reached(outputs) | Outcome::Halted(outputs) => outputs,
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::error;
+
+use crate::Machine;
+
+/// An error produced when parsing or applying a patch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// A line of the patch file could not be parsed.
+    Syntax { line: usize, message: String },
+    /// A cell did not hold the value the patch expected.
+    Mismatch {
+        line: usize,
+        addr: usize,
+        expected: i64,
+        actual: i64,
+    },
+    /// A search pattern did not occur in memory.
+    NotFound { line: usize },
+    /// An edit reaches beyond the memory limit of the machine.
+    OutOfRange { line: usize, addr: usize },
+}
+
+impl PatchError {
+    /// Returns the one-based line of the patch the error belongs to.
+    pub fn line(&self) -> usize {
+        match *self {
+            PatchError::Syntax { line, .. }
+            | PatchError::Mismatch { line, .. }
+            | PatchError::NotFound { line }
+            | PatchError::OutOfRange { line, .. } => line,
+        }
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PatchError::Syntax { line, ref message } => write!(f, "line {}: {}", line, message),
+            PatchError::Mismatch {
+                line,
+                addr,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "line {}: expected {} at address {}, found {}",
+                line, expected, addr, actual
+            ),
+            PatchError::NotFound { line } => write!(f, "line {}: pattern not found", line),
+            PatchError::OutOfRange { line, addr } => write!(
+                f,
+                "line {}: address {} is beyond the memory limit",
+                line, addr
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for PatchError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Edit {
+    Set(usize, Vec<i64>),
+    Fill(Range<usize>, i64),
+    Replace(usize, Vec<i64>, Vec<i64>),
+    FindReplace(Vec<i64>, Vec<i64>),
+}
+
+/// A set of memory edits loaded from a patch file.
+///
+/// Every line holds one edit, `#` starts a comment:
+///
+/// ```text
+/// 0 = 2               # write 2 to address 0
+/// 1 = 12, 2           # write 12 to address 1 and 2 to address 2
+/// 10..20 = 0          # clear the addresses 10 to 19
+/// 0: 1 -> 2           # write 2 to address 0 if it holds 1
+/// find 3, 0 -> 3, 3   # replace every occurrence of a pattern
+/// ```
+///
+/// Values and patterns are whole memory cells, not bytes.  Patches are
+/// applied atomically: if any verification fails or an edit reaches
+/// beyond the memory limit of the machine, memory is left untouched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Patch {
+    edits: Vec<(usize, Edit)>,
+}
+
+fn syntax_error<S: Into<String>>(line: usize, message: S) -> PatchError {
+    PatchError::Syntax {
+        line,
+        message: message.into(),
+    }
+}
+
+fn parse_number<T: core::str::FromStr>(s: &str, line: usize) -> Result<T, PatchError> {
+    s.trim()
+        .parse()
+        .map_err(|_| syntax_error(line, format!("invalid number '{}'", s.trim())))
+}
+
+fn parse_values(s: &str, line: usize) -> Result<Vec<i64>, PatchError> {
+    let values = s
+        .split(',')
+        .map(|x| parse_number(x, line))
+        .collect::<Result<Vec<_>, _>>()?;
+    if values.is_empty() {
+        return Err(syntax_error(line, "expected values"));
+    }
+    Ok(values)
+}
+
+fn parse_replacement(s: &str, line: usize) -> Result<(Vec<i64>, Vec<i64>), PatchError> {
+    let arrow = s
+        .find("->")
+        .ok_or_else(|| syntax_error(line, "expected '->'"))?;
+    let old = parse_values(&s[..arrow], line)?;
+    let new = parse_values(&s[arrow + 2..], line)?;
+    if old.len() != new.len() {
+        return Err(syntax_error(
+            line,
+            format!(
+                "replacement has {} values but the original {}",
+                new.len(),
+                old.len()
+            ),
+        ));
+    }
+    Ok((old, new))
+}
+
+fn parse_edit(s: &str, line: usize) -> Result<Edit, PatchError> {
+    if let Some(rest) = s.strip_prefix("find ") {
+        let (old, new) = parse_replacement(rest, line)?;
+        return Ok(Edit::FindReplace(old, new));
+    }
+    if let Some(colon) = s.find(':') {
+        let addr = parse_number(&s[..colon], line)?;
+        let (old, new) = parse_replacement(&s[colon + 1..], line)?;
+        return Ok(Edit::Replace(addr, old, new));
+    }
+    let eq = s
+        .find('=')
+        .ok_or_else(|| syntax_error(line, format!("invalid patch '{}'", s)))?;
+    let (target, value) = (&s[..eq], &s[eq + 1..]);
+    if let Some(dots) = target.find("..") {
+        let start = parse_number(&target[..dots], line)?;
+        let end = parse_number(&target[dots + 2..], line)?;
+        if end < start {
+            return Err(syntax_error(line, "range ends before it starts"));
+        }
+        return Ok(Edit::Fill(start..end, parse_number(value, line)?));
+    }
+    Ok(Edit::Set(
+        parse_number(target, line)?,
+        parse_values(value, line)?,
+    ))
+}
+
+/// The memory of a machine with pending writes on top.
+struct Overlay<'a> {
+    machine: &'a Machine,
+    writes: BTreeMap<usize, i64>,
+}
+
+impl<'a> Overlay<'a> {
+    /// Fails unless the `len` cells at `addr` are within the memory limit.
+    fn check(&self, line: usize, addr: usize, len: usize) -> Result<(), PatchError> {
+        match addr.checked_add(len) {
+            Some(end) if end <= self.machine.memory_limit() => Ok(()),
+            _ => Err(PatchError::OutOfRange {
+                line,
+                addr: addr.max(self.machine.memory_limit()),
+            }),
+        }
+    }
+
+    fn get(&self, addr: usize) -> i64 {
+        match self.writes.get(&addr) {
+            Some(&value) => value,
+            None => self.machine.mem().get(addr).copied().unwrap_or(0),
+        }
+    }
+
+    fn set(&mut self, addr: usize, values: &[i64]) {
+        for (off, &value) in values.iter().enumerate() {
+            self.writes.insert(addr + off, value);
+        }
+    }
+
+    fn verify(&self, line: usize, addr: usize, expected: &[i64]) -> Result<(), PatchError> {
+        for (off, &expected) in expected.iter().enumerate() {
+            let actual = self.get(addr + off);
+            if actual != expected {
+                return Err(PatchError::Mismatch {
+                    line,
+                    addr: addr + off,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        let written = self.writes.keys().next_back().map_or(0, |x| x + 1);
+        self.machine.mem().len().max(written)
+    }
+}
+
+impl Patch {
+    /// Parses a patch file.
+    pub fn parse(source: &str) -> Result<Patch, PatchError> {
+        let mut edits = Vec::new();
+        for (idx, line) in source.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if !line.is_empty() {
+                edits.push((line_no, parse_edit(line, line_no)?));
+            }
+        }
+        Ok(Patch { edits })
+    }
+
+    /// Returns true if the patch has no edits.
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Applies the patch to the memory of a machine.
+    ///
+    /// Edits are applied in order so later lines see the effect of earlier
+    /// ones.  Nothing is written if a verification fails.  Patches work on
+    /// the stored memory, mapped devices are neither read nor written.
+    pub fn apply(&self, machine: &mut Machine) -> Result<(), PatchError> {
+        let mut overlay = Overlay {
+            machine: &*machine,
+            writes: BTreeMap::new(),
+        };
+        for &(line, ref edit) in &self.edits {
+            match *edit {
+                Edit::Set(addr, ref values) => {
+                    overlay.check(line, addr, values.len())?;
+                    overlay.set(addr, values);
+                }
+                Edit::Fill(ref range, value) => {
+                    overlay.check(line, range.start, range.len())?;
+                    for addr in range.clone() {
+                        overlay.set(addr, &[value]);
+                    }
+                }
+                Edit::Replace(addr, ref old, ref new) => {
+                    overlay.check(line, addr, old.len())?;
+                    overlay.verify(line, addr, old)?;
+                    overlay.set(addr, new);
+                }
+                Edit::FindReplace(ref old, ref new) => {
+                    let mut found = false;
+                    let mut addr = 0;
+                    while addr + old.len() <= overlay.len() {
+                        if overlay.verify(line, addr, old).is_ok() {
+                            overlay.set(addr, new);
+                            found = true;
+                            addr += old.len();
+                        } else {
+                            addr += 1;
+                        }
+                    }
+                    if !found {
+                        return Err(PatchError::NotFound { line });
+                    }
+                }
+            }
+        }
+
+        // the edits were verified against raw memory so they bypass mapped
+        // devices as well, `check` already kept them below the limit.
+        let writes = overlay.writes;
+        if let Some(&last) = writes.keys().next_back() {
+            if last >= machine.mem.len() {
+                machine.mem.resize(last + 1, 0);
+            }
+        }
+        for (addr, value) in writes {
+            machine.mem[addr] = value;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+
+use crate::error::Error;
+use crate::{Machine, DEFAULT_MEMORY_LIMIT};
+
+/// A deliberately simple interpreter that follows the puzzle text.
+///
+/// This is not meant to be fast or extensible.  It exists as an oracle to
+/// check `Machine` against, see `lockstep`.
+#[derive(Debug, Clone, Default)]
+pub struct ReferenceMachine {
+    mem: Vec<i64>,
+    ip: usize,
+    relative_base: i64,
+    inputs: VecDeque<i64>,
+    default_input: i64,
+    memory_limit: Option<usize>,
+    halted: bool,
+}
+
+impl ReferenceMachine {
+    /// Creates a reference machine for the given program.
+    pub fn new(program: &[i64]) -> ReferenceMachine {
+        ReferenceMachine {
+            mem: program.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    /// Queues an input value.
+    pub fn feed(&mut self, value: i64) {
+        self.inputs.push_back(value);
+    }
+
+    /// Sets the value read when no input is queued.
+    pub fn set_default_input(&mut self, value: i64) {
+        self.default_input = value;
+    }
+
+    /// Limits the addresses instructions can access.
+    ///
+    /// The default is `DEFAULT_MEMORY_LIMIT` like on `Machine`.
+    pub fn set_memory_limit(&mut self, limit: usize) {
+        self.memory_limit = Some(limit);
+    }
+
+    /// Returns the memory.
+    pub fn mem(&self) -> &[i64] {
+        &self.mem
+    }
+
+    /// Returns the instruction pointer.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Returns the relative base.
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    /// Returns true if the machine halted.
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Executes a single instruction and returns its output if any.
+    ///
+    /// A failing instruction leaves the machine unchanged.
+    pub fn step(&mut self) -> Result<Option<i64>, Error> {
+        let instruction = self.read(self.ip);
+        let opcode = instruction % 100;
+        let ip = self.ip;
+
+        // "ABCDE": DE is the opcode, C the mode of the first parameter,
+        // B of the second and A of the third.
+        let mode = |n: u32| instruction / 100 / 10i64.pow(n - 1) % 10;
+
+        match opcode {
+            1 | 2 | 7 | 8 => {
+                let a = self.param(1, mode(1))?;
+                let b = self.param(2, mode(2))?;
+                let target = self.target(3, mode(3))?;
+                let value = match opcode {
+                    1 => a.checked_add(b).ok_or(Error::Overflow { ip })?,
+                    2 => a.checked_mul(b).ok_or(Error::Overflow { ip })?,
+                    7 => (a < b) as i64,
+                    _ => (a == b) as i64,
+                };
+                self.write(target, value);
+                self.ip += 4;
+            }
+            3 => {
+                let target = self.target(1, mode(1))?;
+                let value = self.inputs.pop_front().unwrap_or(self.default_input);
+                self.write(target, value);
+                self.ip += 2;
+            }
+            4 => {
+                let value = self.param(1, mode(1))?;
+                self.ip += 2;
+                return Ok(Some(value));
+            }
+            5 | 6 => {
+                let value = self.param(1, mode(1))?;
+                if (value != 0) == (opcode == 5) {
+                    let target = self.param(2, mode(2))?;
+                    self.ip = self.address(target)?;
+                } else {
+                    self.ip += 3;
+                }
+            }
+            9 => {
+                let value = self.param(1, mode(1))?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(value)
+                    .ok_or(Error::Overflow { ip })?;
+                self.ip += 2;
+            }
+            99 => self.halted = true,
+            _ => {
+                return Err(Error::InvalidOpcode {
+                    ip,
+                    opcode: instruction,
+                })
+            }
+        }
+        Ok(None)
+    }
+
+    fn read(&self, addr: usize) -> i64 {
+        self.mem.get(addr).copied().unwrap_or(0)
+    }
+
+    fn write(&mut self, addr: usize, value: i64) {
+        if addr >= self.mem.len() {
+            self.mem.resize(addr + 1, 0);
+        }
+        self.mem[addr] = value;
+    }
+
+    fn address(&self, value: i64) -> Result<usize, Error> {
+        match usize::try_from(value) {
+            Ok(addr) if addr < self.memory_limit.unwrap_or(DEFAULT_MEMORY_LIMIT) => Ok(addr),
+            _ => Err(Error::InvalidAddress { ip: self.ip }),
+        }
+    }
+
+    fn relative(&self, value: i64) -> Result<usize, Error> {
+        let addr = self
+            .relative_base
+            .checked_add(value)
+            .ok_or(Error::Overflow { ip: self.ip })?;
+        self.address(addr)
+    }
+
+    fn param(&self, n: usize, mode: i64) -> Result<i64, Error> {
+        let raw = self.read(self.ip + n);
+        match mode {
+            0 => Ok(self.read(self.address(raw)?)),
+            1 => Ok(raw),
+            2 => Ok(self.read(self.relative(raw)?)),
+            mode => Err(Error::InvalidMode { ip: self.ip, mode }),
+        }
+    }
+
+    fn target(&self, n: usize, mode: i64) -> Result<usize, Error> {
+        let raw = self.read(self.ip + n);
+        match mode {
+            0 => self.address(raw),
+            2 => self.relative(raw),
+            mode => Err(Error::InvalidMode { ip: self.ip, mode }),
+        }
+    }
+}
+
+/// What differed between the machine and the reference.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DivergenceKind {
+    /// The instruction pointers differ.
+    Ip { expected: usize, actual: usize },
+    /// The relative bases differ.
+    RelativeBase { expected: i64, actual: i64 },
+    /// A memory cell differs.
+    Memory {
+        addr: usize,
+        expected: i64,
+        actual: i64,
+    },
+    /// The produced output differs.
+    Output {
+        expected: Option<i64>,
+        actual: Option<i64>,
+    },
+    /// Only one of them halted.
+    Halted { expected: bool, actual: bool },
+    /// The errors differ.
+    Error {
+        expected: Option<Error>,
+        actual: Option<Error>,
+    },
+}
+
+/// The first difference found by `lockstep`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    step: u64,
+    ip: usize,
+    kind: DivergenceKind,
+}
+
+impl Divergence {
+    /// Returns the number of instructions executed before the divergence.
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    /// Returns the address of the instruction that diverged.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Returns what differed.
+    pub fn kind(&self) -> &DivergenceKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {} at ip {}: ", self.step, self.ip)?;
+        match self.kind {
+            DivergenceKind::Ip { expected, actual } => {
+                write!(f, "ip is {}, expected {}", actual, expected)
+            }
+            DivergenceKind::RelativeBase { expected, actual } => {
+                write!(f, "relative base is {}, expected {}", actual, expected)
+            }
+            DivergenceKind::Memory {
+                addr,
+                expected,
+                actual,
+            } => write!(f, "mem[{}] is {}, expected {}", addr, actual, expected),
+            DivergenceKind::Output { expected, actual } => {
+                write!(f, "output is {:?}, expected {:?}", actual, expected)
+            }
+            DivergenceKind::Halted { expected, actual } => {
+                write!(f, "halted is {}, expected {}", actual, expected)
+            }
+            DivergenceKind::Error {
+                ref expected,
+                ref actual,
+            } => write!(f, "error is {:?}, expected {:?}", actual, expected),
+        }
+    }
+}
+
+fn compare(machine: &Machine, reference: &ReferenceMachine) -> Option<DivergenceKind> {
+    if machine.ip != reference.ip {
+        return Some(DivergenceKind::Ip {
+            expected: reference.ip,
+            actual: machine.ip,
+        });
+    }
+    if machine.relative_base != reference.relative_base {
+        return Some(DivergenceKind::RelativeBase {
+            expected: reference.relative_base,
+            actual: machine.relative_base,
+        });
+    }
+    if machine.halted != reference.halted {
+        return Some(DivergenceKind::Halted {
+            expected: reference.halted,
+            actual: machine.halted,
+        });
+    }
+    // cells past the end of either memory read as zero
+    for addr in 0..machine.mem.len().max(reference.mem.len()) {
+        let expected = reference.read(addr);
+        let actual = machine.mem.get(addr).copied().unwrap_or(0);
+        if expected != actual {
+            return Some(DivergenceKind::Memory {
+                addr,
+                expected,
+                actual,
+            });
+        }
+    }
+    None
+}
+
+/// Runs a machine in lockstep with the reference interpreter.
+///
+/// The reference starts from the state of the machine including queued
+/// inputs and the memory limit.  After every instruction the instruction
+/// pointer, relative base, memory, output and errors are compared and the
+/// first difference is returned.  Runs until the machine halts, fails or
+/// executed `max_steps` instructions and returns the number of executed
+/// instructions.  Custom opcodes, mapped devices and instruction budgets
+/// are not modelled by the reference and will show up as divergences.
+pub fn lockstep(machine: &mut Machine, max_steps: u64) -> Result<u64, Divergence> {
+    let mut reference = ReferenceMachine {
+        mem: machine.mem.clone(),
+        ip: machine.ip,
+        relative_base: machine.relative_base,
+        inputs: machine.inputs.iter().copied().collect(),
+        default_input: machine.mem_input,
+        memory_limit: Some(machine.memory_limit()),
+        halted: machine.halted,
+    };
+
+    let mut step = 0;
+    while step < max_steps && !reference.halted {
+        let ip = reference.ip;
+        let diverged = |kind| Divergence { step, ip, kind };
+        let expected = reference.step();
+        let actual = machine
+            .try_step_instruction()
+            .map(|output| Some(machine.output).filter(|_| output && !machine.halted));
+        match (expected, actual) {
+            (Ok(expected), Ok(actual)) => {
+                if expected != actual {
+                    return Err(diverged(DivergenceKind::Output { expected, actual }));
+                }
+            }
+            (Err(expected), Err(actual)) => {
+                if expected != actual {
+                    return Err(diverged(DivergenceKind::Error {
+                        expected: Some(expected),
+                        actual: Some(actual),
+                    }));
+                }
+                return Ok(step);
+            }
+            (expected, actual) => {
+                return Err(diverged(DivergenceKind::Error {
+                    expected: expected.err(),
+                    actual: actual.err(),
+                }));
+            }
+        }
+        if let Some(kind) = compare(machine, &reference) {
+            return Err(diverged(kind));
+        }
+        step += 1;
+    }
+    Ok(step)
+}
@@ -0,0 +1,278 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::error::Error;
+use crate::Machine;
+
+/// The number of instructions a single run may execute by default.
+const DEFAULT_STEP_BUDGET: u64 = 10_000_000;
+
+/// Returns the number of threads searches use by default.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |x| x.get())
+}
+
+/// Applies `func` to the candidates on a pool of threads and returns the
+/// first result that is not `None`.
+///
+/// Candidates are handed out in order and once a match was found no
+/// later candidates are started.  The returned result is always the one
+/// of the earliest matching candidate, so the outcome does not depend on
+/// scheduling.
+pub fn parallel_find_map<I, T, R, F>(candidates: I, threads: usize, func: F) -> Option<R>
+where
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send,
+    T: Send,
+    R: Send,
+    F: Fn(T) -> Option<R> + Sync,
+{
+    let queue = Mutex::new(candidates.into_iter().enumerate());
+    let found = AtomicUsize::new(usize::MAX);
+    let best = Mutex::new(None::<(usize, R)>);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let (idx, candidate) = match queue.lock().unwrap().next() {
+                    Some(item) => item,
+                    None => break,
+                };
+                if idx > found.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Some(rv) = func(candidate) {
+                    found.fetch_min(idx, Ordering::SeqCst);
+                    let mut best = best.lock().unwrap();
+                    if best.as_ref().is_none_or(|x| idx < x.0) {
+                        *best = Some((idx, rv));
+                    }
+                }
+            });
+        }
+    });
+
+    best.into_inner().unwrap().map(|x| x.1)
+}
+
+/// Scores all candidates on a pool of threads and returns the candidate
+/// with the highest score together with that score.
+///
+/// If multiple candidates share the highest score the earliest one wins.
+pub fn parallel_max_by_key<I, T, K, F>(candidates: I, threads: usize, key: F) -> Option<(T, K)>
+where
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send,
+    T: Send,
+    K: Ord + Send,
+    F: Fn(&T) -> K + Sync,
+{
+    parallel_map_max(candidates, threads, |candidate| {
+        let score = key(&candidate);
+        (candidate, score)
+    })
+}
+
+/// Maps the candidates to results with scores on a pool of threads and
+/// returns the result with the highest score.
+fn parallel_map_max<I, T, R, K, F>(candidates: I, threads: usize, func: F) -> Option<(R, K)>
+where
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send,
+    T: Send,
+    R: Send,
+    K: Ord + Send,
+    F: Fn(T) -> (R, K) + Sync,
+{
+    let queue = Mutex::new(candidates.into_iter().enumerate());
+    let best = Mutex::new(None::<(usize, R, K)>);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let (idx, candidate) = match queue.lock().unwrap().next() {
+                    Some(item) => item,
+                    None => break,
+                };
+                let (rv, score) = func(candidate);
+                let mut best = best.lock().unwrap();
+                let better = best.as_ref().is_none_or(|&(best_idx, _, ref best_score)| {
+                    score > *best_score || (score == *best_score && idx < best_idx)
+                });
+                if better {
+                    *best = Some((idx, rv, score));
+                }
+            });
+        }
+    });
+
+    best.into_inner().unwrap().map(|(_, rv, score)| (rv, score))
+}
+
+/// A set of memory patches and inputs to try on a machine.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Candidate {
+    patches: Vec<(usize, i64)>,
+    inputs: Vec<i64>,
+}
+
+impl Candidate {
+    /// Creates a candidate from memory patches and inputs.
+    pub fn new(patches: &[(usize, i64)], inputs: &[i64]) -> Candidate {
+        Candidate {
+            patches: patches.to_vec(),
+            inputs: inputs.to_vec(),
+        }
+    }
+
+    /// Creates a candidate that only patches memory.
+    pub fn from_patches(patches: &[(usize, i64)]) -> Candidate {
+        Candidate::new(patches, &[])
+    }
+
+    /// Creates a candidate that only feeds inputs.
+    pub fn from_inputs(inputs: &[i64]) -> Candidate {
+        Candidate::new(&[], inputs)
+    }
+
+    /// Returns the memory patches as address and value pairs.
+    pub fn patches(&self) -> &[(usize, i64)] {
+        &self.patches
+    }
+
+    /// Returns the inputs.
+    pub fn inputs(&self) -> &[i64] {
+        &self.inputs
+    }
+
+    /// Applies the patches and feeds the inputs to a machine.
+    ///
+    /// Panics if a patch is outside of the machine's memory.
+    pub fn apply(&self, machine: &mut Machine) {
+        self.try_apply(machine)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `apply` but returns an error instead of panicking.
+    pub fn try_apply(&self, machine: &mut Machine) -> Result<(), Error> {
+        for &(addr, value) in &self.patches {
+            machine.try_mem_set(addr, value)?;
+        }
+        for &value in &self.inputs {
+            machine.feed(value);
+        }
+        Ok(())
+    }
+}
+
+/// The result of running a single candidate.
+#[derive(Debug)]
+pub struct Run {
+    candidate: Candidate,
+    machine: Machine,
+    result: Result<Vec<i64>, Error>,
+}
+
+impl Run {
+    /// Returns the candidate that was run.
+    pub fn candidate(&self) -> &Candidate {
+        &self.candidate
+    }
+
+    /// Returns the machine in the state it stopped in.
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    /// Returns the outputs produced if the machine halted cleanly.
+    pub fn outputs(&self) -> Option<&[i64]> {
+        self.result.as_ref().ok().map(|x| &x[..])
+    }
+
+    /// Returns the error if the machine failed.
+    pub fn error(&self) -> Option<&Error> {
+        self.result.as_ref().err()
+    }
+}
+
+/// Runs many candidates against copies of a template machine in parallel.
+pub struct Search {
+    template: Machine,
+    threads: usize,
+    step_budget: u64,
+}
+
+impl Search {
+    /// Creates a search that runs candidates on clones of the given machine.
+    pub fn new(template: Machine) -> Search {
+        Search {
+            template,
+            threads: default_threads(),
+            step_budget: DEFAULT_STEP_BUDGET,
+        }
+    }
+
+    /// Sets the number of threads to use.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
+    /// Sets the number of instructions a single run may execute.
+    ///
+    /// Runs that take longer fail with a budget exceeded error so that a
+    /// candidate that loops forever does not stall the search.  The
+    /// default is ten million instructions.
+    pub fn set_step_budget(&mut self, steps: u64) {
+        self.step_budget = steps;
+    }
+
+    /// Runs a single candidate to completion or until the step budget is
+    /// used up.
+    pub fn run(&self, candidate: Candidate) -> Run {
+        let mut machine = self.template.clone();
+        let budget = machine.instruction_count().saturating_add(self.step_budget);
+        machine.instruction_budget =
+            Some(machine.instruction_budget.map_or(budget, |x| x.min(budget)));
+        let result = candidate
+            .try_apply(&mut machine)
+            .and_then(|()| machine.try_eval_multi());
+        Run {
+            candidate,
+            machine,
+            result,
+        }
+    }
+
+    /// Returns the run of the earliest candidate matching the predicate.
+    pub fn find<I, P>(&self, candidates: I, predicate: P) -> Option<Run>
+    where
+        I: IntoIterator<Item = Candidate>,
+        I::IntoIter: Send,
+        P: Fn(&Run) -> bool + Sync,
+    {
+        parallel_find_map(candidates, self.threads, |candidate| {
+            let run = self.run(candidate);
+            if predicate(&run) {
+                Some(run)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the run with the highest score together with the score.
+    pub fn max_by_score<I, S, K>(&self, candidates: I, score: S) -> Option<(Run, K)>
+    where
+        I: IntoIterator<Item = Candidate>,
+        I::IntoIter: Send,
+        S: Fn(&Run) -> K + Sync,
+        K: Ord + Send,
+    {
+        parallel_map_max(candidates, self.threads, |candidate| {
+            let run = self.run(candidate);
+            let score = score(&run);
+            (run, score)
+        })
+    }
+}
@@ -0,0 +1,852 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::RangeInclusive;
+#[cfg(feature = "std")]
+use std::error;
+
+use crate::error::Error;
+use crate::outcome::Outcome;
+use crate::{Machine, DEFAULT_MEMORY_LIMIT};
+
+/// A symbolic expression over the variables of a symbolic machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// A concrete value.
+    Const(i64),
+    /// A variable with its index and name.
+    Var(usize, Arc<str>),
+    /// The sum of two expressions.
+    Add(Box<Expr>, Box<Expr>),
+    /// The product of two expressions.
+    Mul(Box<Expr>, Box<Expr>),
+    /// The value of the memory cell at a symbolic address.
+    Load(Box<Expr>),
+}
+
+impl Expr {
+    /// Returns the concrete value if the expression is constant.
+    pub fn as_const(&self) -> Option<i64> {
+        match *self {
+            Expr::Const(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn add(a: Expr, b: Expr) -> Option<Expr> {
+        Some(match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(a.checked_add(b)?),
+            (Expr::Const(0), x) | (x, Expr::Const(0)) => x,
+            (a, b) => Expr::Add(Box::new(a), Box::new(b)),
+        })
+    }
+
+    fn mul(a: Expr, b: Expr) -> Option<Expr> {
+        Some(match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(a.checked_mul(b)?),
+            (Expr::Const(0), _) | (_, Expr::Const(0)) => Expr::Const(0),
+            (Expr::Const(1), x) | (x, Expr::Const(1)) => x,
+            (a, b) => Expr::Mul(Box::new(a), Box::new(b)),
+        })
+    }
+
+    fn collect_variables(&self, out: &mut BTreeSet<usize>) {
+        match *self {
+            Expr::Const(_) => {}
+            Expr::Var(idx, _) => {
+                out.insert(idx);
+            }
+            Expr::Add(ref a, ref b) | Expr::Mul(ref a, ref b) => {
+                a.collect_variables(out);
+                b.collect_variables(out);
+            }
+            Expr::Load(ref addr) => addr.collect_variables(out),
+        }
+    }
+
+    /// Evaluates the expression with the given variable values.
+    ///
+    /// Returns `None` if the expression contains loads or overflows.
+    pub fn eval(&self, values: &[i64]) -> Option<i64> {
+        match *self {
+            Expr::Const(value) => Some(value),
+            Expr::Var(idx, _) => values.get(idx).copied(),
+            Expr::Add(ref a, ref b) => a.eval(values)?.checked_add(b.eval(values)?),
+            Expr::Mul(ref a, ref b) => a.eval(values)?.checked_mul(b.eval(values)?),
+            Expr::Load(_) => None,
+        }
+    }
+
+    /// Converts the expression into a polynomial in normal form.
+    ///
+    /// Returns `None` if the expression contains loads or overflows.
+    pub fn to_poly(&self) -> Option<Poly> {
+        match *self {
+            Expr::Const(value) => Some(Poly::constant(value)),
+            Expr::Var(idx, ref name) => {
+                let mut rv = Poly::constant(0);
+                rv.terms.insert(vec![idx], 1);
+                rv.names.insert(idx, name.clone());
+                Some(rv)
+            }
+            Expr::Add(ref a, ref b) => a.to_poly()?.add(&b.to_poly()?),
+            Expr::Mul(ref a, ref b) => a.to_poly()?.mul(&b.to_poly()?),
+            Expr::Load(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(poly) = self.to_poly() {
+            return write!(f, "{}", poly);
+        }
+        match *self {
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Var(_, ref name) => write!(f, "{}", name),
+            Expr::Add(ref a, ref b) => write!(f, "({} + {})", a, b),
+            Expr::Mul(ref a, ref b) => write!(f, "({} * {})", a, b),
+            Expr::Load(ref addr) => write!(f, "mem[{}]", addr),
+        }
+    }
+}
+
+/// A polynomial with integer coefficients over the variables.
+///
+/// Every term is keyed by the sorted list of variable indexes it
+/// multiplies, so `noun * noun` is keyed as `[0, 0]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Poly {
+    terms: BTreeMap<Vec<usize>, i64>,
+    names: BTreeMap<usize, Arc<str>>,
+}
+
+impl Poly {
+    fn constant(value: i64) -> Poly {
+        let mut terms = BTreeMap::new();
+        if value != 0 {
+            terms.insert(vec![], value);
+        }
+        Poly {
+            terms,
+            names: BTreeMap::new(),
+        }
+    }
+
+    fn add_term(&mut self, monomial: Vec<usize>, coefficient: i64) -> Option<()> {
+        let value = self.terms.get(&monomial).copied().unwrap_or(0);
+        let value = value.checked_add(coefficient)?;
+        if value == 0 {
+            self.terms.remove(&monomial);
+        } else {
+            self.terms.insert(monomial, value);
+        }
+        Some(())
+    }
+
+    fn add(&self, other: &Poly) -> Option<Poly> {
+        let mut rv = self.clone();
+        rv.names.extend(other.names.clone());
+        for (monomial, &coefficient) in &other.terms {
+            rv.add_term(monomial.clone(), coefficient)?;
+        }
+        Some(rv)
+    }
+
+    fn mul(&self, other: &Poly) -> Option<Poly> {
+        let mut rv = Poly::constant(0);
+        rv.names = self.names.clone();
+        rv.names.extend(other.names.clone());
+        for (a, &x) in &self.terms {
+            for (b, &y) in &other.terms {
+                let mut monomial = a.clone();
+                monomial.extend_from_slice(b);
+                monomial.sort_unstable();
+                rv.add_term(monomial, x.checked_mul(y)?)?;
+            }
+        }
+        Some(rv)
+    }
+
+    /// Returns the coefficient of the given term.
+    pub fn coefficient(&self, monomial: &[usize]) -> i64 {
+        self.terms.get(monomial).copied().unwrap_or(0)
+    }
+
+    /// Returns the indexes of all variables the polynomial depends on.
+    pub fn variables(&self) -> Vec<usize> {
+        let mut rv: Vec<_> = self.terms.keys().flatten().copied().collect();
+        rv.sort_unstable();
+        rv.dedup();
+        rv
+    }
+
+    /// Evaluates the polynomial with the given variable values.
+    pub fn eval(&self, values: &[i64]) -> Option<i64> {
+        let mut rv = 0i64;
+        for (monomial, &coefficient) in &self.terms {
+            let mut term = coefficient;
+            for &idx in monomial {
+                term = term.checked_mul(*values.get(idx)?)?;
+            }
+            rv = rv.checked_add(term)?;
+        }
+        Some(rv)
+    }
+
+    /// Splits the polynomial into `a * var + b` if it is linear in `var`.
+    fn split_linear(&self, var: usize) -> Option<(Poly, Poly)> {
+        let mut a = Poly::constant(0);
+        let mut b = Poly::constant(0);
+        for (monomial, &coefficient) in &self.terms {
+            match monomial.iter().filter(|&&x| x == var).count() {
+                0 => b.add_term(monomial.clone(), coefficient)?,
+                1 => {
+                    let rest = monomial.iter().copied().filter(|&x| x != var).collect();
+                    a.add_term(rest, coefficient)?
+                }
+                _ => return None,
+            }
+        }
+        Some((a, b))
+    }
+}
+
+impl fmt::Display for Poly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        let mut terms: Vec<_> = self.terms.iter().collect();
+        terms.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(b.0)));
+        for (idx, (monomial, &coefficient)) in terms.into_iter().enumerate() {
+            if idx == 0 {
+                if coefficient < 0 {
+                    write!(f, "-")?;
+                }
+            } else if coefficient < 0 {
+                write!(f, " - ")?;
+            } else {
+                write!(f, " + ")?;
+            }
+            let magnitude = coefficient.unsigned_abs();
+            if monomial.is_empty() || magnitude != 1 {
+                write!(f, "{}", magnitude)?;
+                if !monomial.is_empty() {
+                    write!(f, " * ")?;
+                }
+            }
+            for (idx, var) in monomial.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, " * ")?;
+                }
+                match self.names.get(var) {
+                    Some(name) => write!(f, "{}", name)?,
+                    None => write!(f, "${}", var)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A condition a path took a branch on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    /// The expression is zero or not.
+    Zero(Expr, bool),
+    /// The first expression is less than the second or not.
+    LessThan(Expr, Expr, bool),
+    /// The two expressions are equal or not.
+    Equals(Expr, Expr, bool),
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Constraint::Zero(ref a, true) => write!(f, "{} == 0", a),
+            Constraint::Zero(ref a, false) => write!(f, "{} != 0", a),
+            Constraint::LessThan(ref a, ref b, true) => write!(f, "{} < {}", a, b),
+            Constraint::LessThan(ref a, ref b, false) => write!(f, "{} >= {}", a, b),
+            Constraint::Equals(ref a, ref b, true) => write!(f, "{} == {}", a, b),
+            Constraint::Equals(ref a, ref b, false) => write!(f, "{} != {}", a, b),
+        }
+    }
+}
+
+impl Constraint {
+    fn collect_variables(&self, out: &mut BTreeSet<usize>) {
+        match *self {
+            Constraint::Zero(ref a, _) => a.collect_variables(out),
+            Constraint::LessThan(ref a, ref b, _) | Constraint::Equals(ref a, ref b, _) => {
+                a.collect_variables(out);
+                b.collect_variables(out);
+            }
+        }
+    }
+}
+
+/// Errors produced by the symbolic machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolicError {
+    /// The concrete semantics failed.
+    Machine(Error),
+    /// An instruction itself depends on a variable.
+    SymbolicInstruction { ip: usize },
+    /// An instruction wrote to or jumped to a symbolic address.
+    SymbolicAddress { ip: usize },
+    /// The relative base would depend on a variable.
+    SymbolicRelativeBase { ip: usize },
+    /// An input instruction ran out of inputs.
+    MissingInput { ip: usize },
+    /// A path ran for more steps than allowed.
+    StepLimit { ip: usize },
+    /// More paths were found than allowed.
+    PathLimit,
+    /// The values to enumerate while solving exceed the limit.
+    SearchSpaceTooLarge,
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SymbolicError::Machine(ref err) => write!(f, "{}", err),
+            SymbolicError::SymbolicInstruction { ip } => {
+                write!(f, "symbolic instruction at ip {}", ip)
+            }
+            SymbolicError::SymbolicAddress { ip } => write!(f, "symbolic address at ip {}", ip),
+            SymbolicError::SymbolicRelativeBase { ip } => {
+                write!(f, "symbolic relative base at ip {}", ip)
+            }
+            SymbolicError::MissingInput { ip } => write!(f, "missing input at ip {}", ip),
+            SymbolicError::StepLimit { ip } => write!(f, "step limit reached at ip {}", ip),
+            SymbolicError::PathLimit => write!(f, "too many paths"),
+            SymbolicError::SearchSpaceTooLarge => write!(f, "search space too large"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for SymbolicError {}
+
+impl From<Error> for SymbolicError {
+    fn from(err: Error) -> SymbolicError {
+        SymbolicError::Machine(err)
+    }
+}
+
+/// A single execution path through a program.
+#[derive(Debug, Clone)]
+pub struct Path {
+    mem: Vec<Expr>,
+    ip: usize,
+    relative_base: i64,
+    input_pos: usize,
+    outputs: Vec<Expr>,
+    constraints: Vec<Constraint>,
+    steps: usize,
+    memory_limit: usize,
+    error: Option<SymbolicError>,
+}
+
+impl Path {
+    /// Returns the expression stored at the given address when the path ended.
+    pub fn mem_get(&self, addr: usize) -> Expr {
+        self.mem.get(addr).cloned().unwrap_or(Expr::Const(0))
+    }
+
+    /// Returns the outputs produced on this path.
+    pub fn outputs(&self) -> &[Expr] {
+        &self.outputs
+    }
+
+    /// Returns the conditions the path depends on.
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    /// Returns the error that ended the path if it did not halt.
+    pub fn error(&self) -> Option<&SymbolicError> {
+        self.error.as_ref()
+    }
+
+    /// Returns true if the path halted cleanly.
+    pub fn halted(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn mode(&self, off: usize) -> Result<i64, SymbolicError> {
+        let instr = self.concrete(self.ip)?;
+        Ok(10i64
+            .checked_pow((off - 1) as u32)
+            .map_or(0, |x| instr / 100 / x % 10))
+    }
+
+    fn concrete(&self, addr: usize) -> Result<i64, SymbolicError> {
+        self.mem_get(addr)
+            .as_const()
+            .ok_or(SymbolicError::SymbolicInstruction { ip: self.ip })
+    }
+
+    fn addr(&self, value: i64) -> Result<usize, SymbolicError> {
+        usize::try_from(value)
+            .ok()
+            .filter(|&x| x < self.memory_limit)
+            .ok_or_else(|| Error::InvalidAddress { ip: self.ip }.into())
+    }
+
+    fn arg(&self, off: usize) -> Result<Expr, SymbolicError> {
+        let val = self.mem_get(self.ip + off);
+        let base = match self.mode(off)? {
+            0 => 0,
+            1 => return Ok(val),
+            2 => self.relative_base,
+            mode => return Err(Error::InvalidMode { ip: self.ip, mode }.into()),
+        };
+        let overflow = SymbolicError::Machine(Error::Overflow { ip: self.ip });
+        match Expr::add(Expr::Const(base), val).ok_or(overflow)? {
+            Expr::Const(addr) => Ok(self.mem_get(self.addr(addr)?)),
+            addr => Ok(Expr::Load(Box::new(addr))),
+        }
+    }
+
+    fn put(&mut self, off: usize, val: Expr) -> Result<(), SymbolicError> {
+        let target = self
+            .mem_get(self.ip + off)
+            .as_const()
+            .ok_or(SymbolicError::SymbolicAddress { ip: self.ip })?;
+        let addr = match self.mode(off)? {
+            0 => target,
+            2 => self
+                .relative_base
+                .checked_add(target)
+                .ok_or(Error::Overflow { ip: self.ip })?,
+            mode => return Err(Error::InvalidMode { ip: self.ip, mode }.into()),
+        };
+        let addr = self.addr(addr)?;
+        if addr >= self.mem.len() {
+            self.mem.resize(addr + 1, Expr::Const(0));
+        }
+        self.mem[addr] = val;
+        Ok(())
+    }
+
+    fn jump(&mut self, taken: bool) -> Result<(), SymbolicError> {
+        if taken {
+            let target = self
+                .arg(2)?
+                .as_const()
+                .ok_or(SymbolicError::SymbolicAddress { ip: self.ip })?;
+            self.ip = self.addr(target)?;
+        } else {
+            self.ip += 3;
+        }
+        Ok(())
+    }
+
+    /// Executes one instruction, returning a forked path if the
+    /// instruction branched on a symbolic value.
+    fn execute(&mut self, inputs: &[i64]) -> Result<Option<Path>, SymbolicError> {
+        let opcode = self.concrete(self.ip)?;
+        let overflow = SymbolicError::Machine(Error::Overflow { ip: self.ip });
+        match opcode % 100 {
+            1 => {
+                let rv = Expr::add(self.arg(1)?, self.arg(2)?).ok_or(overflow)?;
+                self.put(3, rv)?;
+                self.ip += 4;
+            }
+            2 => {
+                let rv = Expr::mul(self.arg(1)?, self.arg(2)?).ok_or(overflow)?;
+                self.put(3, rv)?;
+                self.ip += 4;
+            }
+            3 => {
+                let value = *inputs
+                    .get(self.input_pos)
+                    .ok_or(SymbolicError::MissingInput { ip: self.ip })?;
+                self.input_pos += 1;
+                self.put(1, Expr::Const(value))?;
+                self.ip += 2;
+            }
+            4 => {
+                let value = self.arg(1)?;
+                self.outputs.push(value);
+                self.ip += 2;
+            }
+            code @ 5 | code @ 6 => {
+                let cond = self.arg(1)?;
+                if let Some(value) = cond.as_const() {
+                    self.jump((value != 0) == (code == 5))?;
+                } else {
+                    let mut fork = self.clone();
+                    self.constraints.push(Constraint::Zero(cond.clone(), false));
+                    self.jump(code == 5)?;
+                    fork.constraints.push(Constraint::Zero(cond, true));
+                    fork.jump(code == 6)?;
+                    return Ok(Some(fork));
+                }
+            }
+            code @ 7 | code @ 8 => {
+                let a = self.arg(1)?;
+                let b = self.arg(2)?;
+                if let (Some(a), Some(b)) = (a.as_const(), b.as_const()) {
+                    let rv = if code == 7 { a < b } else { a == b };
+                    self.put(3, Expr::Const(rv as i64))?;
+                    self.ip += 4;
+                } else {
+                    let constraint = |holds| match code {
+                        7 => Constraint::LessThan(a.clone(), b.clone(), holds),
+                        _ => Constraint::Equals(a.clone(), b.clone(), holds),
+                    };
+                    let mut fork = self.clone();
+                    self.constraints.push(constraint(true));
+                    self.put(3, Expr::Const(1))?;
+                    self.ip += 4;
+                    fork.constraints.push(constraint(false));
+                    fork.put(3, Expr::Const(0))?;
+                    fork.ip += 4;
+                    return Ok(Some(fork));
+                }
+            }
+            9 => {
+                let offset = self
+                    .arg(1)?
+                    .as_const()
+                    .ok_or(SymbolicError::SymbolicRelativeBase { ip: self.ip })?;
+                self.relative_base = self.relative_base.checked_add(offset).ok_or(overflow)?;
+                self.ip += 2;
+            }
+            99 => {}
+            _ => {
+                return Err(Error::InvalidOpcode {
+                    ip: self.ip,
+                    opcode,
+                }
+                .into())
+            }
+        }
+        Ok(None)
+    }
+}
+
+struct Variable {
+    addr: usize,
+    name: Arc<str>,
+    domain: RangeInclusive<i64>,
+}
+
+/// Runs a program with some memory cells replaced by symbolic variables.
+///
+/// Arithmetic on variables builds up expressions, branches that depend on
+/// variables fork the execution into separate paths.  This can be used to
+/// understand what a program computes and to solve for the variable values
+/// that produce a given result:
+///
+/// ```
+/// # use interpreter::SymbolicMachine;
+/// let mut machine = SymbolicMachine::new(&[1, 5, 6, 0, 99, 0, 0]);
+/// machine.add_variable(5, "a", 0..=99);
+/// machine.add_variable(6, "b", 0..=99);
+/// let paths = machine.explore().unwrap();
+/// assert_eq!(paths[0].mem_get(0).to_string(), "a + b");
+/// assert_eq!(machine.solve_mem(0, 150).unwrap(), Some(vec![51, 99]));
+/// ```
+pub struct SymbolicMachine {
+    program: Vec<i64>,
+    variables: Vec<Variable>,
+    inputs: Vec<i64>,
+    max_paths: usize,
+    max_steps: usize,
+    max_candidates: u64,
+    memory_limit: usize,
+}
+
+impl SymbolicMachine {
+    /// Creates a symbolic machine for a program.
+    pub fn new(program: &[i64]) -> SymbolicMachine {
+        SymbolicMachine {
+            program: program.to_vec(),
+            variables: Vec::new(),
+            inputs: Vec::new(),
+            max_paths: 1024,
+            max_steps: 1_000_000,
+            max_candidates: 100_000_000,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+        }
+    }
+
+    /// Replaces a memory cell with a variable ranging over `domain`.
+    ///
+    /// Returns the index of the variable.  Solutions list the values of
+    /// the variables in the order they were added.
+    pub fn add_variable(&mut self, addr: usize, name: &str, domain: RangeInclusive<i64>) -> usize {
+        self.variables.push(Variable {
+            addr,
+            name: name.into(),
+            domain,
+        });
+        self.variables.len() - 1
+    }
+
+    /// Feeds a concrete input.
+    pub fn feed(&mut self, value: i64) {
+        self.inputs.push(value);
+    }
+
+    /// Sets the maximum number of paths to explore.
+    pub fn set_max_paths(&mut self, max_paths: usize) {
+        self.max_paths = max_paths;
+    }
+
+    /// Sets the maximum number of instructions to execute per path.
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.max_steps = max_steps;
+    }
+
+    /// Sets the number of memory cells a path may address.
+    ///
+    /// Paths that refer to an address at or above the limit fail with an
+    /// invalid address error just like on a concrete machine.  The default
+    /// is `DEFAULT_MEMORY_LIMIT`.
+    pub fn set_memory_limit(&mut self, limit: usize) {
+        self.memory_limit = limit;
+    }
+
+    /// Explores all paths through the program.
+    ///
+    /// Paths that fail are returned with their error rather than failing
+    /// the whole exploration as they are frequently infeasible.
+    pub fn explore(&self) -> Result<Vec<Path>, SymbolicError> {
+        let mut mem: Vec<_> = self.program.iter().map(|&x| Expr::Const(x)).collect();
+        for (idx, var) in self.variables.iter().enumerate() {
+            if var.addr >= self.memory_limit {
+                return Err(Error::InvalidAddress { ip: 0 }.into());
+            }
+            if var.addr >= mem.len() {
+                mem.resize(var.addr + 1, Expr::Const(0));
+            }
+            mem[var.addr] = Expr::Var(idx, var.name.clone());
+        }
+
+        let mut pending = vec![Path {
+            mem,
+            ip: 0,
+            relative_base: 0,
+            input_pos: 0,
+            outputs: Vec::new(),
+            constraints: Vec::new(),
+            steps: 0,
+            memory_limit: self.memory_limit,
+            error: None,
+        }];
+        let mut rv = Vec::new();
+
+        while let Some(mut path) = pending.pop() {
+            loop {
+                if path.steps >= self.max_steps {
+                    path.error = Some(SymbolicError::StepLimit { ip: path.ip });
+                    break;
+                }
+                if path.mem_get(path.ip).as_const().map(|x| x % 100) == Some(99) {
+                    break;
+                }
+                path.steps += 1;
+                match path.execute(&self.inputs) {
+                    Ok(Some(fork)) => {
+                        if rv.len() + pending.len() + 2 > self.max_paths {
+                            return Err(SymbolicError::PathLimit);
+                        }
+                        pending.push(fork);
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        path.error = Some(err);
+                        break;
+                    }
+                }
+            }
+            rv.push(path);
+        }
+
+        Ok(rv)
+    }
+
+    /// Solves for variable values that leave `target` in memory cell `addr`
+    /// when the program halts.
+    pub fn solve_mem(&self, addr: usize, target: i64) -> Result<Option<Vec<i64>>, SymbolicError> {
+        self.solve(
+            |path| Some(path.mem_get(addr)),
+            |machine, _| machine.mem_get(addr) == target,
+            target,
+        )
+    }
+
+    /// Solves for variable values that make the output with the given
+    /// index equal `target`.
+    pub fn solve_output(
+        &self,
+        index: usize,
+        target: i64,
+    ) -> Result<Option<Vec<i64>>, SymbolicError> {
+        self.solve(
+            |path| path.outputs().get(index).cloned(),
+            |_, outputs| outputs.get(index) == Some(&target),
+            target,
+        )
+    }
+
+    /// Solves for a target on every halting path.
+    ///
+    /// Candidates are derived from the polynomial the path computes if
+    /// possible and otherwise found by enumeration.  Every candidate is
+    /// verified by running it on a concrete machine which also takes care
+    /// of the path constraints.  The concrete run is held to the same step
+    /// limit and fails on missing input just like the symbolic one.
+    fn solve<E, V>(
+        &self,
+        expr: E,
+        verify: V,
+        target: i64,
+    ) -> Result<Option<Vec<i64>>, SymbolicError>
+    where
+        E: Fn(&Path) -> Option<Expr>,
+        V: Fn(&Machine, &[i64]) -> bool,
+    {
+        let check = |values: &[i64]| -> Result<bool, SymbolicError> {
+            let mut machine = Machine::new(&self.program);
+            machine.set_instruction_budget(self.max_steps as u64);
+            machine.set_memory_limit(self.memory_limit);
+            for (var, &value) in self.variables.iter().zip(values) {
+                machine.try_mem_set(var.addr, value)?;
+            }
+            for &value in &self.inputs {
+                machine.feed(value);
+            }
+            Ok(match machine.try_run_until_input_needed() {
+                Ok(Outcome::Halted(outputs)) => verify(&machine, &outputs),
+                _ => false,
+            })
+        };
+
+        let all_vars: Vec<_> = (0..self.variables.len()).collect();
+        for path in self.explore()?.iter().filter(|x| x.halted()) {
+            let rv = match expr(path).map(|x| x.to_poly()) {
+                None => continue,
+                Some(Some(poly)) => self.solve_poly(&poly, path.constraints(), target, &check)?,
+                Some(None) => self.enumerate(&all_vars, |values| {
+                    Ok(if check(values)? {
+                        Some(values.to_vec())
+                    } else {
+                        None
+                    })
+                })?,
+            };
+            if rv.is_some() {
+                return Ok(rv);
+            }
+        }
+        Ok(None)
+    }
+
+    fn solve_poly<F>(
+        &self,
+        poly: &Poly,
+        constraints: &[Constraint],
+        target: i64,
+        check: &F,
+    ) -> Result<Option<Vec<i64>>, SymbolicError>
+    where
+        F: Fn(&[i64]) -> Result<bool, SymbolicError>,
+    {
+        let used = poly.variables();
+        let linear = used
+            .iter()
+            .rev()
+            .find_map(|&var| Some((var, poly.split_linear(var)?)));
+
+        // variables the path branched on have to be enumerated as well,
+        // otherwise they stay pinned to a value that may not take the path.
+        let mut vars: BTreeSet<_> = used.iter().copied().collect();
+        for constraint in constraints {
+            constraint.collect_variables(&mut vars);
+        }
+
+        // if the polynomial is `a * var + b` only the other variables need
+        // to be enumerated as `var` follows from them.
+        if let Some((var, (a, b))) = linear {
+            let others: Vec<_> = vars.iter().copied().filter(|&x| x != var).collect();
+            let domain = &self.variables[var].domain;
+            self.enumerate(&others, |values| {
+                let solved = a.eval(values).and_then(|a| {
+                    let rest = target.checked_sub(b.eval(values)?)?;
+                    if a == 0 || rest % a != 0 || !domain.contains(&(rest / a)) {
+                        None
+                    } else {
+                        Some(rest / a)
+                    }
+                });
+                let mut values = values.to_vec();
+                match solved {
+                    Some(value) => values[var] = value,
+                    None => return Ok(None),
+                }
+                Ok(if check(&values)? { Some(values) } else { None })
+            })
+        } else {
+            let vars: Vec<_> = vars.into_iter().collect();
+            self.enumerate(&vars, |values| {
+                Ok(if poly.eval(values) == Some(target) && check(values)? {
+                    Some(values.to_vec())
+                } else {
+                    None
+                })
+            })
+        }
+    }
+
+    /// Enumerates the domains of the given variables, keeping all other
+    /// variables at the start of their domain, until `func` returns a
+    /// solution.
+    fn enumerate<F>(&self, vars: &[usize], mut func: F) -> Result<Option<Vec<i64>>, SymbolicError>
+    where
+        F: FnMut(&[i64]) -> Result<Option<Vec<i64>>, SymbolicError>,
+    {
+        let mut total = 1u64;
+        for &var in vars {
+            let domain = &self.variables[var].domain;
+            let size = (*domain.end() as i128 - *domain.start() as i128 + 1).max(0) as u64;
+            total = total.saturating_mul(size);
+        }
+        if total > self.max_candidates {
+            return Err(SymbolicError::SearchSpaceTooLarge);
+        }
+        if total == 0 {
+            return Ok(None);
+        }
+
+        let mut values: Vec<_> = self.variables.iter().map(|x| *x.domain.start()).collect();
+        loop {
+            if let Some(rv) = func(&values)? {
+                return Ok(Some(rv));
+            }
+            let mut pos = vars.len();
+            loop {
+                if pos == 0 {
+                    return Ok(None);
+                }
+                pos -= 1;
+                let var = vars[pos];
+                let domain = &self.variables[var].domain;
+                if values[var] < *domain.end() {
+                    values[var] += 1;
+                    break;
+                }
+                values[var] = *domain.start();
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+
+use crate::cell::Cell;
+use crate::error::Error;
+use crate::Machine;
+
+/// A single input or output with the instruction count it happened at.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<C: Cell = i64> {
+    /// An input instruction consumed a value.
+    Input { step: u64, value: C },
+    /// An output instruction produced a value.
+    Output { step: u64, value: C },
+}
+
+impl<C: Cell> Event<C> {
+    /// Returns the instruction count at which the event happened.
+    pub fn step(&self) -> u64 {
+        match *self {
+            Event::Input { step, .. } | Event::Output { step, .. } => step,
+        }
+    }
+
+    /// Returns the value that was read or written.
+    pub fn value(&self) -> &C {
+        match *self {
+            Event::Input { ref value, .. } | Event::Output { ref value, .. } => value,
+        }
+    }
+}
+
+/// An error from parsing or replaying a transcript.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranscriptError<C: Cell = i64> {
+    /// A line of a transcript file could not be parsed.
+    Syntax { line: usize, message: String },
+    /// The machine failed during replay.
+    Machine(Error),
+    /// The event happened at a different instruction count.
+    StepMismatch { expected: u64, actual: u64 },
+    /// The machine produced a different output.
+    OutputMismatch { step: u64, expected: C, actual: C },
+    /// The machine produced an output the transcript does not have.
+    UnexpectedOutput { step: u64, value: C },
+    /// The machine wants input where the transcript has an output.
+    UnexpectedInput { step: u64 },
+    /// The machine halted before all events were replayed.
+    Incomplete { remaining: usize },
+}
+
+impl<C: Cell> fmt::Display for TranscriptError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TranscriptError::Syntax { line, ref message } => {
+                write!(f, "line {}: {}", line, message)
+            }
+            TranscriptError::Machine(ref err) => write!(f, "machine failed: {}", err),
+            TranscriptError::StepMismatch { expected, actual } => write!(
+                f,
+                "event at step {}, expected it at step {}",
+                actual, expected
+            ),
+            TranscriptError::OutputMismatch {
+                step,
+                ref expected,
+                ref actual,
+            } => write!(f, "step {}: output {}, expected {}", step, actual, expected),
+            TranscriptError::UnexpectedOutput { step, ref value } => {
+                write!(f, "step {}: unexpected output {}", step, value)
+            }
+            TranscriptError::UnexpectedInput { step } => {
+                write!(f, "step {}: unexpected input", step)
+            }
+            TranscriptError::Incomplete { remaining } => {
+                write!(f, "machine halted with {} events left to replay", remaining)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<C: Cell> error::Error for TranscriptError<C> {}
+
+/// A record of all inputs and outputs of a run.
+///
+/// Transcripts are recorded with `Machine::start_recording`.  Their text
+/// form has one event per line, `in` or `out` followed by the instruction
+/// count and the value:
+///
+/// ```text
+/// in 0 5
+/// out 3 10
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcript<C: Cell = i64> {
+    events: Vec<Event<C>>,
+}
+
+impl<C: Cell> Transcript<C> {
+    /// Creates an empty transcript.
+    pub fn new() -> Transcript<C> {
+        Transcript { events: Vec::new() }
+    }
+
+    /// Parses the text form of a transcript.
+    pub fn parse(source: &str) -> Result<Transcript<C>, TranscriptError<C>> {
+        let mut events = Vec::new();
+        for (idx, line) in source.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let syntax_error = || TranscriptError::Syntax {
+                line: line_no,
+                message: format!("invalid event '{}'", line),
+            };
+            let mut parts = line.split_whitespace();
+            let kind = parts.next().ok_or_else(syntax_error)?;
+            let step = parts
+                .next()
+                .and_then(|x| x.parse().ok())
+                .ok_or_else(syntax_error)?;
+            let value = parts
+                .next()
+                .and_then(|x| x.parse().ok())
+                .ok_or_else(syntax_error)?;
+            if parts.next().is_some() {
+                return Err(syntax_error());
+            }
+            events.push(match kind {
+                "in" => Event::Input { step, value },
+                "out" => Event::Output { step, value },
+                _ => return Err(syntax_error()),
+            });
+        }
+        Ok(Transcript { events })
+    }
+
+    /// Appends an event.
+    pub fn push(&mut self, event: Event<C>) {
+        self.events.push(event);
+    }
+
+    /// Returns all events in order.
+    pub fn events(&self) -> &[Event<C>] {
+        &self.events
+    }
+
+    /// Returns the consumed inputs in order.
+    pub fn inputs(&self) -> Vec<C> {
+        self.events
+            .iter()
+            .filter(|x| matches!(x, Event::Input { .. }))
+            .map(|x| x.value().clone())
+            .collect()
+    }
+
+    /// Returns the produced outputs in order.
+    pub fn outputs(&self) -> Vec<C> {
+        self.events
+            .iter()
+            .filter(|x| matches!(x, Event::Output { .. }))
+            .map(|x| x.value().clone())
+            .collect()
+    }
+
+    /// Replays the transcript on a machine.
+    ///
+    /// Inputs are fed to the machine exactly when it asks for them and
+    /// every output and the instruction count of every event are compared
+    /// with the transcript.  After the last event the machine runs until
+    /// it halts or asks for more input.  The machine should start in the
+    /// state the recording started in without any queued input.
+    pub fn replay(&self, machine: &mut Machine<C>) -> Result<(), TranscriptError<C>> {
+        let mut events = self.events.iter();
+        loop {
+            if machine.halted() {
+                let remaining = events.len();
+                if remaining > 0 {
+                    return Err(TranscriptError::Incomplete { remaining });
+                }
+                return Ok(());
+            }
+            let step = machine.instruction_count();
+            if machine.needs_input() {
+                match events.next() {
+                    Some(&Event::Input {
+                        step: expected,
+                        ref value,
+                    }) => {
+                        check_step(expected, step)?;
+                        machine.feed(value.clone());
+                    }
+                    Some(_) => return Err(TranscriptError::UnexpectedInput { step }),
+                    None => return Ok(()),
+                }
+            }
+            if !machine
+                .try_step_instruction()
+                .map_err(TranscriptError::Machine)?
+                || machine.halted()
+            {
+                continue;
+            }
+            let actual = machine.last_output();
+            match events.next() {
+                Some(&Event::Output {
+                    step: expected_step,
+                    ref value,
+                }) => {
+                    check_step(expected_step, step)?;
+                    if *value != actual {
+                        return Err(TranscriptError::OutputMismatch {
+                            step,
+                            expected: value.clone(),
+                            actual,
+                        });
+                    }
+                }
+                _ => {
+                    return Err(TranscriptError::UnexpectedOutput {
+                        step,
+                        value: actual,
+                    })
+                }
+            }
+        }
+    }
+}
+
+fn check_step<C: Cell>(expected: u64, actual: u64) -> Result<(), TranscriptError<C>> {
+    if expected != actual {
+        Err(TranscriptError::StepMismatch { expected, actual })
+    } else {
+        Ok(())
+    }
+}
+
+impl<C: Cell> fmt::Display for Transcript<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            match *event {
+                Event::Input { step, ref value } => writeln!(f, "in {} {}", step, value)?,
+                Event::Output { step, ref value } => writeln!(f, "out {} {}", step, value)?,
+            }
+        }
+        Ok(())
+    }
+}
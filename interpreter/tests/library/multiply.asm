@@ -0,0 +1,16 @@
+; multiply: multiply_result = multiply_a * multiply_b
+;
+; Like every routine in the library, arguments are passed in exported
+; cells and the caller stores the address to return to in multiply_ret.
+        global multiply, multiply_a, multiply_b, multiply_result, multiply_ret
+multiply:
+        mul multiply_a, multiply_b, multiply_result
+        jt #1, multiply_ret
+multiply_a:
+        data 0
+multiply_b:
+        data 0
+multiply_result:
+        data 0
+multiply_ret:
+        data 0
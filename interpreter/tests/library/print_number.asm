@@ -0,0 +1,46 @@
+; print_number: outputs print_number_value as ASCII decimal digits
+;
+; Negative numbers get a leading minus.  The value is clobbered.
+        global print_number, print_number_value, print_number_ret
+print_number:
+        lt print_number_value, #0, flag
+        jf flag, #positive
+        out #45
+        mul print_number_value, #-1, print_number_value
+positive:
+        add #powers, #0, load+1
+        add #0, #0, started
+load:   add 0, #0, power        ; the first operand walks the powers
+        eq power, #1, last
+        mul power, #-1, power
+        add #0, #0, digit
+count:  add print_number_value, power, rest
+        lt rest, #0, flag
+        jt flag, #emit
+        add rest, #0, print_number_value
+        add digit, #1, digit
+        jt #1, #count
+emit:   add started, digit, flag
+        add flag, last, flag    ; skip leading zeros but not the last digit
+        jf flag, #skip
+        add #1, #0, started
+        add digit, #48, digit
+        out digit
+skip:   jt last, print_number_ret
+        add load+1, #1, load+1
+        jt #1, #load
+powers: data 1000000000000000000, 100000000000000000, 10000000000000000
+        data 1000000000000000, 100000000000000, 10000000000000
+        data 1000000000000, 100000000000, 10000000000, 1000000000
+        data 100000000, 10000000, 1000000, 100000, 10000, 1000, 100, 10, 1
+flag:   data 0
+started:
+        data 0
+power:  data 0
+last:   data 0
+digit:  data 0
+rest:   data 0
+print_number_value:
+        data 0
+print_number_ret:
+        data 0
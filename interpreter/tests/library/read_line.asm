@@ -0,0 +1,23 @@
+; read_line: reads input up to a newline into memory
+;
+; The characters are stored starting at the address in read_line_buffer
+; without the newline and their number is stored in read_line_length.
+        global read_line, read_line_buffer, read_line_length, read_line_ret
+read_line:
+        add read_line_buffer, #0, store+3
+        add #0, #0, read_line_length
+next:   in char
+        eq char, #10, done
+        jt done, read_line_ret
+store:  add char, #0, 0         ; the target walks the buffer
+        add store+3, #1, store+3
+        add read_line_length, #1, read_line_length
+        jt #1, #next
+char:   data 0
+done:   data 0
+read_line_buffer:
+        data 0
+read_line_length:
+        data 0
+read_line_ret:
+        data 0
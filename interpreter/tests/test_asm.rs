@@ -0,0 +1,97 @@
+use interpreter::{assemble, disassemble, disassemble_instruction, Machine};
+
+#[test]
+fn test_assemble_basic() {
+    assert_eq!(
+        assemble("add #1, #2, 5\nout 5\nhlt").unwrap(),
+        vec![1101, 1, 2, 5, 4, 5, 99]
+    );
+    assert_eq!(
+        assemble("arb #10\nin @-1\n").unwrap(),
+        vec![109, 10, 203, -1]
+    );
+}
+
+#[test]
+fn test_assemble_labels() {
+    let program = assemble(
+        "
+        ; echoes input until it reads zero
+        loop:   in value
+                jf value, #end
+                out value
+                jt #1, #loop
+        end:    hlt
+        value:  data 0
+        ",
+    )
+    .unwrap();
+    let mut machine = Machine::new(&program);
+    for value in &[3, 2, 1, 0] {
+        machine.feed(*value);
+    }
+    assert_eq!(machine.eval_multi(), vec![3, 2, 1]);
+}
+
+#[test]
+fn test_assemble_label_offsets() {
+    assert_eq!(
+        assemble("a: add #1, #2, a+3\ndata a-1, a").unwrap(),
+        vec![1101, 1, 2, 3, -1, 0]
+    );
+}
+
+#[test]
+fn test_assemble_global() {
+    // only objects export labels, plain programs ignore the directive
+    assert_eq!(
+        assemble(
+            "global a, b
+a: out b
+b: hlt"
+        )
+        .unwrap(),
+        vec![4, 2, 99]
+    );
+    let err = assemble(
+        "global a
+hlt",
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "line 1: undefined label 'a'");
+}
+
+#[test]
+fn test_assemble_errors() {
+    let err = assemble("add #1, #2\n").unwrap_err();
+    assert_eq!(err.line(), 1);
+    assert_eq!(err.to_string(), "line 1: add takes 3 operands, got 2");
+    let err = assemble("hlt\nfoo 1").unwrap_err();
+    assert_eq!(err.to_string(), "line 2: unknown mnemonic 'foo'");
+    let err = assemble("jt #1, #missing").unwrap_err();
+    assert_eq!(err.to_string(), "line 1: undefined label 'missing'");
+    let err = assemble("x: hlt\nx: hlt").unwrap_err();
+    assert_eq!(err.to_string(), "line 2: duplicate label 'x'");
+}
+
+#[test]
+fn test_disassemble() {
+    let program = [1101, 1, 2, 5, 204, -3, 99, 42];
+    assert_eq!(
+        disassemble_instruction(&program, 0),
+        ("add #1, #2, 5".to_string(), 4)
+    );
+    assert_eq!(
+        disassemble(&program),
+        "    0: add #1, #2, 5\n    4: out @-3\n    6: hlt\n    7: data 42\n"
+    );
+}
+
+#[test]
+fn test_roundtrip() {
+    let source = "in 9\nmul 9, #3, 10\nout 10\nhlt";
+    let program = assemble(source).unwrap();
+    let listing = disassemble(&program[..9]);
+    let lines: Vec<_> = listing.lines().map(|x| x[7..].to_string()).collect();
+    assert_eq!(lines.join("\n"), source);
+}
@@ -0,0 +1,249 @@
+#![cfg(feature = "async")]
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use futures_core::Stream;
+use interpreter::{intcode, parse_ascii_program, AsyncMachine, Error, Machine};
+
+/// Counts how often a task was woken.
+#[derive(Default)]
+struct Wakeups(AtomicUsize);
+
+impl Wake for Wakeups {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Wakeups {
+    fn take(&self) -> usize {
+        self.0.swap(0, Ordering::SeqCst)
+    }
+}
+
+/// Runs tasks on the current thread until all of them finished.
+///
+/// Tasks are only polled again after they were woken.
+fn run(futures: Vec<Pin<Box<dyn Future<Output = ()>>>>) {
+    let mut tasks: Vec<_> = futures
+        .into_iter()
+        .map(|future| {
+            let wakeups = Arc::new(Wakeups::default());
+            wakeups.0.store(1, Ordering::SeqCst);
+            (future, wakeups)
+        })
+        .collect();
+    while !tasks.is_empty() {
+        let mut progress = false;
+        tasks.retain_mut(|(future, wakeups)| {
+            if wakeups.take() == 0 {
+                return true;
+            }
+            progress = true;
+            let waker = Waker::from(wakeups.clone());
+            future
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_pending()
+        });
+        assert!(progress, "all tasks are waiting");
+    }
+}
+
+#[derive(Default)]
+struct Channel {
+    values: VecDeque<i64>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+#[derive(Clone)]
+struct Sender(Rc<RefCell<Channel>>);
+
+impl Sender {
+    fn send(&self, value: i64) {
+        let mut channel = self.0.borrow_mut();
+        channel.values.push_back(value);
+        if let Some(waker) = channel.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn close(&self) {
+        let mut channel = self.0.borrow_mut();
+        channel.closed = true;
+        if let Some(waker) = channel.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+struct Receiver(Rc<RefCell<Channel>>);
+
+impl Stream for Receiver {
+    type Item = i64;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<i64>> {
+        let mut channel = self.0.borrow_mut();
+        if let Some(value) = channel.values.pop_front() {
+            Poll::Ready(Some(value))
+        } else if channel.closed {
+            Poll::Ready(None)
+        } else {
+            channel.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+fn channel() -> (Sender, Receiver) {
+    let channel = Rc::new(RefCell::new(Channel::default()));
+    (Sender(channel.clone()), Receiver(channel))
+}
+
+async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+fn poll<S: Stream + Unpin>(stream: &mut S, wakeups: &Arc<Wakeups>) -> Poll<Option<S::Item>> {
+    let waker = Waker::from(wakeups.clone());
+    Pin::new(stream).poll_next(&mut Context::from_waker(&waker))
+}
+
+#[test]
+fn test_outputs() {
+    let (tx, rx) = channel();
+    tx.send(1);
+    tx.close();
+    let program = parse_ascii_program(include_str!("../../aoc9/input.txt"));
+    let mut machine = AsyncMachine::new(Machine::new(&program), rx);
+    let outputs = Rc::new(RefCell::new(vec![]));
+    let collected = outputs.clone();
+    run(vec![Box::pin(async move {
+        while let Some(output) = next(&mut machine).await {
+            collected.borrow_mut().push(output.unwrap());
+        }
+        assert!(machine.machine().halted());
+    })]);
+    assert_eq!(*outputs.borrow(), vec![4261108180]);
+}
+
+#[test]
+fn test_suspends_for_input() {
+    let (tx, rx) = channel();
+    let mut machine = AsyncMachine::new(
+        Machine::new(intcode! {
+        start:
+            in value;
+            out value;
+            jt #1, #start;
+        value:
+            data 0;
+        }),
+        rx,
+    );
+    let wakeups = Arc::new(Wakeups::default());
+    assert_eq!(poll(&mut machine, &wakeups), Poll::Pending);
+    assert_eq!(wakeups.take(), 0);
+
+    tx.send(5);
+    tx.send(6);
+    assert_eq!(wakeups.take(), 1);
+    assert_eq!(poll(&mut machine, &wakeups), Poll::Ready(Some(Ok(5))));
+    assert_eq!(poll(&mut machine, &wakeups), Poll::Ready(Some(Ok(6))));
+    assert_eq!(poll(&mut machine, &wakeups), Poll::Pending);
+
+    // the inputs ending while the machine waits ends the outputs
+    tx.close();
+    assert_eq!(wakeups.take(), 1);
+    assert_eq!(poll(&mut machine, &wakeups), Poll::Ready(None));
+    assert!(!machine.machine().halted());
+    assert_eq!(poll(&mut machine, &wakeups), Poll::Ready(None));
+}
+
+#[test]
+fn test_errors() {
+    let (_tx, rx) = channel();
+    let mut machine = AsyncMachine::new(Machine::new(&[42]), rx);
+    let wakeups = Arc::new(Wakeups::default());
+    assert_eq!(
+        poll(&mut machine, &wakeups),
+        Poll::Ready(Some(Err(Error::InvalidOpcode { ip: 0, opcode: 42 })))
+    );
+    assert_eq!(poll(&mut machine, &wakeups), Poll::Ready(None));
+}
+
+#[test]
+fn test_yields_while_computing() {
+    let (_tx, rx) = channel();
+    let mut machine = AsyncMachine::new(
+        Machine::new(intcode! {
+        start:
+            jt #1, #start;
+        }),
+        rx,
+    );
+    let wakeups = Arc::new(Wakeups::default());
+    assert_eq!(poll(&mut machine, &wakeups), Poll::Pending);
+    assert_eq!(wakeups.take(), 1);
+    let count = machine.machine().instruction_count();
+    assert!(count > 0);
+    assert_eq!(poll(&mut machine, &wakeups), Poll::Pending);
+    assert_eq!(machine.machine().instruction_count(), count * 2);
+}
+
+fn permutations(items: Vec<i64>) -> Vec<Vec<i64>> {
+    if items.len() <= 1 {
+        return vec![items];
+    }
+    let mut rv = vec![];
+    for idx in 0..items.len() {
+        let mut rest = items.clone();
+        let first = rest.remove(idx);
+        for mut permutation in permutations(rest) {
+            permutation.insert(0, first);
+            rv.push(permutation);
+        }
+    }
+    rv
+}
+
+#[test]
+fn test_feedback_loop() {
+    // all amplifiers of every phase setting run as tasks on one thread
+    let program = parse_ascii_program(include_str!("../../aoc7/input.txt"));
+    let results = Rc::new(RefCell::new(vec![]));
+    let mut tasks: Vec<Pin<Box<dyn Future<Output = ()>>>> = vec![];
+    for phases in permutations((5..10).collect()) {
+        let channels: Vec<_> = (0..5).map(|_| channel()).collect();
+        let senders: Vec<_> = channels.iter().map(|x| x.0.clone()).collect();
+        senders[0].send(0);
+        for (idx, ((_, rx), phase)) in channels.into_iter().zip(phases).enumerate() {
+            let mut machine = Machine::new(&program);
+            machine.feed(phase);
+            let mut machine = AsyncMachine::new(machine, rx);
+            let tx = senders[(idx + 1) % 5].clone();
+            let results = results.clone();
+            tasks.push(Box::pin(async move {
+                let mut last = 0;
+                while let Some(output) = next(&mut machine).await {
+                    last = output.unwrap();
+                    tx.send(last);
+                }
+                assert!(machine.machine().halted());
+                if idx == 4 {
+                    results.borrow_mut().push(last);
+                }
+            }));
+        }
+    }
+    run(tasks);
+    assert_eq!(results.borrow().len(), 120);
+    assert_eq!(results.borrow().iter().max(), Some(&17279674));
+}
@@ -1,7 +1,7 @@
-use interpreter::Machine;
+use interpreter::{parse_numbers, Machine};
 
 fn eval(code: &str, input: i64) -> Vec<i64> {
-    let mut machine = Machine::from_ascii_code(code);
+    let mut machine = Machine::from_ascii_program(code);
     machine.feed(input);
     machine.eval_multi()
 }
@@ -51,3 +51,21 @@ fn test_basic_io() {
         vec![1001]
     );
 }
+
+#[test]
+fn test_instruction_count() {
+    let mut machine = Machine::from_ascii_program("1101,1,1,5,4,0,99");
+    machine.step();
+    assert_eq!(machine.instruction_count(), 2);
+    machine.step();
+    assert_eq!(machine.instruction_count(), 3);
+}
+
+#[test]
+fn test_parse_numbers() {
+    assert_eq!(parse_numbers("1,-2\n 3 4,\n"), Ok(vec![1, -2, 3, 4]));
+    assert_eq!(parse_numbers(""), Ok(vec![]));
+    let err = parse_numbers("1,x2,3").unwrap_err();
+    assert_eq!(err.token(), "x2");
+    assert_eq!(err.to_string(), "invalid number 'x2'");
+}
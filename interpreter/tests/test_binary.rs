@@ -0,0 +1,82 @@
+use interpreter::{
+    decode_program, encode_program, format_ascii_program, parse_ascii_program, BinaryError,
+    Machine, BINARY_MAGIC,
+};
+
+#[test]
+fn test_roundtrip() {
+    let programs: &[&[i64]] = &[
+        &[],
+        &[99],
+        &[1, -1, 0, 63, 64, -64, -65, 1 << 40, i64::MAX, i64::MIN],
+    ];
+    for &program in programs {
+        let data = encode_program(program);
+        assert!(data.starts_with(BINARY_MAGIC));
+        assert_eq!(decode_program(&data).unwrap(), program);
+    }
+}
+
+#[test]
+fn test_encoding() {
+    assert_eq!(
+        encode_program(&[1, -1, 64]),
+        b"\x7fICB\x01\x03\x7d\xd5\x0b\xc6\x02\x01\x80\x01"
+    );
+}
+
+#[test]
+fn test_day9_program() {
+    let program = parse_ascii_program(include_str!("../../aoc9/input.txt"));
+    let data = encode_program(&program);
+    assert!(data.len() < format_ascii_program(&program).len() / 2);
+
+    let mut machine = Machine::from_binary_program(&data).unwrap();
+    machine.feed(1);
+    assert_eq!(machine.eval(), 4_261_108_180);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_read_write() {
+    use interpreter::{read_binary_program, write_binary_program};
+
+    let program = parse_ascii_program(include_str!("../../aoc9/input.txt"));
+    let mut buf = Vec::new();
+    write_binary_program(&mut buf, &program).unwrap();
+    assert_eq!(read_binary_program(&buf[..]).unwrap(), program);
+}
+
+#[test]
+fn test_errors() {
+    let data = encode_program(&[1, 2, 3]);
+    assert!(matches!(
+        decode_program(b"1,2,3"),
+        Err(BinaryError::BadMagic)
+    ));
+
+    let mut bad = data.clone();
+    bad[4] = 2;
+    assert!(matches!(
+        decode_program(&bad),
+        Err(BinaryError::UnsupportedVersion(2))
+    ));
+
+    let mut bad = data.clone();
+    *bad.last_mut().unwrap() = 4;
+    assert!(matches!(
+        decode_program(&bad),
+        Err(BinaryError::ChecksumMismatch { .. })
+    ));
+
+    assert!(matches!(
+        decode_program(&data[..7]),
+        Err(BinaryError::Truncated)
+    ));
+}
+
+#[test]
+fn test_format_ascii_program() {
+    assert_eq!(format_ascii_program(&[1, -2, 99]), "1,-2,99");
+    assert_eq!(parse_ascii_program("1,-2,99"), vec![1, -2, 99]);
+}
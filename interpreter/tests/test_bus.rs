@@ -0,0 +1,124 @@
+#![cfg(feature = "std")]
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use interpreter::{assemble, Device, Error, Machine, Patch};
+
+#[derive(Default)]
+struct Clock {
+    ticks: i64,
+}
+
+impl Device for Clock {
+    fn read(&mut self, _offset: usize, _stored: &i64) -> i64 {
+        self.ticks += 1;
+        self.ticks
+    }
+}
+
+#[derive(Default)]
+struct FrameBuffer {
+    pixels: Vec<(usize, i64)>,
+}
+
+impl Device for FrameBuffer {
+    fn write(&mut self, offset: usize, value: i64) -> i64 {
+        self.pixels.push((offset, value));
+        value
+    }
+}
+
+#[test]
+fn test_clock() {
+    let program = assemble("out 100\nout 100\nadd 100, #0, 0\nhlt").unwrap();
+    let mut machine = Machine::new(&program);
+    machine.map_device(100..101, Arc::new(Mutex::new(Clock::default())));
+    assert_eq!(machine.eval_multi(), vec![1, 2]);
+    assert_eq!(machine.mem_get(0), 3);
+}
+
+#[test]
+fn test_framebuffer() {
+    let program = assemble("add #1, #2, 1001\nmul #3, #4, 1000\nhlt").unwrap();
+    let fb = Arc::new(Mutex::new(FrameBuffer::default()));
+    let mut machine = Machine::new(&program);
+    machine.map_device(1000..1004, fb.clone());
+    machine.eval();
+    assert_eq!(fb.lock().unwrap().pixels, vec![(1, 3), (0, 12)]);
+    assert_eq!(machine.mem()[1000..1002], [12, 3]);
+}
+
+#[test]
+fn test_patch_bypasses_devices() {
+    let fb = Arc::new(Mutex::new(FrameBuffer::default()));
+    let mut machine = Machine::new(&[1, 2, 3]);
+    machine.map_device(1..5, fb.clone());
+    let patch = Patch::parse("1: 2 -> 7\n4 = 9").unwrap();
+    patch.apply(&mut machine).unwrap();
+    assert_eq!(machine.mem(), &[1, 7, 3, 0, 9]);
+    assert!(fb.lock().unwrap().pixels.is_empty());
+}
+
+#[test]
+fn test_write_filter() {
+    struct ReadOnly;
+    impl Device for ReadOnly {
+        fn write(&mut self, _offset: usize, _value: i64) -> i64 {
+            42
+        }
+    }
+    let mut machine = Machine::from_ascii_program("1101,1,1,5,99,0");
+    machine.map_device(5..6, Arc::new(Mutex::new(ReadOnly)));
+    machine.eval();
+    assert_eq!(machine.mem_get(5), 42);
+    assert!(machine.unmap_device(5));
+    assert!(!machine.unmap_device(5));
+    machine.mem_set(5, 7);
+    assert_eq!(machine.mem_get(5), 7);
+}
+
+#[test]
+#[should_panic(expected = "device ranges overlap")]
+fn test_overlap() {
+    let mut machine = Machine::new(&[99]);
+    machine.map_device(10..20, Arc::new(Mutex::new(Clock::default())));
+    machine.map_device(15..25, Arc::new(Mutex::new(Clock::default())));
+}
+
+#[test]
+#[should_panic(expected = "device range is empty")]
+fn test_empty_range() {
+    let mut machine = Machine::new(&[99]);
+    machine.map_device(10..10, Arc::new(Mutex::new(Clock::default())));
+}
+
+#[test]
+fn test_instructions_bypass_devices() {
+    let mut machine = Machine::new(&assemble("out 100\nin 100\nout 100\nhlt").unwrap());
+    let clock = Arc::new(Mutex::new(Clock::default()));
+    machine.map_device(0..101, clock.clone());
+    machine.set_history_limit(10);
+    assert!(!machine.needs_input());
+    assert_eq!(machine.eval_multi(), vec![1, 2]);
+    assert_eq!(clock.lock().unwrap().ticks, 2);
+}
+
+#[test]
+fn test_poisoned_device() {
+    let clock = Arc::new(Mutex::new(Clock::default()));
+    let poisoned = clock.clone();
+    thread::spawn(move || {
+        let _guard = poisoned.lock().unwrap();
+        panic!("device failure");
+    })
+    .join()
+    .unwrap_err();
+
+    let mut machine = Machine::new(&assemble("out 100\nhlt").unwrap());
+    machine.map_device(100..101, clock);
+    assert_eq!(machine.try_step(), Err(Error::DeviceFailed { ip: 0 }));
+    assert_eq!(machine.try_mem_get(100), Err(Error::DeviceFailed { ip: 0 }));
+    assert_eq!(machine.try_mem_get(2), Ok(99));
+    assert!(machine.try_mem_set(100, 1).is_err());
+}
@@ -0,0 +1,36 @@
+#![cfg(not(feature = "std"))]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use interpreter::{assemble, Device, Error, Machine};
+
+#[derive(Default)]
+struct Counter {
+    reads: i64,
+}
+
+impl Device for Counter {
+    fn read(&mut self, _offset: usize, _stored: &i64) -> i64 {
+        self.reads += 1;
+        self.reads
+    }
+}
+
+#[test]
+fn test_device_without_std() {
+    let counter = Rc::new(RefCell::new(Counter::default()));
+    let mut machine = Machine::new(&assemble("out 100\nout 100\nhlt").unwrap());
+    machine.map_device(100..101, counter.clone());
+    assert_eq!(machine.eval_multi(), vec![1, 2]);
+    assert_eq!(counter.borrow().reads, 2);
+}
+
+#[test]
+fn test_borrowed_device() {
+    let counter = Rc::new(RefCell::new(Counter::default()));
+    let mut machine = Machine::new(&assemble("out 100\nhlt").unwrap());
+    machine.map_device(100..101, counter.clone());
+    let _borrow = counter.borrow_mut();
+    assert_eq!(machine.try_step(), Err(Error::DeviceFailed { ip: 0 }));
+}
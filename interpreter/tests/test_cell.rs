@@ -0,0 +1,50 @@
+use interpreter::{Error, Machine};
+
+const LARGE_PRODUCT: &str = "1102,34915192,34915192,7,4,7,99,0";
+const OVERFLOWING_PRODUCT: &str = "1102,4294967296,4294967296,7,4,7,99,0";
+
+#[test]
+fn test_i64_large_numbers() {
+    let mut machine = Machine::from_ascii_program(LARGE_PRODUCT);
+    assert_eq!(machine.eval(), 1_219_070_632_396_864);
+}
+
+#[test]
+fn test_i64_overflow() {
+    let mut machine = Machine::from_ascii_program(OVERFLOWING_PRODUCT);
+    assert_eq!(machine.try_eval(), Err(Error::Overflow { ip: 0 }));
+}
+
+#[test]
+fn test_i128_no_overflow() {
+    let mut machine = Machine::<i128>::from_ascii_program_as(OVERFLOWING_PRODUCT);
+    assert_eq!(machine.eval(), 1 << 64);
+}
+
+#[test]
+fn test_invalid_opcode() {
+    let mut machine = Machine::from_ascii_program("1101,1,1,5,42,0");
+    assert_eq!(
+        machine.try_eval(),
+        Err(Error::InvalidOpcode { ip: 4, opcode: 42 })
+    );
+}
+
+#[test]
+fn test_invalid_address() {
+    let mut machine = Machine::from_ascii_program("4,-1,99");
+    assert_eq!(machine.try_eval(), Err(Error::InvalidAddress { ip: 0 }));
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn test_bignum() {
+    use num_bigint::BigInt;
+    let mut machine = Machine::<BigInt>::from_ascii_program_as(
+        "1102,18446744073709551616,18446744073709551616,7,4,7,99,0",
+    );
+    assert_eq!(
+        machine.eval().to_string(),
+        "340282366920938463463374607431768211456"
+    );
+}
@@ -0,0 +1,104 @@
+use interpreter::{assemble, CellFilter, CheatFinder, Machine, MemorySnapshot};
+
+#[test]
+fn test_snapshot_diff() {
+    let mut machine = Machine::new(&[1, 2, 3]);
+    let before = MemorySnapshot::take(&machine);
+    machine.mem_set(1, 5);
+    machine.mem_set(4, 7);
+    let after = MemorySnapshot::take(&machine);
+    assert_eq!(before.diff(&after), vec![(1, 2, 5), (4, 0, 7)]);
+    assert_eq!(after.get(100), 0);
+}
+
+#[test]
+fn test_filters() {
+    assert!(CellFilter::Changed.matches(1, 2));
+    assert!(CellFilter::Unchanged.matches(2, 2));
+    assert!(CellFilter::Increased.matches(1, 2));
+    assert!(!CellFilter::Increased.matches(2, 2));
+    assert!(CellFilter::Decreased.matches(3, 2));
+    assert!(CellFilter::Equals(2).matches(7, 2));
+}
+
+#[test]
+fn test_find_counter() {
+    // counts the inputs in one cell and sums them up in another
+    let mut machine = Machine::new(
+        &assemble(
+            "
+            loop:   in value
+                    add value, sum, sum
+                    add #1, count, count
+                    jt #1, #loop
+            value:  data 0
+            sum:    data 0
+            count:  data 0
+            ",
+        )
+        .unwrap(),
+    );
+    machine.run_until_input_needed();
+    let mut finder = CheatFinder::new(&machine);
+
+    machine.feed(5);
+    machine.run_until_input_needed();
+    assert_eq!(finder.filter(&machine, CellFilter::Increased), 3);
+
+    machine.feed(0);
+    machine.run_until_input_needed();
+    assert_eq!(finder.filter(&machine, CellFilter::Increased), 1);
+    assert_eq!(finder.values(&machine), vec![(15, 2)]);
+}
+
+#[test]
+fn test_grown_memory() {
+    let mut machine = Machine::new(&[1, 2, 3]);
+    let mut finder = CheatFinder::new(&machine);
+    machine.mem_set(5, 0);
+    assert_eq!(finder.filter(&machine, CellFilter::Unchanged), 6);
+    machine.mem_set(5, 4);
+    machine.mem_set(0, 2);
+    assert_eq!(finder.filter(&machine, CellFilter::Increased), 2);
+    assert_eq!(finder.values(&machine), vec![(0, 2), (5, 4)]);
+
+    // a zero cell could not have increased so new cells are not candidates
+    machine.mem_set(9, 1);
+    assert_eq!(finder.filter(&machine, CellFilter::Unchanged), 2);
+    assert_eq!(finder.candidates(), &[0, 5]);
+}
+
+#[test]
+fn test_find_paddle() {
+    let mut machine = Machine::from_ascii_program(include_str!("../../aoc13/input.txt"));
+    machine.mem_set(0, 2);
+
+    // tracks the paddle position from the drawing commands
+    let mut paddle_x = 0;
+    let mut frame = |machine: &mut Machine| {
+        for cmd in machine.run_until_input_needed().outputs().chunks(3) {
+            if cmd[2] == 3 {
+                paddle_x = cmd[0];
+            }
+        }
+        paddle_x
+    };
+
+    frame(&mut machine);
+    let mut finder = CheatFinder::new(&machine);
+    for &(input, filter) in &[
+        (0, CellFilter::Unchanged),
+        (1, CellFilter::Increased),
+        (-1, CellFilter::Decreased),
+        (0, CellFilter::Unchanged),
+        (1, CellFilter::Increased),
+        (1, CellFilter::Increased),
+        (-1, CellFilter::Decreased),
+    ] {
+        machine.feed(input);
+        frame(&mut machine);
+        finder.filter(&machine, filter);
+    }
+    let paddle_x = frame(&mut machine);
+    assert_eq!(finder.values(&machine), vec![(392, paddle_x)]);
+}
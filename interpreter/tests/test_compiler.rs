@@ -0,0 +1,306 @@
+use interpreter::{compile, compile_to_assembly, Machine};
+
+fn run(source: &str, inputs: &[i64]) -> Vec<i64> {
+    let mut machine = Machine::new(&compile(source).unwrap());
+    for &input in inputs {
+        machine.feed(input);
+    }
+    let outcome = machine.run_until(|_| false);
+    assert!(outcome.is_halted());
+    outcome.into_outputs()
+}
+
+fn compile_error(source: &str) -> (usize, String) {
+    let err = compile(source).unwrap_err();
+    (err.line(), err.message().to_string())
+}
+
+#[test]
+fn test_arithmetic() {
+    let source = "
+        fn main() {
+            let a = input();
+            let b = input();
+            output(a + b * 2);
+            output(a - b);
+            output(-(a - 10) * 3);
+            output(a - -b);
+            output(7 - 3 - 2);
+        }
+    ";
+    assert_eq!(run(source, &[5, 3]), vec![11, 2, 15, 8, 2]);
+}
+
+#[test]
+fn test_comparisons() {
+    let source = "
+        fn main() {
+            let a = input();
+            let b = input();
+            output(a < b);
+            output(a <= b);
+            output(a > b);
+            output(a >= b);
+            output(a == b);
+            output(a != b);
+            output(!a);
+        }
+    ";
+    assert_eq!(run(source, &[1, 2]), vec![1, 1, 0, 0, 0, 1, 0]);
+    assert_eq!(run(source, &[2, 2]), vec![0, 1, 0, 1, 1, 0, 0]);
+    assert_eq!(run(source, &[0, -1]), vec![0, 0, 1, 1, 0, 1, 1]);
+}
+
+#[test]
+fn test_short_circuit() {
+    let source = "
+        let calls = 0;
+
+        fn check(x) {
+            calls = calls + 1;
+            return x;
+        }
+
+        fn main() {
+            output(check(0) && check(1));
+            output(check(1) || check(0));
+            output(check(1) && check(2));
+            output(check(0) || check(0));
+            output(calls);
+        }
+    ";
+    assert_eq!(run(source, &[]), vec![0, 1, 1, 0, 6]);
+}
+
+#[test]
+fn test_control_flow() {
+    let source = "
+        fn classify(x) {
+            if x < 0 {
+                return -1;
+            } else if x == 0 {
+                return 0;
+            } else {
+                return 1;
+            }
+        }
+
+        fn main() {
+            let i = 0;
+            while 1 {
+                i = i + 1;
+                if i == 3 {
+                    continue;
+                }
+                if i > 5 {
+                    break;
+                }
+                output(i);
+            }
+            output(classify(-5));
+            output(classify(0));
+            output(classify(9));
+        }
+    ";
+    assert_eq!(run(source, &[]), vec![1, 2, 4, 5, -1, 0, 1]);
+}
+
+#[test]
+fn test_scopes() {
+    let source = "
+        fn main() {
+            let x = 1;
+            if 1 {
+                let x = x + 10;
+                output(x);
+            }
+            output(x);
+        }
+    ";
+    assert_eq!(run(source, &[]), vec![11, 1]);
+}
+
+#[test]
+fn test_recursion() {
+    let source = "
+        fn fib(n) {
+            if n < 2 {
+                return n;
+            }
+            return fib(n - 1) + fib(n - 2);
+        }
+
+        fn ackermann(m, n) {
+            if m == 0 {
+                return n + 1;
+            }
+            if n == 0 {
+                return ackermann(m - 1, 1);
+            }
+            return ackermann(m - 1, ackermann(m, n - 1));
+        }
+
+        fn main() {
+            output(fib(input()));
+            output(ackermann(2, 3));
+        }
+    ";
+    assert_eq!(run(source, &[20]), vec![6765, 9]);
+}
+
+#[test]
+fn test_primes() {
+    let source = "
+        // integer division by repeated subtraction
+        fn div(a, b) {
+            let q = 0;
+            while a >= b {
+                a = a - b;
+                q = q + 1;
+            }
+            return q;
+        }
+
+        fn is_prime(n) {
+            let d = 2;
+            while d * d <= n {
+                if n - div(n, d) * d == 0 {
+                    return 0;
+                }
+                d = d + 1;
+            }
+            return n > 1;
+        }
+
+        fn main() {
+            let n = 0;
+            let limit = input();
+            while n < limit {
+                if is_prime(n) {
+                    output(n);
+                }
+                n = n + 1;
+            }
+        }
+    ";
+    assert_eq!(run(source, &[30]), vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+}
+
+#[test]
+fn test_ascii_io() {
+    let source = "
+        // echoes a line in upper case
+        fn main() {
+            let c = input();
+            while c != '\\n' {
+                if c >= 'a' && c <= 'z' {
+                    c = c - 'a' + 'A';
+                }
+                output(c);
+                c = input();
+            }
+            halt();
+            output(0);
+        }
+    ";
+    let inputs: Vec<i64> = "hello, world\n".bytes().map(i64::from).collect();
+    let outputs: String = run(source, &inputs)
+        .into_iter()
+        .map(|x| x as u8 as char)
+        .collect();
+    assert_eq!(outputs, "HELLO, WORLD");
+}
+
+#[test]
+fn test_assembly() {
+    let assembly = compile_to_assembly("fn main() { output(42); }").unwrap();
+    assert!(assembly.contains("fn_main:\n    out #42\n"));
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(
+        compile_error("fn foo() {}"),
+        (1, "missing function 'main'".into())
+    );
+    assert_eq!(
+        compile_error("fn main() {\n  output(x);\n}"),
+        (2, "undefined variable 'x'".into())
+    );
+    assert_eq!(
+        compile_error("fn main() {\n  x = 1;\n}"),
+        (2, "undefined variable 'x'".into())
+    );
+    assert_eq!(
+        compile_error("fn main() {\n  foo();\n}"),
+        (2, "undefined function 'foo'".into())
+    );
+    assert_eq!(
+        compile_error("fn f(a) {}\nfn main() { f(1, 2); }"),
+        (2, "f takes 1 arguments, got 2".into())
+    );
+    assert_eq!(
+        compile_error("fn main() {}\nfn main() {}"),
+        (2, "duplicate function 'main'".into())
+    );
+    assert_eq!(
+        compile_error("fn input() {}\nfn main() {}"),
+        (1, "duplicate function 'input'".into())
+    );
+    assert_eq!(
+        compile_error("let x = 1;\nlet x = 2;\nfn main() { output(x); }"),
+        (2, "duplicate global 'x'".into())
+    );
+    assert_eq!(
+        compile_error("fn f(a,\n  a) {}\nfn main() {}"),
+        (2, "duplicate parameter 'a'".into())
+    );
+    assert_eq!(
+        compile_error("fn main() {\n  break;\n}"),
+        (2, "not inside a loop".into())
+    );
+    assert_eq!(
+        compile_error("fn main() {\n  let = 1;\n}"),
+        (2, "expected a name, found '='".into())
+    );
+    assert_eq!(
+        compile_error("fn main() {\n  output(1)\n}"),
+        (3, "expected ';', found '}'".into())
+    );
+    assert_eq!(
+        compile_error("fn main() {\n  output(1 $ 2);\n}"),
+        (2, "unexpected character '$'".into())
+    );
+    assert_eq!(
+        compile_error("fn main() {"),
+        (1, "expected an expression, found end of input".into())
+    );
+    assert_eq!(
+        compile_error("fn main(x) {}"),
+        (1, "main cannot take arguments".into())
+    );
+}
+
+#[test]
+fn test_nesting_limit() {
+    let nested = |depth| {
+        format!(
+            "fn main() {{ output({}1{}); }}",
+            "-(".repeat(depth),
+            ")".repeat(depth)
+        )
+    };
+    assert_eq!(run(&nested(60), &[]), vec![1]);
+    assert_eq!(
+        compile_error(&nested(100_000)),
+        (1, "nesting too deep".into())
+    );
+    let sum = format!("fn main() {{ output(0{}); }}", " + 1".repeat(100_000));
+    assert_eq!(compile_error(&sum), (1, "nesting too deep".into()));
+    let blocks = format!(
+        "fn main() {{\n{}{} }}",
+        "if 1 { ".repeat(100_000),
+        "}".repeat(100_000)
+    );
+    assert_eq!(compile_error(&blocks), (2, "nesting too deep".into()));
+}
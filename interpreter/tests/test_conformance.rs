@@ -0,0 +1,195 @@
+use interpreter::{parse_ascii_program, Error, Machine, DEFAULT_MEMORY_LIMIT};
+
+fn run(code: &str, inputs: &[i64]) -> (Machine, Vec<i64>) {
+    let mut machine = Machine::from_ascii_program(code);
+    for &input in inputs {
+        machine.feed(input);
+    }
+    let outputs = machine.eval_multi();
+    (machine, outputs)
+}
+
+fn outputs(code: &str, inputs: &[i64]) -> Vec<i64> {
+    run(code, inputs).1
+}
+
+fn memory(code: &str) -> Vec<i64> {
+    run(code, &[]).0.mem().to_vec()
+}
+
+fn error(code: &str) -> Error {
+    Machine::from_ascii_program(code)
+        .try_eval_multi()
+        .unwrap_err()
+}
+
+#[test]
+fn test_day2_examples() {
+    assert_eq!(
+        memory("1,9,10,3,2,3,11,0,99,30,40,50"),
+        parse_ascii_program("3500,9,10,70,2,3,11,0,99,30,40,50")
+    );
+    assert_eq!(memory("1,0,0,0,99"), vec![2, 0, 0, 0, 99]);
+    assert_eq!(memory("2,3,0,3,99"), vec![2, 3, 0, 6, 99]);
+    assert_eq!(memory("2,4,4,5,99,0"), vec![2, 4, 4, 5, 99, 9801]);
+    assert_eq!(
+        memory("1,1,1,4,99,5,6,0,99"),
+        vec![30, 1, 1, 4, 2, 5, 6, 0, 99]
+    );
+}
+
+#[test]
+fn test_add_and_mul_modes() {
+    // position, immediate and relative sources with position and
+    // relative destinations
+    assert_eq!(memory("1,5,6,7,99,20,22,0")[7], 42);
+    assert_eq!(memory("1101,20,22,5,99,0")[5], 42);
+    assert_eq!(memory("1001,5,22,5,99,20")[5], 42);
+    assert_eq!(memory("109,5,22201,2,3,4,99,20,22,0")[9], 42);
+    assert_eq!(memory("1102,6,7,5,99,0")[5], 42);
+    assert_eq!(memory("2,5,6,7,99,6,7,0")[7], 42);
+    assert_eq!(memory("109,3,21202,4,7,4,99,6")[7], 42);
+}
+
+#[test]
+fn test_negative_values() {
+    assert_eq!(memory("1101,100,-1,4,0"), vec![1101, 100, -1, 4, 99]);
+    assert_eq!(memory("1102,-6,7,5,99,0")[5], -42);
+}
+
+#[test]
+fn test_input_and_output_modes() {
+    assert_eq!(outputs("3,0,4,0,99", &[42]), vec![42]);
+    assert_eq!(outputs("104,42,99", &[]), vec![42]);
+    assert_eq!(outputs("109,3,204,0,99", &[]), vec![0]);
+    assert_eq!(outputs("109,20,203,-5,204,-5,99", &[42]), vec![42]);
+}
+
+#[test]
+fn test_jumps() {
+    // jump-if-true and jump-if-false in position and immediate mode
+    let jt_pos = "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9";
+    assert_eq!(outputs(jt_pos, &[0]), vec![0]);
+    assert_eq!(outputs(jt_pos, &[5]), vec![1]);
+    let jt_imm = "3,3,1105,-1,9,1101,0,0,12,4,12,99,1";
+    assert_eq!(outputs(jt_imm, &[0]), vec![0]);
+    assert_eq!(outputs(jt_imm, &[5]), vec![1]);
+    assert_eq!(outputs("1105,1,4,99,104,7,99", &[]), vec![7]);
+    assert_eq!(outputs("1106,0,4,99,104,7,99", &[]), vec![7]);
+    assert_eq!(outputs("1105,0,4,99,104,7,99", &[]), vec![]);
+    assert_eq!(outputs("1106,1,4,99,104,7,99", &[]), vec![]);
+}
+
+#[test]
+fn test_comparisons() {
+    for &(code, input, expected) in &[
+        ("3,9,8,9,10,9,4,9,99,-1,8", 8, 1),
+        ("3,9,8,9,10,9,4,9,99,-1,8", 7, 0),
+        ("3,9,7,9,10,9,4,9,99,-1,8", 7, 1),
+        ("3,9,7,9,10,9,4,9,99,-1,8", 8, 0),
+        ("3,3,1108,-1,8,3,4,3,99", 8, 1),
+        ("3,3,1108,-1,8,3,4,3,99", 9, 0),
+        ("3,3,1107,-1,8,3,4,3,99", 7, 1),
+        ("3,3,1107,-1,8,3,4,3,99", 8, 0),
+    ] {
+        assert_eq!(outputs(code, &[input]), vec![expected], "{}", code);
+    }
+}
+
+#[test]
+fn test_relative_base() {
+    // the relative base accumulates and can go down again
+    assert_eq!(outputs("109,7,109,-2,204,1,99", &[]), vec![99]);
+    // relative writes for every writing instruction
+    assert_eq!(memory("109,10,21101,1,2,0,99")[10], 3);
+    assert_eq!(memory("109,10,21108,2,2,1,99")[11], 1);
+    assert_eq!(memory("109,10,21107,1,2,2,99")[12], 1);
+}
+
+#[test]
+fn test_memory_beyond_program() {
+    assert_eq!(outputs("1101,5,6,1000,4,1000,99", &[]), vec![11]);
+    assert_eq!(outputs("4,1000,99", &[]), vec![0]);
+}
+
+#[test]
+fn test_day9_examples() {
+    let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    assert_eq!(outputs(quine, &[]), parse_ascii_program(quine));
+    let big = outputs("1102,34915192,34915192,7,4,7,99,0", &[]);
+    assert_eq!(big[0].to_string().len(), 16);
+    assert_eq!(
+        outputs("104,1125899906842624,99", &[]),
+        vec![1_125_899_906_842_624]
+    );
+}
+
+#[test]
+fn test_self_modifying_code() {
+    assert_eq!(memory("1002,4,3,4,33"), vec![1002, 4, 3, 4, 99]);
+    // replaces the next instruction and an immediate operand
+    assert_eq!(outputs("1101,90,9,4,104,0,99", &[]), vec![]);
+    assert_eq!(outputs("1101,40,2,5,104,0,99", &[]), vec![42]);
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(error("42"), Error::InvalidOpcode { ip: 0, opcode: 42 });
+    assert_eq!(
+        error("1101,1,1,0,77"),
+        Error::InvalidOpcode { ip: 4, opcode: 77 }
+    );
+    assert_eq!(error("304,0,99"), Error::InvalidMode { ip: 0, mode: 3 });
+    assert_eq!(
+        error("11101,1,1,0,99"),
+        Error::InvalidMode { ip: 0, mode: 1 }
+    );
+    assert_eq!(error("4,-1,99"), Error::InvalidAddress { ip: 0 });
+    assert_eq!(error("109,-5,204,0,99"), Error::InvalidAddress { ip: 2 });
+    // all parameters are resolved before the result is computed
+    assert_eq!(
+        error("1102,72057594037927936,128,-1,99"),
+        Error::InvalidAddress { ip: 0 }
+    );
+    assert_eq!(
+        error("1102,72057594037927936,128,0,99"),
+        Error::Overflow { ip: 0 }
+    );
+}
+
+#[test]
+fn test_memory_limit() {
+    let mut machine = Machine::from_ascii_program("1101,1,1,5000,99");
+    machine.set_memory_limit(4096);
+    assert_eq!(
+        machine.try_eval_multi().unwrap_err(),
+        Error::InvalidAddress { ip: 0 }
+    );
+    assert!(machine.mem().len() < 4096);
+}
+
+#[test]
+fn test_default_memory_limit() {
+    let mut machine = Machine::from_ascii_program("1101,1,1,1000000000000,99");
+    assert_eq!(machine.memory_limit(), DEFAULT_MEMORY_LIMIT);
+    assert_eq!(
+        machine.try_eval_multi().unwrap_err(),
+        Error::InvalidAddress { ip: 0 }
+    );
+    assert_eq!(
+        machine.try_mem_set(usize::MAX, 1),
+        Err(Error::InvalidAddress { ip: 0 })
+    );
+    assert!(machine.mem().len() < 10);
+}
+
+#[test]
+fn test_failed_input_is_kept() {
+    let mut machine = Machine::from_ascii_program("3,-1,99");
+    machine.feed(42);
+    assert_eq!(
+        machine.try_eval_multi().unwrap_err(),
+        Error::InvalidAddress { ip: 0 }
+    );
+    assert_eq!(machine.pending_inputs(), &[42]);
+}
@@ -0,0 +1,152 @@
+use interpreter::{intcode, CrashDump, Error, Event, Machine};
+
+fn crashing() -> Machine {
+    let mut machine = Machine::new(intcode! {
+        in value;
+        add value, #1, value;
+        out value;
+        data 42;
+    value:
+        data 0;
+    });
+    machine.set_history_limit(2);
+    machine.feed(4);
+    machine
+}
+
+#[test]
+fn test_history() {
+    let mut machine = crashing();
+    let err = machine
+        .try_step()
+        .and_then(|_| machine.try_step())
+        .unwrap_err();
+    assert_eq!(err, Error::InvalidOpcode { ip: 8, opcode: 42 });
+
+    let dump = machine.crash_dump(Some(&err));
+    assert_eq!(dump.error(), Some("invalid opcode 42 at ip 8"));
+    assert_eq!(dump.ip(), 8);
+    assert_eq!(dump.instruction_count(), 3);
+    assert_eq!(dump.last_output(), 5);
+    assert_eq!(dump.trace(), &[(6, vec![4, 9]), (8, vec![42])]);
+    assert_eq!(
+        dump.io(),
+        &[
+            Event::Input { step: 0, value: 4 },
+            Event::Output { step: 2, value: 5 },
+        ]
+    );
+}
+
+#[test]
+fn test_no_history() {
+    let mut machine = crashing();
+    machine.set_history_limit(0);
+    let err = machine.try_run_until(|_| false).unwrap_err();
+    let dump = machine.crash_dump(Some(&err));
+    assert!(dump.trace().is_empty());
+    assert!(dump.io().is_empty());
+    assert_eq!(dump.mem(), machine.mem());
+}
+
+#[test]
+fn test_history_limit_change() {
+    let mut machine = crashing();
+    machine.set_history_limit(10);
+    machine.step();
+    machine.set_history_limit(1);
+    assert_eq!(machine.crash_dump(None).trace(), &[(6, vec![4, 9])]);
+    machine.set_history_limit(10);
+    assert_eq!(machine.crash_dump(None).trace().len(), 1);
+}
+
+#[test]
+fn test_text_roundtrip() {
+    let mut machine = crashing();
+    machine.feed(7);
+    let err = machine.try_run_until(|_| false).unwrap_err();
+    let dump = machine.crash_dump(Some(&err));
+    let text = dump.to_string();
+    assert!(text.contains("error: invalid opcode 42 at ip 8\n"));
+    assert!(text.contains("    6: 4,9  # out 9\n"));
+    assert!(text.contains("[disassembly]\n     6: out 9\n>    8: data 42\n     9: jt 0, 0\n"));
+    assert_eq!(CrashDump::parse(&text).unwrap(), dump);
+}
+
+#[test]
+fn test_parse_errors() {
+    let err = CrashDump::parse("ip: 1\nfoo: 2\n").unwrap_err();
+    assert_eq!(err.line(), 2);
+    assert_eq!(err.message(), "unknown field 'foo'");
+    let err = CrashDump::parse("ip: 1\n\n[io]\nin 0\n").unwrap_err();
+    assert_eq!(err.line(), 4);
+    let err = CrashDump::parse("[memory]\n1,2\n3,x\n").unwrap_err();
+    assert_eq!(err.line(), 3);
+    assert_eq!(err.message(), "invalid value 'x'");
+    assert_eq!(CrashDump::parse("[stack]\n").unwrap_err().line(), 1);
+}
+
+#[test]
+fn test_huge_addresses() {
+    let dump = CrashDump::parse(
+        "ip: 18446744073709551615\n[trace]\n18446744073709551615: 104,1\n[memory]\n99\n",
+    )
+    .unwrap();
+    let text = dump.to_string();
+    assert!(text.contains("[disassembly]\n\n[memory]\n99\n"));
+    assert_eq!(CrashDump::parse(&text).unwrap(), dump);
+}
+
+#[test]
+fn test_wide_cells() {
+    let big = 1i128 << 80;
+    let mut machine = Machine::new(&[104, big, 1101, 1, big, 7, 42, 0]);
+    machine.set_history_limit(4);
+    let err = machine.try_run_until(|_| false).unwrap_err();
+    assert_eq!(err, Error::InvalidOpcode { ip: 6, opcode: 42 });
+    let dump = machine.crash_dump(Some(&err));
+    assert_eq!(dump.last_output(), big);
+    assert_eq!(dump.trace()[0], (0, vec![104, big]));
+
+    let text = dump.to_string();
+    assert!(text.contains("    0: 104,1208925819614629174706176  # data 104\n"));
+    assert_eq!(CrashDump::<i128>::parse_as(&text).unwrap(), dump);
+    assert_eq!(dump.to_machine().mem(), machine.mem());
+}
+
+#[test]
+fn test_to_machine() {
+    let mut machine = crashing();
+    machine.feed(7);
+    let err = machine.try_run_until(|_| false).unwrap_err();
+    let mut restored = CrashDump::parse(&machine.crash_dump(Some(&err)).to_string())
+        .unwrap()
+        .to_machine();
+    assert_eq!(restored.ip(), 8);
+    assert_eq!(restored.mem(), machine.mem());
+    assert_eq!(restored.pending_inputs(), &[7]);
+    assert_eq!(restored.instruction_count(), 3);
+    let dump = restored.crash_dump(None);
+    assert_eq!(dump.trace(), &[(6, vec![4, 9]), (8, vec![42])]);
+    assert_eq!(dump.io().len(), 2);
+
+    // skip over the bad cell and continue
+    restored.mem_set(8, 99);
+    restored.run_until(|_| false);
+    assert!(restored.halted());
+}
+
+#[test]
+fn test_instruction_budget() {
+    let mut machine = Machine::new(intcode! {
+    start:
+        jt #1, #start;
+    });
+    machine.set_instruction_budget(10);
+    assert_eq!(machine.try_step(), Err(Error::BudgetExceeded { ip: 0 }));
+    assert_eq!(machine.instruction_count(), 10);
+    assert_eq!(
+        machine.try_step_instruction(),
+        Err(Error::BudgetExceeded { ip: 0 })
+    );
+}
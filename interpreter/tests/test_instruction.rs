@@ -0,0 +1,114 @@
+use interpreter::{
+    decode, disassemble_instruction, encode, Error, Instruction, Operand, ParameterMode,
+};
+
+#[test]
+fn test_decode() {
+    assert_eq!(
+        decode(&[1002, 4, 3, 4], 0),
+        Ok(Instruction::Mul(
+            Operand::position(4),
+            Operand::immediate(3),
+            Operand::position(4)
+        ))
+    );
+    assert_eq!(
+        decode(&[0, 0, 204, -7], 2),
+        Ok(Instruction::Output(Operand::relative(-7)))
+    );
+    assert_eq!(decode(&[99], 0), Ok(Instruction::Halt));
+    // operands past the end of memory read as zero
+    assert_eq!(
+        decode(&[9], 0),
+        Ok(Instruction::AdjustBase(Operand::position(0)))
+    );
+}
+
+#[test]
+fn test_decode_errors() {
+    assert_eq!(
+        decode(&[42], 0),
+        Err(Error::InvalidOpcode { ip: 0, opcode: 42 })
+    );
+    assert_eq!(
+        decode(&[-1], 0),
+        Err(Error::InvalidOpcode { ip: 0, opcode: -1 })
+    );
+    assert_eq!(
+        decode(&[304, 0], 0),
+        Err(Error::InvalidMode { ip: 0, mode: 3 })
+    );
+    // written operands cannot be immediate
+    assert_eq!(
+        decode(&[11101, 1, 1, 0], 0),
+        Err(Error::InvalidMode { ip: 0, mode: 1 })
+    );
+}
+
+#[test]
+fn test_decode_extra_modes() {
+    // mode digits beyond the last operand are ignored like the machine does
+    for &program in &[&[10099][..], &[11004, 0, 99], &[1000001, 0, 0, 0, 99]] {
+        let instruction = decode(program, 0).unwrap();
+        let mut machine = interpreter::Machine::new(program);
+        assert!(machine.try_step_instruction().is_ok());
+        if !machine.halted() {
+            assert_eq!(machine.ip(), instruction.size());
+        }
+    }
+    assert_eq!(decode(&[10099], 0), Ok(Instruction::Halt));
+    assert_eq!(
+        decode(&[11004], 0),
+        Ok(Instruction::Output(Operand::position(0)))
+    );
+}
+
+#[test]
+fn test_encode_roundtrip() {
+    let program = interpreter::parse_ascii_program(include_str!("../../aoc9/input.txt"));
+    let mut addr = 0;
+    // the first instructions of the program up to the first data cell
+    while let Ok(instruction) = decode(&program, addr) {
+        let size = instruction.size();
+        assert_eq!(encode(&[instruction]), &program[addr..addr + size]);
+        addr += size;
+    }
+    assert!(addr > 0);
+}
+
+#[test]
+fn test_display() {
+    let instruction = Instruction::Add(
+        Operand::immediate(1),
+        Operand::relative(-2),
+        Operand::position(3),
+    );
+    assert_eq!(instruction.to_string(), "add #1, @-2, 3");
+    assert_eq!(instruction.opcode(), 1);
+    assert_eq!(instruction.write_operand(), Some(2));
+    assert_eq!(Instruction::Halt.to_string(), "hlt");
+    assert_eq!(
+        encode(&[instruction, Instruction::Halt]),
+        vec![2101, 1, -2, 3, 99]
+    );
+}
+
+#[test]
+fn test_parameter_modes() {
+    for digit in 0..3 {
+        assert_eq!(ParameterMode::from_digit(digit).unwrap().digit(), digit);
+    }
+    assert_eq!(ParameterMode::from_digit(3), None);
+}
+
+#[test]
+fn test_disassembler_uses_decode() {
+    assert_eq!(
+        disassemble_instruction(&[21101, 1, 2, 3], 0),
+        ("add #1, #2, @3".to_string(), 4)
+    );
+    assert_eq!(
+        disassemble_instruction(&[11101, 1, 2, 3], 0),
+        ("data 11101".to_string(), 1)
+    );
+}
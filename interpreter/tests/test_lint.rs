@@ -0,0 +1,251 @@
+use interpreter::{
+    assemble, compile, format_assembly, lint, lint_assembly, parse_ascii_program, LintKind,
+};
+
+fn messages(source: &str) -> Vec<String> {
+    lint_assembly(source)
+        .unwrap()
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+}
+
+#[test]
+fn test_clean() {
+    let source = "
+        loop:   in value
+                jf value, #end
+                out value
+                jt #1, #loop
+        end:    hlt
+        value:  data 0
+    ";
+    assert!(lint_assembly(source).unwrap().is_empty());
+    assert!(lint(&assemble(source).unwrap()).is_empty());
+}
+
+#[test]
+fn test_immediate_write() {
+    // the assembler happily encodes this
+    let program = [11101, 1, 2, 3, 4, 0, 99];
+    let lints = lint(&program);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].addr(), 0);
+    assert_eq!(lints[0].kind(), &LintKind::ImmediateWrite);
+    assert_eq!(
+        lints[0].to_string(),
+        "addr 0: write to an immediate operand"
+    );
+
+    assert_eq!(
+        messages("in #x\nx: hlt"),
+        vec!["line 1: write to an immediate operand"]
+    );
+}
+
+#[test]
+fn test_invalid_jump_target() {
+    // into the operands of the add
+    let program = [1005, 8, 4, 1101, 99, 0, 8, 99, 0];
+    let lints = lint(&program);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].kind(), &LintKind::InvalidJumpTarget { target: 4 });
+    assert_eq!(
+        lints[0].to_string(),
+        "addr 0: jump to 4 which is not an instruction"
+    );
+
+    let lints = lint(&[1105, 1, 100]);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(
+        lints[0].kind(),
+        &LintKind::InvalidJumpTarget { target: 100 }
+    );
+
+    // assembly knows that data is not code, even if it decodes
+    assert_eq!(
+        messages(
+            "
+            jt #1, #stop
+            hlt
+    stop:   data 99
+            "
+        ),
+        vec![
+            "line 2: jump to 4 which is not an instruction",
+            "line 3: unreachable code"
+        ]
+    );
+}
+
+#[test]
+fn test_uninitialized_read() {
+    let source = "
+        in 100
+        out 100
+        out 101
+        hlt
+    ";
+    assert_eq!(
+        messages(source),
+        vec!["line 4: read of uninitialized address 101"]
+    );
+    assert_eq!(
+        lint(&assemble(source).unwrap())[0].kind(),
+        &LintKind::UninitializedRead { addr: 101 }
+    );
+
+    // relative mode could write anywhere
+    assert!(messages("arb #100\nin @1\nout 101\nhlt").is_empty());
+}
+
+#[test]
+fn test_unreachable_code() {
+    let source = "
+        jt #1, #end
+        out #1
+        out #2
+end:    hlt
+        out #3
+        jf #1, #end
+        out #4
+    ";
+    assert_eq!(
+        messages(source),
+        vec!["line 3: unreachable code", "line 6: unreachable code"]
+    );
+    // raw programs do not know where code and data is
+    assert!(lint(&assemble(source).unwrap()).is_empty());
+}
+
+#[test]
+fn test_computed_jumps() {
+    // routines return through a cell holding a label that is used as value
+    let source = "
+        add #back, #0, ret
+        jt #1, #routine
+back:   hlt
+routine:
+        out #1
+        jt #1, ret
+        out #2
+ret:    data 0
+    ";
+    assert_eq!(messages(source), vec!["line 8: unreachable code"]);
+}
+
+#[test]
+fn test_unused_labels() {
+    let source = "
+start:  out value
+unused: hlt
+value:  data 1
+        global exported
+exported:
+        data 2
+    ";
+    assert_eq!(
+        messages(source),
+        vec![
+            "line 2: unused label 'start'",
+            "line 3: unused label 'unused'"
+        ]
+    );
+}
+
+#[test]
+fn test_compiled_programs_are_clean() {
+    let program = compile(
+        "
+        fn square(x) {
+            return x * x;
+        }
+
+        fn main() {
+            output(square(input()));
+        }
+        ",
+    )
+    .unwrap();
+    assert!(lint(&program).is_empty());
+}
+
+#[test]
+fn test_puzzle_inputs_are_clean() {
+    for source in &[
+        include_str!("../../aoc2/input.txt"),
+        include_str!("../../aoc5/input.txt"),
+        include_str!("../../aoc9/input.txt"),
+        include_str!("../../aoc11/input.txt"),
+        include_str!("../../aoc13/input.txt"),
+    ] {
+        assert_eq!(lint(&parse_ascii_program(source)), vec![]);
+    }
+    for source in &[
+        include_str!("library/multiply.asm"),
+        include_str!("library/print_number.asm"),
+        include_str!("library/read_line.asm"),
+    ] {
+        assert_eq!(lint_assembly(source).unwrap(), vec![]);
+    }
+}
+
+#[test]
+fn test_format() {
+    let source = "
+; doubles numbers
+
+
+start:   IN  value ;read
+  JF value,#end
+  mul value ,#2, value
+        out value   ;   write it
+        jt #1,#start
+   ; stop here
+a_very_long_label: end: hlt
+value:data 0, end-1,end+2";
+    let formatted = format_assembly(source).unwrap();
+    assert_eq!(
+        formatted,
+        "\
+; doubles numbers
+
+start:  in value                ;read
+        jf value, #end
+        mul value, #2, value
+        out value               ;   write it
+        jt #1, #start
+        ; stop here
+a_very_long_label:
+end:    hlt
+value:  data 0, end-1, end+2
+"
+    );
+    assert_eq!(format_assembly(&formatted).unwrap(), formatted);
+    assert_eq!(assemble(&formatted), assemble(source));
+
+    let long = "a_long_label: add #1000000, #2000000, result ; comment\nresult: data 0\n";
+    assert_eq!(
+        format_assembly(long).unwrap(),
+        "a_long_label:\n        add #1000000, #2000000, result  ; comment\nresult: data 0\n"
+    );
+    assert_eq!(format_assembly("foo 1").unwrap_err().line(), 1);
+    for source in &[
+        include_str!("library/multiply.asm"),
+        include_str!("library/print_number.asm"),
+        include_str!("library/read_line.asm"),
+    ] {
+        assert_eq!(format_assembly(source).unwrap(), *source);
+    }
+    assert!(format_assembly("jt #1, #elsewhere").is_ok());
+}
+
+#[test]
+fn test_written_jump_targets_terminate() {
+    // the write makes the jump target unknown which hides the write again
+    assert!(lint(&[105, 1, 4, 99, 5, 1101, 0, 0, 4, 99]).is_empty());
+    assert_eq!(
+        messages("jt #1, target\nhlt\ntarget: data next\nnext: add #0, #0, target\nhlt\n"),
+        vec!["line 2: unreachable code"]
+    );
+}
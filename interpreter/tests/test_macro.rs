@@ -0,0 +1,93 @@
+use interpreter::{intcode, parse_ascii_program, Machine};
+
+fn eval(program: &[i64], input: i64) -> Vec<i64> {
+    let mut machine = Machine::new(program);
+    machine.feed(input);
+    machine.eval_multi()
+}
+
+#[test]
+fn test_equals_position_mode() {
+    let program = intcode! {
+        in value;
+        eq value, eight, value;
+        out value;
+        hlt;
+    value:
+        data -1;
+    eight:
+        data 8;
+    };
+    assert_eq!(
+        program,
+        &parse_ascii_program("3,9,8,9,10,9,4,9,99,-1,8")[..]
+    );
+    assert_eq!(eval(program, 8), vec![1]);
+    assert_eq!(eval(program, 42), vec![0]);
+}
+
+#[test]
+fn test_less_than_immediate_mode() {
+    let program = intcode! {
+        in value + 1;
+    value:
+        lt #-1, #8, value + 1;
+        out value + 1;
+        hlt;
+    };
+    assert_eq!(program, &parse_ascii_program("3,3,1107,-1,8,3,4,3,99")[..]);
+    assert_eq!(eval(program, 7), vec![1]);
+    assert_eq!(eval(program, 8), vec![0]);
+}
+
+#[test]
+fn test_jumps_with_labels() {
+    let program = intcode! {
+        in input;
+        jt input, #nonzero;
+        out zero;
+        hlt;
+    nonzero:
+        out one;
+        hlt;
+    input:
+        data 0;
+    zero:
+        data 0;
+    one:
+        data 1;
+    };
+    assert_eq!(eval(program, 0), vec![0]);
+    assert_eq!(eval(program, 42), vec![1]);
+}
+
+#[test]
+fn test_relative_mode_and_label_arithmetic() {
+    let program = intcode! {
+        arb #table;
+        out @0;
+        out @1;
+        out table + 2;
+        hlt;
+    table:
+        data 10, 20, 30;
+    };
+    assert_eq!(program[0], 109);
+    assert_eq!(program[2], 204);
+    assert_eq!(Machine::new(program).eval_multi(), vec![10, 20, 30]);
+}
+
+#[test]
+fn test_countdown_loop() {
+    let program = intcode! {
+        in counter;
+    start:
+        out counter;
+        add counter, #-1, counter;
+        jt counter, #start;
+        hlt;
+    counter:
+        data 0;
+    };
+    assert_eq!(eval(program, 3), vec![3, 2, 1]);
+}
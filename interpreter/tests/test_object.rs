@@ -0,0 +1,230 @@
+use interpreter::{assemble, link, LinkError, Machine, Object, Relocation};
+
+fn library() -> Vec<Object> {
+    [
+        include_str!("library/multiply.asm"),
+        include_str!("library/print_number.asm"),
+        include_str!("library/read_line.asm"),
+    ]
+    .iter()
+    .map(|source| Object::assemble(source).unwrap())
+    .collect()
+}
+
+/// Links a program against the library and runs it.
+fn run(source: &str, input: &str) -> String {
+    let mut objects = vec![Object::assemble(source).unwrap()];
+    objects.extend(library());
+    let mut machine = Machine::new(&link(&objects).unwrap());
+    for byte in input.bytes() {
+        machine.feed(i64::from(byte));
+    }
+    let outcome = machine.run_until(|_| false);
+    assert!(outcome.is_halted());
+    outcome.outputs().iter().map(|&x| x as u8 as char).collect()
+}
+
+#[test]
+fn test_assemble_object() {
+    let object = Object::assemble(
+        "
+                global double
+        double: mul value, #2, value
+                jt #1, double_ret
+        value:  data 0
+        ",
+    )
+    .unwrap();
+    assert_eq!(object.code(), &[1002, 7, 2, 7, 105, 1, 0, 0]);
+    assert_eq!(object.symbols().get("double"), Some(&0));
+    assert_eq!(object.symbols().len(), 1);
+    assert_eq!(
+        object.relocations(),
+        &[
+            Relocation::Local { offset: 1 },
+            Relocation::Local { offset: 3 },
+            Relocation::External {
+                offset: 6,
+                name: "double_ret".into()
+            },
+        ]
+    );
+    assert_eq!(object.undefined_symbols(), vec!["double_ret"]);
+
+    let err = Object::assemble("global missing\nhlt").unwrap_err();
+    assert_eq!(err.to_string(), "line 1: undefined label 'missing'");
+    let err = Object::assemble("a: global #a").unwrap_err();
+    assert_eq!(err.to_string(), "line 1: global expects label names");
+}
+
+#[test]
+fn test_link_matches_assemble() {
+    let source = "
+        start:  in value
+                jf value, #end
+                add value, table+1, value
+                out value
+                jt #1, #start
+        end:    hlt
+        table:  data 5, 7, end
+        value:  data 0
+    ";
+    let object = Object::assemble(source).unwrap();
+    assert_eq!(link(&[object]).unwrap(), assemble(source).unwrap());
+}
+
+#[test]
+fn test_link_relocates() {
+    let main = Object::assemble(
+        "
+        add #back, #0, double_ret
+        jt #1, #double
+back:   out double_value
+        hlt
+        ",
+    )
+    .unwrap();
+    let double = Object::assemble(
+        "
+                global double, double_value, double_ret
+        double: mul double_value, #2, double_value
+                jt #1, double_ret
+        double_value: data 21
+        double_ret:   data 0
+        ",
+    )
+    .unwrap();
+    let program = link(&[main.clone(), double.clone()]).unwrap();
+    assert_eq!(program.len(), main.code().len() + double.code().len());
+    assert_eq!(Machine::new(&program).eval_multi(), vec![42]);
+
+    let objects = [main, double.clone(), double];
+    let err = link(&objects[..1]).unwrap_err();
+    assert_eq!(
+        err,
+        LinkError::UndefinedSymbol {
+            name: "double_ret".into()
+        }
+    );
+    assert_eq!(err.to_string(), "undefined symbol 'double_ret'");
+    let err = link(&objects).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate symbol 'double'");
+}
+
+#[test]
+fn test_overflow() {
+    let source = "data 0\nx: data x+9223372036854775807";
+    assert_eq!(
+        Object::assemble(source).unwrap_err().message(),
+        "offset from label 'x' is out of range"
+    );
+    assert!(assemble(source).is_err());
+
+    let first = Object::parse("[code]\n99\n").unwrap();
+    let second = Object::parse("[relocations]\n0\n\n[code]\n9223372036854775807\n").unwrap();
+    let err = link(&[first, second]).unwrap_err();
+    assert_eq!(err, LinkError::Overflow { addr: 1 });
+    assert_eq!(err.to_string(), "relocating address 1 overflows");
+}
+
+#[test]
+fn test_text_roundtrip() {
+    for object in library() {
+        let text = object.to_string();
+        assert!(text.starts_with("# intcode object\n[symbols]\n"));
+        assert_eq!(Object::parse(&text).unwrap(), object);
+    }
+}
+
+#[test]
+fn test_parse_errors() {
+    let err = Object::parse("[symbols]\nfoo 1\n\n[code]\n1,2\n[stack]\n").unwrap_err();
+    assert_eq!(err.line(), 6);
+    assert_eq!(err.message(), "unknown section 'stack'");
+    let err = Object::parse("[symbols]\nfoo\n").unwrap_err();
+    assert_eq!(err.message(), "expected name and offset");
+    let err = Object::parse("[symbols]\nfoo 1\nfoo 2\n").unwrap_err();
+    assert_eq!(err.to_string(), "line 3: duplicate symbol 'foo'");
+    let err = Object::parse("[relocations]\n2\n[code]\n1,2\n").unwrap_err();
+    assert_eq!(err.to_string(), "line 2: offset 2 is outside the code");
+    let err = Object::parse("[code]\n1,x\n").unwrap_err();
+    assert_eq!(err.message(), "invalid value 'x'");
+    assert_eq!(Object::parse("1,2\n").unwrap_err().line(), 1);
+
+    // symbols can mark the end of the code
+    let object = Object::parse("[symbols]\nend 2\n[code]\n1,2\n").unwrap();
+    assert_eq!(object.symbols().get("end"), Some(&2));
+}
+
+#[test]
+fn test_library_multiply() {
+    let source = "
+        add #6, #0, multiply_a
+        add #-7, #0, multiply_b
+        add #back, #0, multiply_ret
+        jt #1, #multiply
+back:   add multiply_result, #0, print_number_value
+        add #done, #0, print_number_ret
+        jt #1, #print_number
+done:   hlt
+    ";
+    assert_eq!(run(source, ""), "-42");
+}
+
+#[test]
+fn test_library_print_number() {
+    let source = "
+        in count
+again:  in print_number_value
+        add #next, #0, print_number_ret
+        jt #1, #print_number
+next:   out #44
+        add count, #-1, count
+        jt count, #again
+        hlt
+count:  data 0
+    ";
+    let mut objects = vec![Object::assemble(source).unwrap()];
+    objects.extend(library());
+    let program = link(&objects).unwrap();
+    let values = [0, 7, 10, 42, -123, 1000000, i64::MAX, -i64::MAX];
+    let mut machine = Machine::new(&program);
+    machine.feed(values.len() as i64);
+    for &value in &values {
+        machine.feed(value);
+    }
+    let output: String = machine
+        .eval_multi()
+        .into_iter()
+        .map(|x| x as u8 as char)
+        .collect();
+    let expected: Vec<_> = values.iter().map(|x| x.to_string()).collect();
+    assert_eq!(output, expected.join(",") + ",");
+}
+
+#[test]
+fn test_library_read_line() {
+    // echoes lines reversed together with their length
+    let source = "
+again:  add #buffer, #0, read_line_buffer
+        add #back, #0, read_line_ret
+        jt #1, #read_line
+back:   jf read_line_length, #done
+        add #buffer-1, read_line_length, load+1
+load:   out 0
+        add load+1, #-1, load+1
+        lt load+1, #buffer, stop
+        jf stop, #load
+        out #32
+        add read_line_length, #0, print_number_value
+        add #newline, #0, print_number_ret
+        jt #1, #print_number
+newline:
+        out #10
+        jt #1, #again
+done:   hlt
+stop:   data 0
+buffer: data 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
+    ";
+    assert_eq!(run(source, "hello\nintcode\n\n"), "olleh 5\nedoctni 7\n");
+}
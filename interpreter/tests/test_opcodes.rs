@@ -0,0 +1,88 @@
+use interpreter::{Control, Error, Machine, Opcode, DAY2_OPCODES};
+
+#[test]
+fn test_restrict_opcodes() {
+    let mut machine = Machine::from_ascii_program("1,0,0,0,99");
+    machine.restrict_opcodes(DAY2_OPCODES);
+    machine.eval();
+    assert_eq!(machine.mem(), &[2, 0, 0, 0, 99]);
+
+    let mut machine = Machine::from_ascii_program("3,0,4,0,99");
+    machine.restrict_opcodes(DAY2_OPCODES);
+    machine.feed(42);
+    assert_eq!(
+        machine.try_eval(),
+        Err(Error::InvalidOpcode { ip: 0, opcode: 3 })
+    );
+}
+
+#[test]
+fn test_custom_opcode() {
+    // 10 = modulo: a % b -> c
+    let mut machine = Machine::from_ascii_program("1110,17,5,7,4,7,99,0");
+    machine.register_opcode(Opcode::new(10, 3, &[3], |_, args| {
+        args[2] = args[0] % args[1];
+        Ok(Control::Next)
+    }));
+    assert_eq!(machine.eval(), 2);
+}
+
+#[test]
+fn test_custom_opcode_relative_write() {
+    let mut machine = Machine::from_ascii_program("109,10,20110,3,4,0,204,0,99");
+    machine.register_opcode(Opcode::new(10, 3, &[3], |_, args| {
+        args[2] = args[0] * 10 + args[1];
+        Ok(Control::Next)
+    }));
+    assert_eq!(machine.eval(), 34);
+}
+
+#[test]
+fn test_custom_opcode_control() {
+    // 20 = output twice the operand, 21 = halt
+    let mut machine = Machine::from_ascii_program("120,21,120,5,21");
+    machine.register_opcode(Opcode::new(20, 1, &[], |_, args| {
+        Ok(Control::Output(args[0] * 2))
+    }));
+    machine.register_opcode(Opcode::new(21, 0, &[], |_, _| Ok(Control::Halt)));
+    assert_eq!(machine.eval_multi(), vec![42, 10]);
+}
+
+#[test]
+fn test_override_builtin() {
+    // make ADD subtract instead
+    let mut machine = Machine::from_ascii_program("1101,10,3,0,4,0,99");
+    machine.register_opcode(Opcode::new(1, 3, &[3], |_, args| {
+        args[2] = args[0] - args[1];
+        Ok(Control::Next)
+    }));
+    assert_eq!(machine.eval(), 7);
+}
+
+#[test]
+fn test_register_extends_restriction() {
+    let mut machine = Machine::from_ascii_program("121,99");
+    machine.restrict_opcodes(DAY2_OPCODES);
+    machine.register_opcode(Opcode::new(21, 0, &[], |_, _| Ok(Control::Next)));
+    machine.eval();
+    assert_eq!(machine.ip(), 1);
+}
+
+#[test]
+fn test_custom_opcode_many_operands() {
+    // sums the cells 1 to 18 which hold their own addresses
+    let mut code: Vec<i64> = (0..=18).collect();
+    code[0] = 10;
+    code.push(99);
+    let mut machine = Machine::new(&code);
+    machine.register_opcode(Opcode::new(10, 18, &[], |_, args| {
+        Ok(Control::Output(args.iter().sum()))
+    }));
+    assert_eq!(machine.eval_multi(), vec![171]);
+}
+
+#[test]
+#[should_panic(expected = "opcodes take at most 18 operands")]
+fn test_custom_opcode_too_many_operands() {
+    Opcode::<i64>::new(10, 19, &[], |_, _| Ok(Control::Next));
+}
@@ -0,0 +1,283 @@
+use interpreter::{encode, intcode, optimize, Instruction, Machine, Operand};
+use quickcheck::{quickcheck, Arbitrary, Gen};
+
+const INSTRUCTION_LIMIT: u64 = 2000;
+
+/// Runs a program and returns its outputs and whether it halted cleanly.
+///
+/// Returns `None` if it did not finish within the instruction limit.
+fn run(program: &[i64], inputs: &[i64]) -> Option<(Vec<i64>, bool, u64)> {
+    let mut machine = Machine::new(program);
+    for &input in inputs {
+        machine.feed(input);
+    }
+    let mut outputs = vec![];
+    while machine.instruction_count() < INSTRUCTION_LIMIT {
+        match machine.try_step_instruction() {
+            Ok(true) if machine.halted() => {
+                return Some((outputs, true, machine.instruction_count()))
+            }
+            Ok(true) => outputs.push(machine.last_output()),
+            Ok(false) => {}
+            Err(_) => return Some((outputs, false, machine.instruction_count())),
+        }
+    }
+    None
+}
+
+#[test]
+fn test_constant_folding() {
+    let program = intcode! {
+        add a, #2, x;
+        mul x, #3, x;
+        out x;
+        hlt;
+    a:
+        data 5;
+    x:
+        data 0;
+    };
+    assert_eq!(
+        optimize(program),
+        intcode! {
+            add #7, #0, x;
+            mul x, #3, x;
+            out x;
+            hlt;
+        x:
+            data 0;
+        }
+    );
+}
+
+#[test]
+fn test_known_conditions() {
+    let program = intcode! {
+        lt a, #10, flag;
+        jf flag, #skip;
+        out #1;
+    skip:
+        jt a, #end;
+        out #2;
+    end:
+        hlt;
+    a:
+        data 5;
+    flag:
+        data 0;
+    };
+    // the first jump depends on a written cell and stays
+    assert_eq!(
+        optimize(program),
+        intcode! {
+            add #1, #0, flag;
+            jf flag, #skip;
+            out #1;
+        skip:
+            hlt;
+        flag:
+            data 0;
+        }
+    );
+}
+
+#[test]
+fn test_jump_threading() {
+    let program = intcode! {
+        in x;
+        jt x, #first;
+        out #0;
+        hlt;
+    first:
+        jt #1, #second;
+    second:
+        jf #0, #third;
+    third:
+        out #1;
+        hlt;
+    x:
+        data 0;
+    };
+    let optimized = optimize(program);
+    assert_eq!(
+        optimized,
+        intcode! {
+            in x;
+            jt x, #third;
+            out #0;
+            hlt;
+        third:
+            out #1;
+            hlt;
+        x:
+            data 0;
+        }
+    );
+    for input in 0..2 {
+        assert_eq!(run(&optimized, &[input]).unwrap().0, vec![input]);
+    }
+}
+
+#[test]
+fn test_entry_point_stays() {
+    let program = intcode! {
+        jf #1, #0;
+        out #1;
+        hlt;
+    };
+    let optimized = optimize(program);
+    assert_eq!(optimized[0], 1106);
+    assert_eq!(run(&optimized, &[]).unwrap().0, vec![1]);
+}
+
+#[test]
+fn test_unsafe_programs_unchanged() {
+    // reads its own code
+    let program = intcode! {
+        add 0, #1, x;
+        out x;
+        hlt;
+    x:
+        data 0;
+    };
+    assert_eq!(optimize(program), program);
+
+    // modifies its own code
+    let program = intcode! {
+        add #4, #0, patch;
+    patch:
+        out #0;
+        hlt;
+    };
+    assert_eq!(optimize(program), program);
+
+    // uses relative mode
+    let program = intcode! {
+        arb #10;
+        out @0;
+        hlt;
+    };
+    assert_eq!(optimize(program), program);
+
+    // jumps to a computed address
+    let program = intcode! {
+        in x;
+        jt #1, x;
+        hlt;
+    x:
+        data 0;
+    };
+    assert_eq!(optimize(program), program);
+}
+
+#[test]
+fn test_differential_countdown() {
+    let program = intcode! {
+        in n;
+    again:
+        out n;
+        add n, minus_one, n;
+        eq n, zero, done;
+        jf done, #again;
+        jt #1, #finish;
+        out #99;
+    finish:
+        hlt;
+    n:
+        data 0;
+    done:
+        data 0;
+    minus_one:
+        data -1;
+    zero:
+        data 0;
+    };
+    let optimized = optimize(program);
+    assert!(optimized.len() < program.len());
+    for n in 1..20 {
+        let (outputs, halted, count) = run(program, &[n]).unwrap();
+        let (optimized_outputs, optimized_halted, optimized_count) = run(&optimized, &[n]).unwrap();
+        assert_eq!(optimized_outputs, outputs);
+        assert_eq!(optimized_halted, halted);
+        assert!(optimized_count < count);
+    }
+}
+
+/// A random program without self modification and relative mode.
+#[derive(Debug, Clone)]
+struct StaticProgram(Vec<i64>);
+
+const DATA_CELLS: i64 = 6;
+
+impl Arbitrary for StaticProgram {
+    fn arbitrary(g: &mut Gen) -> StaticProgram {
+        let count = usize::arbitrary(g) % 12 + 1;
+        let kinds: Vec<u8> = (0..count)
+            .map(|_| *g.choose(&[1, 2, 3, 4, 5, 6, 7, 8, 99]).unwrap())
+            .collect();
+        let sizes: Vec<i64> = kinds
+            .iter()
+            .map(|kind| match kind {
+                1 | 2 | 7 | 8 => 4,
+                5 | 6 => 3,
+                3 | 4 => 2,
+                _ => 1,
+            })
+            .collect();
+        let starts: Vec<i64> = sizes
+            .iter()
+            .scan(0, |addr, size| {
+                *addr += size;
+                Some(*addr - size)
+            })
+            .collect();
+        let data = starts.last().unwrap() + sizes.last().unwrap() + 1;
+
+        let value = |g: &mut Gen| {
+            if bool::arbitrary(g) {
+                Operand::immediate(i64::from(i8::arbitrary(g) % 4))
+            } else {
+                Operand::position(data + i64::from(u8::arbitrary(g)) % DATA_CELLS)
+            }
+        };
+        let cell = |g: &mut Gen| Operand::position(data + i64::from(u8::arbitrary(g)) % DATA_CELLS);
+        let target = |g: &mut Gen| Operand::immediate(*g.choose(&starts).unwrap());
+
+        let mut instructions: Vec<_> = kinds
+            .iter()
+            .map(|kind| match kind {
+                1 => Instruction::Add(value(g), value(g), cell(g)),
+                2 => Instruction::Mul(value(g), value(g), cell(g)),
+                3 => Instruction::Input(cell(g)),
+                4 => Instruction::Output(value(g)),
+                5 => Instruction::JumpIfTrue(value(g), target(g)),
+                6 => Instruction::JumpIfFalse(value(g), target(g)),
+                7 => Instruction::LessThan(value(g), value(g), cell(g)),
+                8 => Instruction::Equals(value(g), value(g), cell(g)),
+                _ => Instruction::Halt,
+            })
+            .collect();
+        instructions.push(Instruction::Halt);
+        let mut program = encode(&instructions);
+        program.extend((0..DATA_CELLS).map(|_| i64::from(i8::arbitrary(g) % 8)));
+        StaticProgram(program)
+    }
+}
+
+quickcheck! {
+    fn prop_optimize_preserves_outputs(program: StaticProgram, inputs: Vec<i8>) -> bool {
+        let inputs: Vec<i64> = inputs.into_iter().map(i64::from).collect();
+        // the optimized program never runs more instructions so every run
+        // finishing within the limit has to finish there as well
+        let expected = match run(&program.0, &inputs) {
+            Some(expected) => expected,
+            None => return true,
+        };
+        match run(&optimize(&program.0), &inputs) {
+            Some((outputs, halted, count)) => {
+                outputs == expected.0 && halted == expected.1 && count <= expected.2
+            }
+            None => false,
+        }
+    }
+}
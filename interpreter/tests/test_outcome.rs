@@ -0,0 +1,68 @@
+use interpreter::{intcode, Machine, Outcome};
+
+#[test]
+fn test_run_for_outputs() {
+    let mut machine = Machine::new(intcode! {
+        out #1;
+        out #2;
+        out #3;
+        hlt;
+    });
+    assert_eq!(machine.run_for_outputs(2), Outcome::Reached(vec![1, 2]));
+    assert_eq!(machine.run_for_outputs(2), Outcome::Halted(vec![3]));
+    assert!(machine.halted());
+    assert_eq!(machine.run_for_outputs(1), Outcome::Halted(vec![]));
+}
+
+#[test]
+fn test_run_until_input_needed() {
+    let mut machine = Machine::new(intcode! {
+    start:
+        out #7;
+        in value;
+        out value;
+        jt #1, #start;
+    value:
+        data 0;
+    });
+    let outcome = machine.run_until_input_needed();
+    assert_eq!(outcome, Outcome::Reached(vec![7]));
+    assert_eq!(machine.ip(), 2);
+
+    machine.feed(42);
+    assert_eq!(machine.run_until_input_needed().into_outputs(), vec![42, 7]);
+    assert_eq!(machine.ip(), 2);
+}
+
+#[test]
+fn test_run_until_ip() {
+    let mut machine = Machine::new(intcode! {
+        out #1;
+    target:
+        out #2;
+        hlt;
+    });
+    assert_eq!(machine.run_until_ip(2), Outcome::Reached(vec![1]));
+    assert_eq!(machine.run_until_ip(2), Outcome::Reached(vec![]));
+    assert_eq!(machine.run_until_ip(0), Outcome::Halted(vec![2]));
+}
+
+#[test]
+fn test_run_until() {
+    let mut machine = Machine::new(intcode! {
+    start:
+        add counter, #1, counter;
+        jt #1, #start;
+    counter:
+        data 0;
+    });
+    let outcome = machine.run_until(|m| m.mem_get(7) == 5);
+    assert!(outcome.is_reached());
+    assert_eq!(machine.mem_get(7), 5);
+}
+
+#[test]
+fn test_run_errors() {
+    let mut machine = Machine::new(&[42i64]);
+    assert_eq!(machine.try_run_for_outputs(1).unwrap_err().ip(), 0);
+}
@@ -0,0 +1,107 @@
+use interpreter::{Machine, Patch, PatchError};
+
+fn patched(program: &[i64], patch: &str) -> Result<Vec<i64>, PatchError> {
+    let mut machine = Machine::new(program);
+    Patch::parse(patch)?.apply(&mut machine)?;
+    Ok(machine.mem().to_vec())
+}
+
+#[test]
+fn test_set_and_fill() {
+    assert_eq!(
+        patched(&[1, 2, 3, 4, 5], "0 = 9\n2 = 7, 8 # comment\n").unwrap(),
+        vec![9, 2, 7, 8, 5]
+    );
+    assert_eq!(
+        patched(&[1, 2, 3, 4, 5], "1..4 = 0").unwrap(),
+        vec![1, 0, 0, 0, 5]
+    );
+    assert_eq!(patched(&[1], "3 = 4").unwrap(), vec![1, 0, 0, 4]);
+}
+
+#[test]
+fn test_verified_replace() {
+    assert_eq!(
+        patched(&[1, 0, 0, 3], "1: 0, 0 -> 12, 2").unwrap(),
+        vec![1, 12, 2, 3]
+    );
+    assert_eq!(
+        patched(&[1, 0, 5, 3], "1: 0, 0 -> 12, 2"),
+        Err(PatchError::Mismatch {
+            line: 1,
+            addr: 2,
+            expected: 0,
+            actual: 5
+        })
+    );
+}
+
+#[test]
+fn test_find_replace() {
+    assert_eq!(
+        patched(&[0, 3, 0, 3, 0, 3], "find 3, 0 -> 3, 3").unwrap(),
+        vec![0, 3, 3, 3, 3, 3]
+    );
+    assert_eq!(
+        patched(&[1, 2, 3], "# nothing\nfind 4 -> 5"),
+        Err(PatchError::NotFound { line: 2 })
+    );
+}
+
+#[test]
+fn test_atomic() {
+    let mut machine = Machine::new(&[1, 2, 3]);
+    let patch = Patch::parse("0 = 5\n1: 7 -> 8").unwrap();
+    let err = patch.apply(&mut machine).unwrap_err();
+    assert_eq!(err.to_string(), "line 2: expected 7 at address 1, found 2");
+    assert_eq!(machine.mem(), &[1, 2, 3]);
+}
+
+#[test]
+fn test_later_edits_see_earlier_ones() {
+    assert_eq!(
+        patched(&[1, 2, 3], "0 = 5\n0: 5 -> 6").unwrap(),
+        vec![6, 2, 3]
+    );
+}
+
+#[test]
+fn test_syntax_errors() {
+    for &(source, message) in &[
+        ("0 = x", "line 1: invalid number 'x'"),
+        ("\nhello", "line 2: invalid patch 'hello'"),
+        ("0: 1 2", "line 1: expected '->'"),
+        (
+            "0: 1 -> 2, 3",
+            "line 1: replacement has 2 values but the original 1",
+        ),
+        ("5..3 = 0", "line 1: range ends before it starts"),
+        ("-1 = 0", "line 1: invalid number '-1'"),
+    ] {
+        assert_eq!(Patch::parse(source).unwrap_err().to_string(), message);
+    }
+}
+
+#[test]
+fn test_out_of_range() {
+    let mut machine = Machine::new(&[1, 2, 3]);
+    machine.set_memory_limit(100);
+    for &(patch, line, addr) in &[
+        ("0 = 1\n0..1000000000000 = 0", 2, 100),
+        ("98 = 1, 2, 3", 1, 100),
+        ("18446744073709551615 = 1, 2", 1, usize::MAX),
+        ("150: 0 -> 1", 1, 150),
+    ] {
+        assert_eq!(
+            Patch::parse(patch).unwrap().apply(&mut machine),
+            Err(PatchError::OutOfRange { line, addr })
+        );
+    }
+    assert_eq!(machine.mem(), &[1, 2, 3]);
+    assert_eq!(
+        patched(&[1], "0..1000000000000 = 0")
+            .unwrap_err()
+            .to_string(),
+        "line 1: address 16777216 is beyond the memory limit"
+    );
+}
@@ -0,0 +1,150 @@
+use interpreter::{lockstep, Error, Machine};
+use quickcheck::{quickcheck, Arbitrary, Gen};
+
+const MEMORY_LIMIT: usize = 4096;
+const INSTRUCTION_LIMIT: u64 = 2000;
+
+/// A random program biased towards valid instructions.
+#[derive(Debug, Clone)]
+struct Program(Vec<i64>);
+
+impl Arbitrary for Program {
+    fn arbitrary(g: &mut Gen) -> Program {
+        let len = usize::arbitrary(g) % 64 + 1;
+        let mut cells = Vec::with_capacity(len);
+        for _ in 0..len {
+            let cell = match u8::arbitrary(g) % 8 {
+                0..=2 => {
+                    let opcode = *g.choose(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 99]).unwrap();
+                    let modes = *g.choose(&[0, 1, 2]).unwrap()
+                        + *g.choose(&[0, 10, 20]).unwrap()
+                        + *g.choose(&[0, 100, 200]).unwrap();
+                    opcode + modes * 100
+                }
+                3..=6 => i64::from(i8::arbitrary(g)),
+                _ => i64::arbitrary(g),
+            };
+            cells.push(cell);
+        }
+        Program(cells)
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Program>> {
+        Box::new(self.0.shrink().filter(|x| !x.is_empty()).map(Program))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct State {
+    outputs: Vec<i64>,
+    result: Result<(), Error>,
+    mem: Vec<i64>,
+    ip: usize,
+    relative_base: i64,
+    halted: bool,
+    instruction_count: u64,
+}
+
+fn machine(program: &Program, inputs: &[i64]) -> Machine {
+    let mut machine = Machine::new(&program.0);
+    machine.set_memory_limit(MEMORY_LIMIT);
+    for &input in inputs {
+        machine.feed(input);
+    }
+    machine
+}
+
+fn state(machine: &Machine, outputs: Vec<i64>, result: Result<(), Error>) -> State {
+    State {
+        outputs,
+        result,
+        mem: machine.mem().to_vec(),
+        ip: machine.ip(),
+        relative_base: machine.relative_base(),
+        halted: machine.halted(),
+        instruction_count: machine.instruction_count(),
+    }
+}
+
+/// Runs with the bounded run helper.
+fn run_until_limit(mut machine: Machine) -> State {
+    match machine.try_run_until(|m| m.instruction_count() >= INSTRUCTION_LIMIT) {
+        Ok(outcome) => state(&machine, outcome.into_outputs(), Ok(())),
+        Err(err) => state(&machine, vec![], Err(err)),
+    }
+}
+
+/// Runs one instruction at a time which is the reference.
+fn run_instructions(mut machine: Machine) -> State {
+    let mut outputs = vec![];
+    while !machine.halted() && machine.instruction_count() < INSTRUCTION_LIMIT {
+        match machine.try_step_instruction() {
+            Ok(true) if !machine.halted() => outputs.push(machine.last_output()),
+            Ok(_) => {}
+            Err(err) => return state(&machine, vec![], Err(err)),
+        }
+    }
+    state(&machine, outputs, Ok(()))
+}
+
+/// Runs the bounded run helper one instruction at a time.
+fn run_single_steps(mut machine: Machine) -> State {
+    let mut outputs = vec![];
+    while !machine.halted() && machine.instruction_count() < INSTRUCTION_LIMIT {
+        let count = machine.instruction_count();
+        match machine.try_run_until(|m| m.instruction_count() > count) {
+            Ok(outcome) => outputs.extend(outcome.into_outputs()),
+            Err(err) => return state(&machine, vec![], Err(err)),
+        }
+    }
+    state(&machine, outputs, Ok(()))
+}
+
+quickcheck! {
+    fn prop_no_panics(program: Program, inputs: Vec<i64>) -> bool {
+        let mut machine = machine(&program, &inputs);
+        let _ = machine.try_run_until(|m| m.instruction_count() >= INSTRUCTION_LIMIT);
+        true
+    }
+
+    fn prop_clones_are_deterministic(program: Program, inputs: Vec<i64>, split: u16) -> bool {
+        let mut machine = machine(&program, &inputs);
+        let split = u64::from(split) % INSTRUCTION_LIMIT;
+        if machine.try_run_until(|m| m.instruction_count() >= split).is_err() {
+            return true;
+        }
+        let snapshot = machine.clone();
+        run_until_limit(machine) == run_until_limit(snapshot)
+    }
+
+    fn prop_execution_paths_agree(program: Program, inputs: Vec<i64>) -> bool {
+        let reference = run_instructions(machine(&program, &inputs));
+        run_until_limit(machine(&program, &inputs)) == reference
+            && run_single_steps(machine(&program, &inputs)) == reference
+    }
+
+    fn prop_step_matches_instructions(program: Program, inputs: Vec<i64>) -> bool {
+        // step only stops on output or halt so only programs that are
+        // known to halt within the limit are compared
+        let reference = run_instructions(machine(&program, &inputs));
+        if !reference.halted || reference.result.is_err() {
+            return true;
+        }
+        let mut machine = machine(&program, &inputs);
+        let mut outputs = vec![];
+        while !machine.halted() {
+            if machine.try_step().is_err() {
+                return false;
+            }
+            if !machine.halted() {
+                outputs.push(machine.last_output());
+            }
+        }
+        outputs == reference.outputs && machine.mem() == &reference.mem[..]
+    }
+
+    fn prop_matches_reference(program: Program, inputs: Vec<i64>) -> bool {
+        let mut machine = machine(&program, &inputs);
+        lockstep(&mut machine, INSTRUCTION_LIMIT).is_ok()
+    }
+}
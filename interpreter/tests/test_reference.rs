@@ -0,0 +1,103 @@
+use interpreter::{
+    lockstep, parse_ascii_program, Control, DivergenceKind, Error, Machine, Opcode,
+    ReferenceMachine,
+};
+
+#[test]
+fn test_reference_day9_quine() {
+    let quine = parse_ascii_program("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99");
+    let mut reference = ReferenceMachine::new(&quine);
+    let mut outputs = vec![];
+    while !reference.halted() {
+        if let Some(output) = reference.step().unwrap() {
+            outputs.push(output);
+        }
+    }
+    assert_eq!(outputs, quine);
+}
+
+#[test]
+fn test_reference_errors() {
+    let mut reference = ReferenceMachine::new(&[1101, 1, 1, -1, 99]);
+    assert_eq!(reference.step(), Err(Error::InvalidAddress { ip: 0 }));
+    assert_eq!(reference.ip(), 0);
+}
+
+#[test]
+fn test_reference_default_memory_limit() {
+    let mut reference = ReferenceMachine::new(&[1101, 1, 1, 1 << 24, 99]);
+    assert_eq!(reference.step(), Err(Error::InvalidAddress { ip: 0 }));
+    let mut reference = ReferenceMachine::new(&[1101, 1, 1, (1 << 24) - 1, 99]);
+    assert_eq!(reference.step(), Ok(None));
+    assert_eq!(reference.mem()[(1 << 24) - 1], 2);
+}
+
+#[test]
+fn test_lockstep_inputs() {
+    for input in 0..12 {
+        let mut machine = Machine::from_ascii_program(
+            "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+             1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,\
+             999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        );
+        machine.feed(input);
+        assert!(lockstep(&mut machine, 1000).is_ok());
+        assert!(machine.halted());
+    }
+}
+
+#[test]
+fn test_lockstep_puzzle_inputs() {
+    for &(code, input) in &[
+        (include_str!("../../aoc5/input.txt"), 5),
+        (include_str!("../../aoc9/input.txt"), 1),
+    ] {
+        let mut machine = Machine::from_ascii_program(code);
+        machine.feed(input);
+        let steps = lockstep(&mut machine, 1_000_000).unwrap();
+        assert!(steps > 0);
+        assert!(machine.halted());
+    }
+}
+
+#[test]
+fn test_lockstep_reports_errors() {
+    let mut machine = Machine::from_ascii_program("1101,1,1,5,42");
+    assert_eq!(lockstep(&mut machine, 10), Ok(1));
+}
+
+#[test]
+fn test_lockstep_error_precedence() {
+    // an invalid target is reported before the overflow
+    let mut machine = Machine::new(&[1102, 1 << 56, 128, 4096]);
+    machine.set_memory_limit(4096);
+    assert_eq!(lockstep(&mut machine, 10), Ok(0));
+    assert_eq!(
+        ReferenceMachine::new(&[1102, 1 << 56, 128, -1]).step(),
+        Err(Error::InvalidAddress { ip: 0 })
+    );
+}
+
+#[test]
+fn test_lockstep_reports_divergence() {
+    let mut machine = Machine::from_ascii_program("1101,2,3,7,104,7,99,0");
+    machine.register_opcode(Opcode::new(1, 3, &[3], |_, args| {
+        args[2] = args[0] * args[1];
+        Ok(Control::Next)
+    }));
+    let divergence = lockstep(&mut machine, 10).unwrap_err();
+    assert_eq!(divergence.step(), 0);
+    assert_eq!(divergence.ip(), 0);
+    assert_eq!(
+        *divergence.kind(),
+        DivergenceKind::Memory {
+            addr: 7,
+            expected: 5,
+            actual: 6
+        }
+    );
+    assert_eq!(
+        divergence.to_string(),
+        "step 0 at ip 0: mem[7] is 6, expected 5"
+    );
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "std")]
+
+use interpreter::{parallel_find_map, parallel_max_by_key, Candidate, Error, Machine, Search};
+
+// computes 3 * noun + verb into cell 0
+const NOUN_VERB: &str = "2,9,10,0,1,0,11,0,99,3,0,0";
+
+#[test]
+fn test_parallel_find_map_returns_earliest() {
+    for threads in 1..=4 {
+        let rv = parallel_find_map(
+            0..1000,
+            threads,
+            |x| if x % 7 == 3 { Some(x) } else { None },
+        );
+        assert_eq!(rv, Some(3));
+    }
+    assert_eq!(parallel_find_map(0..10, 4, |_| None::<i32>), None);
+}
+
+#[test]
+fn test_parallel_max_by_key() {
+    let rv = parallel_max_by_key(vec![3, -9, 7, -7, 1], 3, |x: &i32| x.abs());
+    assert_eq!(rv, Some((-9, 9)));
+    let rv = parallel_max_by_key(vec![2, -7, 7], 3, |x: &i32| x.abs());
+    assert_eq!(rv, Some((-7, 7)));
+}
+
+#[test]
+fn test_search_patches() {
+    let search = Search::new(Machine::from_ascii_program(NOUN_VERB));
+    let candidates =
+        (0..10).flat_map(|a| (0..10).map(move |b| Candidate::from_patches(&[(10, a), (11, b)])));
+    let run = search
+        .find(candidates, |run| run.machine().mem_get(0) == 23)
+        .unwrap();
+    assert_eq!(run.candidate().patches(), &[(10, 5), (11, 8)]);
+}
+
+#[test]
+fn test_search_inputs_max_score() {
+    let machine = Machine::from_ascii_program("3,0,3,1,2,0,1,2,4,2,99");
+    let mut search = Search::new(machine);
+    search.set_threads(2);
+    let candidates = (-3..=3).map(|x| Candidate::from_inputs(&[x, x + 1]));
+    let (run, score) = search
+        .max_by_score(candidates, |run| run.outputs().unwrap()[0])
+        .unwrap();
+    assert_eq!(score, 12);
+    assert_eq!(run.candidate().inputs(), &[3, 4]);
+}
+
+#[test]
+fn test_search_reports_errors() {
+    let search = Search::new(Machine::from_ascii_program("1,0,0,0,99"));
+    let run = search.run(Candidate::from_patches(&[(4, 42)]));
+    assert!(run.outputs().is_none());
+    assert_eq!(run.error().unwrap().ip(), 4);
+}
+
+#[test]
+fn test_search_step_budget() {
+    // loops forever unless the patched cell is non-zero
+    let mut search = Search::new(Machine::from_ascii_program("1006,7,0,104,1,99,0,0"));
+    search.set_step_budget(1000);
+    let candidates = (0..4).map(|x| Candidate::from_patches(&[(7, x)]));
+    let run = search
+        .find(candidates, |run| run.outputs().is_some())
+        .unwrap();
+    assert_eq!(run.candidate().patches(), &[(7, 1)]);
+    let run = search.run(Candidate::default());
+    assert_eq!(run.error(), Some(&Error::BudgetExceeded { ip: 0 }));
+    assert_eq!(run.machine().instruction_count(), 1000);
+}
+
+#[test]
+fn test_search_out_of_range_patch() {
+    let mut machine = Machine::from_ascii_program("104,1,99");
+    machine.set_memory_limit(16);
+    let search = Search::new(machine);
+    let candidates = vec![
+        Candidate::from_patches(&[(16, 0)]),
+        Candidate::from_patches(&[(15, 0)]),
+    ];
+    let runs: Vec<_> = candidates.into_iter().map(|x| search.run(x)).collect();
+    assert_eq!(runs[0].error(), Some(&Error::InvalidAddress { ip: 0 }));
+    assert_eq!(runs[1].outputs(), Some(&[1][..]));
+    let run = search
+        .find(vec![Candidate::from_patches(&[(usize::MAX, 0)])], |run| {
+            run.error().is_some()
+        })
+        .unwrap();
+    assert_eq!(run.error(), Some(&Error::InvalidAddress { ip: 0 }));
+}
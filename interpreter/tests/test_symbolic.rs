@@ -0,0 +1,214 @@
+use interpreter::{
+    assemble, parse_ascii_program, Constraint, Error, Expr, SymbolicError, SymbolicMachine,
+};
+
+fn day2_machine() -> SymbolicMachine {
+    let program = parse_ascii_program(include_str!("../../aoc2/input.txt"));
+    let mut machine = SymbolicMachine::new(&program);
+    machine.add_variable(1, "noun", 0..=99);
+    machine.add_variable(2, "verb", 0..=99);
+    machine
+}
+
+#[test]
+fn test_day2_formula() {
+    let paths = day2_machine().explore().unwrap();
+    assert_eq!(paths.len(), 1);
+    assert!(paths[0].halted());
+    assert_eq!(
+        paths[0].mem_get(0).to_string(),
+        "320000 * noun + verb + 490634"
+    );
+}
+
+#[test]
+fn test_day2_solve() {
+    let machine = day2_machine();
+    assert_eq!(
+        machine.solve_mem(0, 19_690_720).unwrap(),
+        Some(vec![60, 86])
+    );
+    assert_eq!(machine.solve_mem(0, 4_330_636).unwrap(), Some(vec![12, 2]));
+    assert_eq!(machine.solve_mem(0, 1).unwrap(), None);
+}
+
+#[test]
+fn test_nonlinear() {
+    let program = assemble(
+        "
+        mul x, x, t
+        add t, y, t
+        out t
+        hlt
+    x:  data 0
+    y:  data 0
+    t:  data 0
+        ",
+    )
+    .unwrap();
+    let mut machine = SymbolicMachine::new(&program);
+    machine.add_variable(11, "x", -10..=10);
+    machine.add_variable(12, "y", 0..=5);
+    let paths = machine.explore().unwrap();
+    assert_eq!(paths[0].outputs()[0].to_string(), "x * x + y");
+    assert_eq!(machine.solve_output(0, 30).unwrap(), Some(vec![-5, 5]));
+}
+
+#[test]
+fn test_forking() {
+    let program = assemble(
+        "
+        lt x, #5, flag
+        jt flag, #small
+        mul x, #2, t
+        out t
+        hlt
+    small:
+        out #1
+        hlt
+    x:  data 0
+    flag:
+        data 0
+    t:  data 0
+        ",
+    )
+    .unwrap();
+    let mut machine = SymbolicMachine::new(&program);
+    machine.add_variable(17, "x", 0..=10);
+    let paths = machine.explore().unwrap();
+    assert_eq!(paths.len(), 2);
+    let small = paths
+        .iter()
+        .find(|x| x.constraints()[0] == Constraint::LessThan(var("x"), Expr::Const(5), true))
+        .unwrap();
+    assert_eq!(small.constraints()[0].to_string(), "x < 5");
+    assert_eq!(small.outputs(), &[Expr::Const(1)]);
+    let large = paths.iter().find(|x| !std::ptr::eq(*x, small)).unwrap();
+    assert_eq!(large.constraints()[0].to_string(), "x >= 5");
+    assert_eq!(large.outputs()[0].to_string(), "2 * x");
+
+    assert_eq!(machine.solve_output(0, 1).unwrap(), Some(vec![0]));
+    assert_eq!(machine.solve_output(0, 14).unwrap(), Some(vec![7]));
+    // 2 * 2 would be 4 but 2 takes the other branch
+    assert_eq!(machine.solve_output(0, 4).unwrap(), None);
+}
+
+#[test]
+fn test_symbolic_loads() {
+    // mem[0] = mem[a] + 1
+    let mut machine = SymbolicMachine::new(&[1001, 0, 1, 0, 99, 10, 20, 30]);
+    machine.add_variable(1, "a", 5..=7);
+    let paths = machine.explore().unwrap();
+    assert_eq!(paths[0].mem_get(0).to_string(), "(mem[a] + 1)");
+    assert_eq!(machine.solve_mem(0, 31).unwrap(), Some(vec![7]));
+}
+
+#[test]
+fn test_symbolic_write() {
+    let mut machine = SymbolicMachine::new(&[1101, 1, 1, 0, 99]);
+    machine.add_variable(3, "a", 0..=3);
+    let paths = machine.explore().unwrap();
+    assert_eq!(
+        paths[0].error(),
+        Some(&SymbolicError::SymbolicAddress { ip: 0 })
+    );
+}
+
+#[test]
+fn test_verification_step_limit() {
+    let program = assemble(
+        "
+        lt x, #0, t
+        jt t, #negative
+        mul x, x, t
+        out t
+        hlt
+    negative:
+        jt #1, #negative
+    x:  data 0
+    t:  data 0
+        ",
+    )
+    .unwrap();
+    let mut machine = SymbolicMachine::new(&program);
+    machine.add_variable(17, "x", -2..=5);
+    machine.set_max_steps(1000);
+    // -2 computes 4 as well but loops forever
+    assert_eq!(machine.solve_output(0, 4).unwrap(), Some(vec![2]));
+}
+
+#[test]
+fn test_verification_missing_input() {
+    let program = assemble(
+        "
+        lt x, #0, t
+        jt t, #negative
+        add x, #2, t
+        out t
+        hlt
+    negative:
+        in t
+        out t
+        hlt
+    x:  data 0
+    t:  data 0
+        ",
+    )
+    .unwrap();
+    let mut machine = SymbolicMachine::new(&program);
+    machine.add_variable(19, "x", -2..=5);
+    let paths = machine.explore().unwrap();
+    assert!(paths
+        .iter()
+        .any(|x| x.error() == Some(&SymbolicError::MissingInput { ip: 14 })));
+    // -2 only outputs 0 if there is input to read
+    assert_eq!(machine.solve_output(0, 0).unwrap(), None);
+    machine.feed(0);
+    assert_eq!(machine.solve_output(0, 0).unwrap(), Some(vec![-2]));
+}
+
+#[test]
+fn test_solve_constrained_variables() {
+    let program = assemble(
+        "
+        eq b, #5, t
+        jt t, #take
+        out #0
+        hlt
+    take:
+        out a
+        hlt
+    a:  data 0
+    b:  data 0
+    t:  data 0
+        ",
+    )
+    .unwrap();
+    let mut machine = SymbolicMachine::new(&program);
+    machine.add_variable(13, "a", 0..=9);
+    machine.add_variable(14, "b", 0..=9);
+    // b does not show up in the output but has to be 5 to produce it
+    assert_eq!(machine.solve_output(0, 7).unwrap(), Some(vec![7, 5]));
+}
+
+#[test]
+fn test_memory_limit() {
+    let machine = SymbolicMachine::new(&[1101, 1, 1, 1_000_000_000_000, 99]);
+    let paths = machine.explore().unwrap();
+    assert_eq!(
+        paths[0].error(),
+        Some(&SymbolicError::Machine(Error::InvalidAddress { ip: 0 }))
+    );
+
+    let mut machine = SymbolicMachine::new(&[1, 9, 9, 0, 99]);
+    machine.set_memory_limit(8);
+    machine.add_variable(9, "a", 0..=9);
+    assert_eq!(
+        machine.explore().unwrap_err(),
+        SymbolicError::Machine(Error::InvalidAddress { ip: 0 })
+    );
+}
+
+fn var(name: &str) -> Expr {
+    Expr::Var(0, name.into())
+}
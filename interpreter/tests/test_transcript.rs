@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+
+use interpreter::{intcode, Event, Machine, Patch, Transcript, TranscriptError};
+
+fn doubler() -> Machine {
+    Machine::new(intcode! {
+    start:
+        in value;
+        mul value, #2, value;
+        out value;
+        jt #1, #start;
+    value:
+        data 0;
+    })
+}
+
+fn recorded(inputs: &[i64]) -> Transcript {
+    let mut machine = doubler();
+    machine.start_recording();
+    for &input in inputs {
+        machine.feed(input);
+    }
+    machine.run_until_input_needed();
+    machine.take_transcript().unwrap()
+}
+
+#[test]
+fn test_record() {
+    let transcript = recorded(&[1, 5]);
+    assert_eq!(
+        transcript.events(),
+        &[
+            Event::Input { step: 0, value: 1 },
+            Event::Output { step: 2, value: 2 },
+            Event::Input { step: 4, value: 5 },
+            Event::Output { step: 6, value: 10 },
+        ]
+    );
+    assert_eq!(transcript.inputs(), vec![1, 5]);
+    assert_eq!(transcript.outputs(), vec![2, 10]);
+}
+
+#[test]
+fn test_no_recording_by_default() {
+    let mut machine = doubler();
+    machine.feed(1);
+    machine.run_until_input_needed();
+    assert!(machine.transcript().is_none());
+    assert!(machine.take_transcript().is_none());
+}
+
+#[test]
+fn test_text_roundtrip() {
+    let transcript = recorded(&[1, 5]);
+    let text = transcript.to_string();
+    assert_eq!(text, "in 0 1\nout 2 2\nin 4 5\nout 6 10\n");
+    assert_eq!(Transcript::parse(&text).unwrap(), transcript);
+    assert_eq!(
+        Transcript::<i64>::parse("# comment\n\nout 3 7 # trailing\n")
+            .unwrap()
+            .events(),
+        &[Event::Output { step: 3, value: 7 }]
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    for source in &["in 1", "in 1 2 3", "put 1 2", "in x 2", "out 1 y"] {
+        match Transcript::<i64>::parse(&format!("in 0 1\n{}", source)) {
+            Err(TranscriptError::Syntax { line: 2, .. }) => {}
+            rv => panic!("unexpected result for {:?}: {:?}", source, rv),
+        }
+    }
+}
+
+#[test]
+fn test_replay() {
+    let transcript = recorded(&[1, 5, -3]);
+    let mut machine = doubler();
+    transcript.replay(&mut machine).unwrap();
+    assert_eq!(machine.last_output(), -6);
+    assert!(machine.needs_input());
+}
+
+#[test]
+fn test_replay_mismatches() {
+    let replay = |source: &str| Transcript::parse(source).unwrap().replay(&mut doubler());
+    assert_eq!(
+        replay("in 0 1\nout 2 3\n"),
+        Err(TranscriptError::OutputMismatch {
+            step: 2,
+            expected: 3,
+            actual: 2,
+        })
+    );
+    assert_eq!(
+        replay("in 1 1\n"),
+        Err(TranscriptError::StepMismatch {
+            expected: 1,
+            actual: 0,
+        })
+    );
+    assert_eq!(
+        replay("in 0 1\nin 2 1\n"),
+        Err(TranscriptError::UnexpectedOutput { step: 2, value: 2 })
+    );
+    assert_eq!(
+        replay("in 0 1\nout 2 2\nout 3 2\n"),
+        Err(TranscriptError::UnexpectedInput { step: 4 })
+    );
+    assert_eq!(
+        replay("in 0 1\n"),
+        Err(TranscriptError::UnexpectedOutput { step: 2, value: 2 })
+    );
+}
+
+#[test]
+fn test_replay_incomplete() {
+    let transcript = Transcript::parse("out 0 1\nout 2 2\n").unwrap();
+    let mut machine = Machine::new(intcode! {
+        out #1;
+        hlt;
+    });
+    assert_eq!(
+        transcript.replay(&mut machine),
+        Err(TranscriptError::Incomplete { remaining: 1 })
+    );
+}
+
+/// Replays a checked-in golden transcript on a fresh machine.
+fn replay_golden(transcript: &Transcript, mut machine: Machine) -> Machine {
+    transcript.replay(&mut machine).unwrap();
+    machine
+}
+
+#[test]
+fn test_golden_aoc11() {
+    let golden = Transcript::parse(include_str!("golden/aoc11_part2.txt")).unwrap();
+    let program = include_str!("../../aoc11/input.txt");
+    assert!(replay_golden(&golden, Machine::from_ascii_program(program)).halted());
+
+    // the hull painting robot of part 2 has to reproduce the golden run,
+    // unpainted panels are white
+    let mut machine = Machine::from_ascii_program(program);
+    machine.start_recording();
+    let mut colors = BTreeMap::new();
+    let (mut pos, mut dir) = ((0i64, 0i64), (0i64, -1i64));
+    while !machine.halted() {
+        machine.feed(colors.get(&pos).copied().unwrap_or(1));
+        let outputs = machine.run_until_input_needed().outputs().to_vec();
+        for cmd in outputs.chunks(2) {
+            colors.insert(pos, cmd[0]);
+            dir = if cmd[1] == 0 {
+                (dir.1, -dir.0)
+            } else {
+                (-dir.1, dir.0)
+            };
+            pos = (pos.0 + dir.0, pos.1 + dir.1);
+        }
+    }
+    assert_eq!(machine.take_transcript().unwrap(), golden);
+
+    let image: Vec<String> = (0..6)
+        .map(|y| {
+            (1..40)
+                .map(|x| {
+                    if colors.get(&(x, y)) == Some(&1) {
+                        '*'
+                    } else {
+                        ' '
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        image,
+        [
+            "*  *  **  **** *  * *     **  ***  ****",
+            "*  * *  * *    *  * *    *  * *  * *   ",
+            "**** *  * ***  *  * *    *  * *  * *** ",
+            "*  * **** *    *  * *    **** ***  *   ",
+            "*  * *  * *    *  * *    *  * *    *   ",
+            "*  * *  * *     **  **** *  * *    ****",
+        ]
+    );
+}
+
+#[test]
+fn test_golden_aoc13() {
+    let golden = Transcript::parse(include_str!("golden/aoc13_part1.txt")).unwrap();
+    let program = include_str!("../../aoc13/input.txt");
+    assert!(replay_golden(&golden, Machine::from_ascii_program(program)).halted());
+    let blocks = golden.outputs().chunks(3).filter(|x| x[2] == 2).count();
+    assert_eq!(blocks, 355);
+}
+
+#[test]
+fn test_golden_aoc13_free_play() {
+    // the first moves of a perfect game, the full run is too large to keep
+    let golden = Transcript::parse(include_str!("golden/aoc13_part2.txt")).unwrap();
+    assert_eq!(golden.inputs().len(), 299);
+    let program = include_str!("../../aoc13/input.txt");
+    let patch = Patch::parse(include_str!("../../aoc13/free_play.patch")).unwrap();
+    let mut machine = Machine::from_ascii_program(program);
+    patch.apply(&mut machine).unwrap();
+    assert!(replay_golden(&golden, machine.clone()).needs_input());
+
+    // moves the paddle towards the ball until all blocks are gone, the
+    // game has to start out like the golden run
+    machine.start_recording();
+    let (mut paddle_x, mut ball_x, mut score) = (0, 0, 0);
+    loop {
+        for cmd in machine.run_until_input_needed().outputs().chunks(3) {
+            match (cmd[0], cmd[2]) {
+                (-1, value) => score = value,
+                (x, 3) => paddle_x = x,
+                (x, 4) => ball_x = x,
+                _ => {}
+            }
+        }
+        if machine.halted() {
+            break;
+        }
+        machine.feed((ball_x - paddle_x).signum());
+    }
+    assert_eq!(score, 18371);
+    let transcript = machine.take_transcript().unwrap();
+    assert_eq!(
+        &transcript.events()[..golden.events().len()],
+        golden.events()
+    );
+}